@@ -0,0 +1,96 @@
+//! callback time with bank a alone sounding and with both banks sounding, bank b rendered after
+//! bank a or on its worker, dry and through filter, delay and crush. run with `cargo bench`
+
+use pc_computer_doctor::{audio, AudioHandler, Bank, BankCmd, Cmd, PAD_COUNT};
+use std::time::{Duration, Instant};
+
+const RATE: u32 = 44100;
+const FRAMES: usize = 256;
+const CHANNELS: usize = 2;
+const TICKS: usize = 4000;
+
+/// 16 bit mono wav of a few seconds of noise
+fn image() -> Vec<u8> {
+    let mut seed = 1u32;
+    let pcm: Vec<u8> = (0..RATE * 4)
+        .flat_map(|_| {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            ((seed >> 16) as i16 / 4).to_le_bytes()
+        })
+        .collect();
+    let mut wav = b"RIFF".to_vec();
+    wav.extend((36 + pcm.len() as u32).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    wav.extend(16u32.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(RATE.to_le_bytes());
+    wav.extend((RATE * 2).to_le_bytes());
+    wav.extend(2u16.to_le_bytes());
+    wav.extend(16u16.to_le_bytes());
+    wav.extend(b"data");
+    wav.extend((pcm.len() as u32).to_le_bytes());
+    wav.extend(pcm);
+    wav
+}
+
+/// mean and worst callback time with onsets held on given banks, bank b rendered on worker if
+/// parallel, each bank through its effects if wet
+fn run(banks: &[Bank], parallel: bool, wet: bool) -> (Duration, Duration) {
+    let (mut tx, rx) = audio::cmd_queue::<PAD_COUNT>();
+    let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+    let mut handler = AudioHandler::<PAD_COUNT>::new(rx, tui_tx, None, RATE);
+    handler.assign_parallel(parallel);
+    let data: std::sync::Arc<[u8]> = image().into();
+    let wav = audio::Wav {
+        tempo: Some(120.),
+        steps: Some(16),
+        path: std::path::Path::new("bench.wav").into(),
+        len: data.len() as u64 - 44,
+        data: Some(data),
+        stream: None,
+    };
+    tx.send(Cmd::AssignTempo(120.)).unwrap();
+    for bank in banks {
+        let onset = audio::Onset { wav: wav.clone(), start: 0 };
+        tx.send(Cmd::Bank(*bank, BankCmd::AssignOnset(0, false, Box::new(onset)))).unwrap();
+        tx.send(Cmd::Bank(*bank, BankCmd::ForceEvent(audio::Event::Hold { index: 0, vel: 1. }))).unwrap();
+        if wet {
+            tx.send(Cmd::Bank(*bank, BankCmd::AssignCutoff(0.5))).unwrap();
+            tx.send(Cmd::Bank(*bank, BankCmd::AssignResonance(0.5))).unwrap();
+            tx.send(Cmd::Bank(*bank, BankCmd::AssignFeedback(0.5))).unwrap();
+            tx.send(Cmd::Bank(*bank, BankCmd::AssignWet(0.5))).unwrap();
+            tx.send(Cmd::Bank(*bank, BankCmd::AssignCrushDepth(8))).unwrap();
+            tx.send(Cmd::Bank(*bank, BankCmd::AssignCrushRate(4))).unwrap();
+        }
+    }
+    let mut buffer = vec![0f32; FRAMES * CHANNELS];
+    // warm up, applying commands
+    for _ in 0..100 {
+        handler.tick(&mut buffer, CHANNELS);
+    }
+    let mut total = Duration::ZERO;
+    let mut worst = Duration::ZERO;
+    for _ in 0..TICKS {
+        let now = Instant::now();
+        handler.tick(&mut buffer, CHANNELS);
+        let elapsed = now.elapsed();
+        total += elapsed;
+        worst = worst.max(elapsed);
+    }
+    (total / TICKS as u32, worst)
+}
+
+fn main() {
+    let budget = Duration::from_secs_f64(FRAMES as f64 / RATE as f64);
+    println!("{} ticks of {} frames, {:?} budget each", TICKS, FRAMES, budget);
+    for wet in [false, true] {
+        let fx = if wet { "fx" } else { "dry" };
+        let (mean, worst) = run(&[Bank::A], true, wet);
+        println!("{:>28}: mean {:?}, worst {:?}", format!("bank a, {}", fx), mean, worst);
+        for (name, parallel) in [("serial", false), ("parallel", true)] {
+            let (mean, worst) = run(&[Bank::A, Bank::B], parallel, wet);
+            println!("{:>28}: mean {:?}, worst {:?}", format!("banks a and b {}, {}", name, fx), mean, worst);
+        }
+    }
+}
@@ -1,35 +1,464 @@
 use super::{pads, Fraction};
 use std::{
-    collections::VecDeque, fs::File, io::{Seek, SeekFrom}, mem::MaybeUninit
+    collections::VecDeque, fs::File, io::{Cursor, Read, Seek, SeekFrom}, path::Path, sync::Arc
 };
 use color_eyre::Result;
 
+#[derive(Copy, Clone)]
+pub enum SampleFormat {
+    I16,
+    F32,
+}
+
+impl SampleFormat {
+    /// detect from wav fmt tag and bits per sample
+    pub fn detect(tag: u16, bits: u16) -> Result<Self, std::io::Error> {
+        match (tag, bits) {
+            (1, 16) => Ok(Self::I16),
+            (3, 32) => Ok(Self::F32),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("unsupported wav format: tag {}, {} bits", tag, bits),
+            )),
+        }
+    }
+
+    /// bytes per sample word
+    pub fn width(&self) -> usize {
+        match self {
+            Self::I16 => 2,
+            Self::F32 => 4,
+        }
+    }
+
+    /// mask aligning byte offsets to sample words
+    pub fn align(&self) -> u64 {
+        !(self.width() as u64 - 1)
+    }
+
+    /// decode one little-endian sample word to [-1, 1]
+    pub fn decode(&self, bytes: &[u8]) -> f32 {
+        match self {
+            Self::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32,
+            Self::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+pub struct Header {
+    pub format: SampleFormat,
+    /// sample rate in hz
+    pub rate: u32,
+    /// pcm offset in bytes
+    pub offset: u64,
+    /// pcm length in bytes
+    pub len: u64,
+}
+
+impl Header {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        if super::flac::is_flac(&path) {
+            return super::flac::header(path);
+        }
+        Self::parse(&mut open(path)?)
+    }
+
+    /// locate fmt and data chunks, leaving reader at start of pcm
+    pub fn parse(reader: &mut (impl Read + Seek)) -> Result<Self, std::io::Error> {
+        let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+        let mut riff = [0u8; 12];
+        reader.read_exact(&mut riff)?;
+        let rf64 = match &riff[0..4] {
+            b"RIFF" => false,
+            b"RF64" => true,
+            _ => return Err(invalid("missing riff header")),
+        };
+        if &riff[8..12] != b"WAVE" {
+            return Err(invalid("missing wave header"));
+        }
+        let mut format = None;
+        let mut rate = 0;
+        let mut ds64_len = None;
+        loop {
+            let mut chunk = [0u8; 8];
+            match reader.read_exact(&mut chunk) {
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Err(invalid("missing data chunk")),
+                v => v?,
+            }
+            let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+            // chunks are padded to even length
+            let pad = size as i64 & 1;
+            match &chunk[0..4] {
+                b"ds64" if rf64 => {
+                    if size < 16 {
+                        return Err(invalid("malformed ds64 chunk"));
+                    }
+                    let mut ds64 = [0u8; 16];
+                    reader.read_exact(&mut ds64)?;
+                    ds64_len = Some(u64::from_le_bytes(core::array::from_fn(|i| ds64[8 + i])));
+                    reader.seek(SeekFrom::Current(size as i64 - 16 + pad))?;
+                }
+                b"fmt " => {
+                    if size < 16 {
+                        return Err(invalid("malformed fmt chunk"));
+                    }
+                    let mut fmt = vec![0u8; size as usize];
+                    reader.read_exact(&mut fmt)?;
+                    let mut tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+                    rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                    let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+                    if tag == 0xfffe && size >= 26 {
+                        // extensible; tag leads sub-format guid
+                        tag = u16::from_le_bytes([fmt[24], fmt[25]]);
+                    }
+                    format = Some(SampleFormat::detect(tag, bits)?);
+                    reader.seek(SeekFrom::Current(pad))?;
+                }
+                b"data" => {
+                    let format = format.ok_or_else(|| invalid("missing fmt chunk"))?;
+                    let offset = reader.stream_position()?;
+                    let len = match ds64_len {
+                        Some(v) if size == u32::MAX as u64 => v,
+                        _ => size,
+                    };
+                    // clamp to truncated files
+                    let end = reader.seek(SeekFrom::End(0))?;
+                    let len = len.min(end - offset) & format.align();
+                    if len == 0 {
+                        return Err(invalid("empty data chunk"));
+                    }
+                    reader.seek(SeekFrom::Start(offset))?;
+                    return Ok(Self { format, rate, offset, len });
+                }
+                _ => {
+                    reader.seek(SeekFrom::Current(size as i64 + pad))?;
+                }
+            }
+        }
+    }
+}
+
+pub trait Pcm: Read + Seek {}
+
+impl<T: Read + Seek> Pcm for T {}
+
+/// flac or ogg decoded to in-memory wav, by extension; none for wav
+pub fn decode(path: impl AsRef<Path>) -> Option<Result<Vec<u8>, std::io::Error>> {
+    if super::flac::is_flac(&path) {
+        Some(super::flac::decode(path))
+    } else if super::ogg::is_ogg(&path) {
+        Some(super::ogg::decode(path))
+    } else {
+        None
+    }
+}
+
+/// wav file, or decoded wav in memory
+pub fn open(path: impl AsRef<Path>) -> Result<Box<dyn Pcm>, std::io::Error> {
+    match decode(&path) {
+        Some(data) => Ok(Box::new(Cursor::new(data?))),
+        None => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+/// peak magnitude of pcm decimated to bins
+pub fn peaks(path: impl AsRef<Path>, bins: usize) -> Result<Vec<f32>, std::io::Error> {
+    let mut file = open(path)?;
+    let header = Header::parse(&mut file)?;
+    let stride = header.format.width();
+    let words = header.len as usize / stride;
+    let bin_len = words.div_ceil(bins).max(1);
+    let mut reader = std::io::BufReader::new(file).take(header.len);
+    let mut bin = vec![0u8; bin_len * stride];
+    let mut peaks = Vec::with_capacity(bins);
+    while peaks.len() < bins {
+        let mut n = 0;
+        while n < bin.len() {
+            match reader.read(&mut bin[n..])? {
+                0 => break,
+                v => n += v,
+            }
+        }
+        if n < stride {
+            break;
+        }
+        peaks.push(bin[..n - n % stride]
+            .chunks_exact(stride)
+            .map(|v| header.format.decode(v).abs())
+            .fold(0., f32::max));
+    }
+    Ok(peaks)
+}
+
+/// streamed or preloaded wav file
+pub enum Source {
+    Stream(super::stream::Stream),
+    Memory(Cursor<Arc<[u8]>>),
+    /// fails every read, as a file gone mid play
+    #[cfg(test)]
+    Fail,
+}
+
+impl Read for Source {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Source::Stream(stream) => stream.read(buf),
+            Source::Memory(cursor) => cursor.read(buf),
+            #[cfg(test)]
+            Source::Fail => Err(std::io::Error::other("read failed")),
+        }
+    }
+}
+
+impl Seek for Source {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            Source::Stream(stream) => stream.seek(pos),
+            Source::Memory(cursor) => cursor.seek(pos),
+            #[cfg(test)]
+            Source::Fail => Err(std::io::Error::other("seek failed")),
+        }
+    }
+}
+
 pub struct Wav {
     pub tempo: Option<f32>,
     pub steps: Option<u16>,
-    pub file: File,
+    pub format: SampleFormat,
+    /// sample rate in hz
+    pub rate: u32,
+    pub file: Source,
+    /// pcm offset in bytes
+    pub offset: u64,
     pub len: u64,
 }
 
 impl Wav {
+    pub fn open(wav: &super::Wav) -> Result<Self, std::io::Error> {
+        let (file, Header { format, rate, offset, len }) = match (&wav.data, &wav.stream) {
+            (Some(data), _) => {
+                let mut cursor = Cursor::new(data.clone());
+                let header = Header::parse(&mut cursor)?;
+                (Source::Memory(cursor), header)
+            }
+            // opened and read around onsets on load, read ahead off audio thread
+            (None, Some(stream)) => (Source::Stream(stream.stream()), stream.header()),
+            (None, None) => {
+                return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "wav not loaded"));
+            }
+        };
+        Ok(Self {
+            tempo: wav.tempo,
+            // zero steps would make step offsets infinite
+            steps: wav.steps.filter(|v| *v > 0),
+            format,
+            rate,
+            file,
+            offset,
+            len,
+        })
+    }
+
+    /// fill buffer from current position, wrapping at end of pcm
+    pub fn read(&mut self, mut buffer: &mut [u8]) -> Result<(), std::io::Error> {
+        while !buffer.is_empty() {
+            let avail = (self.len.saturating_sub(self.pos()?) as usize).min(buffer.len());
+            let n = self.file.read(&mut buffer[..avail])?;
+            if n == 0 {
+                self.seek(0)?;
+            }
+            buffer = &mut buffer[n..];
+        }
+        Ok(())
+    }
+
+    /// decode words from `offset`, wrapping at end of pcm
+    pub fn read_words(&mut self, offset: i64, words: &mut [f32]) -> Result<(), std::io::Error> {
+        let stride = self.format.width();
+        let mut read = [0u8; 1024];
+        self.seek(offset)?;
+        for words in words.chunks_mut(read.len() / stride) {
+            let read = &mut read[..words.len() * stride];
+            self.read(read)?;
+            for (word, bytes) in words.iter_mut().zip(read.chunks_exact(stride)) {
+                *word = self.format.decode(bytes);
+            }
+        }
+        Ok(())
+    }
+
+    /// pcm bytes lasting `secs` at file's own rate, word-aligned
+    pub fn span(&self, secs: f32) -> u64 {
+        (secs * self.rate as f32 * self.format.width() as f32) as u64 & self.format.align()
+    }
+
     pub fn pos(&mut self) -> Result<u64, std::io::Error> {
-        Ok(self.file.stream_position()? - 44)
+        Ok(self.file.stream_position()?.saturating_sub(self.offset))
     }
 
     pub fn seek(&mut self, offset: i64) -> Result<(), std::io::Error> {
         self.file.seek(SeekFrom::Start(
-            44 + (offset.rem_euclid(self.len as i64) as u64),
+            self.offset + (offset.rem_euclid(self.len as i64) as u64),
         ))?;
         Ok(())
     }
 }
 
+/// words read into memory, played over output frames independent of buffer length
+#[derive(Default)]
+pub struct Grain {
+    /// decoded words from `lo` less leading guards through trailing guards
+    words: Vec<f32>,
+    /// first word read, relative to origin
+    lo: i64,
+    /// pcm offset of word 0
+    origin: i64,
+    /// read position in words relative to origin
+    phase: f32,
+    /// words advanced per output frame, negative reversed
+    step: f32,
+    /// output frames played
+    age: usize,
+    /// crossfade in at equal power rather than equal gain
+    power: bool,
+}
+
+impl Grain {
+    /// output frames played
+    pub fn age(&self) -> usize {
+        self.age
+    }
+
+    /// interpolated word at phase, then advance a frame
+    fn sample(&mut self, interp: super::Interp) -> f32 {
+        let (pre, post) = interp.guards();
+        let base = self.phase.floor();
+        // words index of first guard word of base
+        let index = base as i64 - self.lo;
+        let last = self.words.len() as i64 - 1;
+        let mut x = [0f32; 4];
+        for (j, v) in x.iter_mut().take(pre + post + 1).enumerate() {
+            *v = self.words[(index + j as i64).clamp(0, last) as usize];
+        }
+        let sample = interp.kernel(&x, self.phase - base);
+        self.phase += self.step;
+        self.age += 1;
+        sample
+    }
+}
+
 pub struct Onset {
     /// source onset index
     pub index: u8,
+    /// second of pad's onsets
+    pub alt: bool,
     pub pan: f32,
     pub wav: Wav,
     pub start: u64,
+    /// velocity gain
+    pub vel: f32,
+    /// humanized read offset in steps, drawn on first clock sync
+    pub jitter: Option<f32>,
+    /// grain playing, if any
+    pub grain: Option<Grain>,
+    /// grain crossfading out, kept for its buffer once faded
+    pub prev: Option<Grain>,
+    /// next grain starts at wav position rather than on schedule, at equal power if true
+    pub restart: Option<bool>,
+    /// envelope gain
+    pub env: f32,
+    /// envelope gain ramping toward
+    pub target: f32,
+}
+
+impl Onset {
+    /// move read position to `offset`, crossfading from playing grain; equal power for unrelated words
+    pub fn jump(&mut self, offset: i64, power: bool) -> Result<(), std::io::Error> {
+        self.wav.seek(offset)?;
+        self.restart = Some(power);
+        Ok(())
+    }
+
+    /// pcm offset of word sounding, or of next grain before any plays
+    pub fn pos(&mut self) -> Result<u64, std::io::Error> {
+        match self.grain.as_ref().filter(|_| self.restart.is_none()) {
+            Some(grain) => {
+                let offset = grain.origin + grain.phase.floor() as i64 * self.wav.format.width() as i64;
+                Ok(offset.rem_euclid(self.wav.len as i64) as u64)
+            }
+            None => self.wav.pos(),
+        }
+    }
+
+    /// read next grain of `rem` bytes at wav position into memory, with room to crossfade for `fade` frames
+    #[allow(clippy::too_many_arguments)]
+    pub fn launch(&mut self, interp: super::Interp, speed: f32, rem: usize, fade: usize, reverse: bool, frozen: bool) -> Result<(), std::io::Error> {
+        let power = self.restart.take().unwrap_or(frozen);
+        // recycle buffer of faded grain
+        let mut grain = self.prev.take().unwrap_or_default();
+        self.prev = self.grain.take();
+        let stride = self.wav.format.width() as i64;
+        let (pre, post) = interp.guards();
+        let rem_words = rem as i64 / stride;
+        let fade_words = (fade as f32 * speed).ceil() as i64 + 1;
+        let origin = self.wav.pos()? as i64;
+        // reversed grains read down from below their last word
+        let (lo, hi) = if reverse {
+            (-fade_words, rem_words)
+        } else {
+            (0, rem_words + fade_words)
+        };
+        grain.words.resize((hi - lo) as usize + pre + post, 0.);
+        self.wav.read_words(origin + (lo - pre as i64) * stride, &mut grain.words)?;
+        grain.lo = lo;
+        grain.origin = origin;
+        grain.phase = if reverse { rem_words as f32 - 1. } else { 0. };
+        grain.step = if reverse { -speed } else { speed };
+        grain.age = 0;
+        grain.power = power;
+        self.grain = Some(grain);
+        let next = if frozen {
+            origin
+        } else if reverse {
+            origin - rem as i64
+        } else {
+            origin + rem as i64
+        };
+        self.wav.seek(next)
+    }
+
+    /// next word of playing grains, crossfading outgoing into incoming over `fade` frames
+    pub fn sample(&mut self, interp: super::Interp, fade: usize) -> f32 {
+        let Some(grain) = self.grain.as_mut() else {
+            return 0.;
+        };
+        let age = grain.age;
+        let power = grain.power;
+        let sample = grain.sample(interp);
+        match self.prev.as_mut() {
+            Some(prev) if age < fade => {
+                let t = (age as f32 + 0.5) / fade as f32;
+                let (gain_in, gain_out) = if power {
+                    let t = t * std::f32::consts::FRAC_PI_2;
+                    (t.sin(), t.cos())
+                } else {
+                    (t, 1. - t)
+                };
+                sample * gain_in + prev.sample(interp) * gain_out
+            }
+            _ => sample,
+        }
+    }
+
+    /// advance envelope by one output frame
+    pub fn tick_env(&mut self) -> f32 {
+        self.env = if self.target > self.env {
+            (self.env + 1. / super::ATTACK_LEN as f32).min(self.target)
+        } else {
+            (self.env - 1. / super::RELEASE_LEN as f32).max(self.target)
+        };
+        self.env
+    }
 }
 
 pub enum Event {
@@ -41,34 +470,39 @@ pub enum Event {
 impl Event {
     pub fn trans<const N: usize>(&mut self, input: &super::Event, step: u16, bias: f32, pads: &pads::Kit<N>) -> Result<()> {
         match input {
-            super::Event::Sync => {
-                *self = Event::Sync;
+            super::Event::Sync => match self {
+                Event::Hold(onset, ..) | Event::Loop(onset, ..) if onset.env > 0. => {
+                    // release before sync
+                    onset.target = 0.;
+                }
+                _ => *self = Event::Sync,
             }
-            super::Event::Hold { index } => {
-                if let Event::Loop(onset, ..) = self {
+            super::Event::Hold { index, vel } => {
+                if let Event::Loop(..) = self {
                     // recast event variant with same Onset
-                    let uninit: &mut MaybeUninit<Onset> = unsafe { std::mem::transmute(onset) };
-                    let mut onset = unsafe { std::mem::replace(uninit, MaybeUninit::uninit()).assume_init() };
-                    // i don't know either, girl
-                    onset.wav.file = onset.wav.file.try_clone()?;
-                    *self = Event::Hold(onset, step);
+                    if let Event::Loop(mut onset, ..) = std::mem::replace(self, Event::Sync) {
+                        onset.target = 1.;
+                        onset.vel = *vel;
+                        *self = Event::Hold(onset, step);
+                    }
                 } else if let Some(alt) = pads.generate_alt(*index, bias) {
-                    let onset = pads.onset_seek(*index, alt, pads::Kit::<N>::generate_pan(*index))?;
+                    let onset = pads.onset_seek(*index, alt, pads::Kit::<N>::generate_pan(*index), *vel)?;
                     *self = Event::Hold(onset, step);
                 }
             }
-            super::Event::Loop { index, len } => {
+            super::Event::Loop { index, len, vel } => {
                 match self {
                     Event::Hold(onset, step) | Event::Loop(onset, step, ..) if onset.index == *index => {
                         // recast event variant with same Onset
-                        let uninit: &mut MaybeUninit<Onset> = unsafe { std::mem::transmute(onset) };
-                        let mut onset = unsafe { std::mem::replace(uninit, MaybeUninit::uninit()).assume_init() };
-                        // i don't know either, girl
-                        onset.wav.file = onset.wav.file.try_clone()?;
-                        *self = Event::Loop(onset, *step, *len);
+                        let step = *step;
+                        if let Event::Hold(mut onset, ..) | Event::Loop(mut onset, ..) = std::mem::replace(self, Event::Sync) {
+                            onset.target = 1.;
+                            onset.vel = *vel;
+                            *self = Event::Loop(onset, step, *len);
+                        }
                     }
                     _ => if let Some(alt) = pads.generate_alt(*index, bias) {
-                        let onset = pads.onset(*index, alt, pads::Kit::<N>::generate_pan(*index))?;
+                        let onset = pads.onset(*index, alt, pads::Kit::<N>::generate_pan(*index), *vel)?;
                         *self = Event::Loop(onset, step, *len);
                     }
                 }
@@ -78,6 +512,16 @@ impl Event {
     }
 }
 
+impl Event {
+    /// releasing onset, if any
+    pub fn release(&self) -> Option<&Onset> {
+        match self {
+            Event::Hold(onset, ..) | Event::Loop(onset, ..) if onset.target == 0. => Some(onset),
+            _ => None,
+        }
+    }
+}
+
 pub struct Input {
     pub active: Event,
     pub buffer: Option<super::Event>,
@@ -89,6 +533,12 @@ impl Input {
     }
 }
 
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct Phrase {
     /// next event index (sans drift)
     pub next: usize,
@@ -98,6 +548,8 @@ pub struct Phrase {
     pub phrase_rem: u16,
     /// active event (last consumed)
     pub active: Event,
+    /// passes completed, for conditional events
+    pub pass: u32,
 }
 
 pub struct Record {
@@ -109,6 +561,8 @@ pub struct Record {
     pub phrase: Option<super::Phrase>,
     /// active phrase, if any
     pub active: Option<Phrase>,
+    /// step capture resumes on, while counting in
+    pub start: Option<u16>,
 }
 
 impl Record {
@@ -118,21 +572,41 @@ impl Record {
             buffer: Vec::new(),
             phrase: None,
             active: None,
+            start: None,
+        }
+    }
+
+    /// drop captured events, ignoring new ones until step
+    pub fn arm(&mut self, start: u16) {
+        self.events.clear();
+        self.start = Some(start);
+    }
+
+    /// drop events stamped before clock moved from step `from` to `to`, keeping steps left of count-in
+    pub fn seek(&mut self, from: u16, to: u16) {
+        self.events.clear();
+        if let Some(start) = self.start.as_mut() {
+            *start = to.saturating_add(start.saturating_sub(from));
         }
     }
 
-    pub fn push(&mut self, event: super::Event, step: u16) {
+    pub fn push(&mut self, event: super::Event, step: u16, prob: f32) {
+        if self.start.is_some_and(|v| step < v) {
+            return;
+        }
         // remove steps beyond max phrase len
-        while self.events.front().is_some_and(|v| step - v.step > super::MAX_PHRASE_LEN) {
+        while self.events.front().is_some_and(|v| step.saturating_sub(v.step) > super::MAX_PHRASE_LEN) {
             self.events.pop_front();
         }
-        self.events.push_back(super::Stamped { event, step });
+        self.events.push_back(super::Stamped { event, step, prob, condition: None });
     }
 
     pub fn bake(&mut self, step: u16) {
         self.buffer = self.events.iter().flat_map(|v| Some(super::Stamped {
             event: v.event.clone(),
             step: (v.step + super::MAX_PHRASE_LEN).checked_sub(step)?,
+            prob: v.prob,
+            condition: v.condition,
         })).collect::<Vec<_>>();
     }
 
@@ -140,13 +614,15 @@ impl Record {
         let events = self.buffer.iter().flat_map(|v| Some(super::Stamped {
             event: v.event.clone(),
             step: (v.step + len).checked_sub(super::MAX_PHRASE_LEN)?,
+            prob: v.prob,
+            condition: v.condition,
         })).collect::<Vec<_>>();
         self.phrase = Some(super::Phrase { events, len });
     }
 
-    pub fn generate_phrase<const N: usize>(&mut self, step: u16, bias: f32, drift: f32, pads: &pads::Kit<N>) -> Result<()> {
+    pub fn generate_phrase<const N: usize>(&mut self, step: u16, bias: f32, drift: f32, reverse: bool, pads: &pads::Kit<N>) -> Result<()> {
         if let Some(phrase) = self.phrase.as_mut() {
-            if let Some(phrase) = phrase.generate_active(&mut self.active, step, bias, drift, pads)? {
+            if let Some(phrase) = phrase.generate_active(&mut self.active, step, bias, drift, reverse, pads)? {
                 self.active = Some(phrase);
             }
         }
@@ -159,6 +635,85 @@ impl Record {
     }
 }
 
+impl Default for Record {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// pool and phrase params played for some passes of a song
+#[derive(Clone)]
+pub struct Section {
+    pub phrases: Vec<u8>,
+    pub order: super::PoolOrder,
+    /// passes through phrases before next section
+    pub repeats: u16,
+    pub drift: f32,
+    pub phrase_drift: f32,
+    pub bias: f32,
+    pub prob: f32,
+}
+
+/// sections played in turn, looping
+pub struct Song {
+    pub sections: Vec<Section>,
+    /// playing section, if started
+    pub index: Option<usize>,
+    /// pool phrases completed in section
+    played: u32,
+}
+
+impl Song {
+    pub fn new() -> Self {
+        Self {
+            sections: Vec::new(),
+            index: None,
+            played: 0,
+        }
+    }
+
+    /// first section to enter, if any
+    pub fn start(&mut self) -> Option<Section> {
+        let section = self.sections.first()?.clone();
+        self.index = Some(0);
+        self.played = 0;
+        Some(section)
+    }
+
+    pub fn stop(&mut self) {
+        self.index = None;
+        self.played = 0;
+    }
+
+    /// count completed pool phrase; next section to enter once repeats done
+    pub fn advance(&mut self) -> Option<Section> {
+        let index = self.index?;
+        let section = &self.sections[index];
+        self.played += 1;
+        if self.played < section.phrases.len().max(1) as u32 * section.repeats.max(1) as u32 {
+            return None;
+        }
+        self.played = 0;
+        let index = (index + 1) % self.sections.len();
+        self.index = Some(index);
+        Some(self.sections[index].clone())
+    }
+
+    /// playing section and count, and its pass and repeats, if started
+    pub fn position(&self) -> Option<(usize, usize, u16, u16)> {
+        let index = self.index?;
+        let section = &self.sections[index];
+        let pass = self.played / section.phrases.len().max(1) as u32;
+        Some((index, self.sections.len(), pass as u16, section.repeats))
+    }
+}
+
+impl Default for Song {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct Pool {
     /// next phrase index (sans drift)
     pub next: usize,
@@ -168,6 +723,11 @@ pub struct Pool {
     pub index: Option<u8>,
     /// active phrase, if any
     pub active: Option<Phrase>,
+    pub order: super::PoolOrder,
+    /// phrase indices left this shuffle cycle
+    bag: Vec<usize>,
+    /// phrase count when bag filled
+    bag_len: usize,
 }
 
 impl Pool {
@@ -177,23 +737,44 @@ impl Pool {
             phrases: Vec::new(),
             index: None,
             active: None,
+            order: super::PoolOrder::default(),
+            bag: Vec::new(),
+            bag_len: 0,
+        }
+    }
+
+    /// next index into phrases by order
+    fn next_index(&mut self, phrase_drift: f32) -> usize {
+        let len = self.phrases.len();
+        match self.order {
+            super::PoolOrder::Sequential => {
+                let skip = rand::random_range(0..=((phrase_drift * len as f32 - 1.).round()) as usize);
+                (self.next + skip) % len
+            }
+            super::PoolOrder::Shuffle => {
+                // refill on exhaustion or when pool changed mid-cycle
+                if self.bag.is_empty() || self.bag_len != len {
+                    self.bag = (0..len).collect();
+                    rand::seq::SliceRandom::shuffle(self.bag.as_mut_slice(), &mut rand::rng());
+                    self.bag_len = len;
+                }
+                self.bag.pop().unwrap_or(0)
+            }
+            super::PoolOrder::Random => rand::random_range(0..len),
         }
     }
 
-    pub fn generate_phrase<const N: usize>(&mut self, step: u16, bias: f32, drift: f32, pads: &pads::Kit<N>) -> Result<()> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_phrase<const N: usize>(&mut self, step: u16, bias: f32, drift: f32, phrase_drift: f32, reverse: bool, pads: &pads::Kit<N>) -> Result<()> {
         if self.phrases.is_empty() {
             self.next = 0;
             self.active = None;
         } else {
-            let index = {
-                // FIXME: use independent phrase_drift instead of same "stamped_drift"?
-                let drift = rand::random_range(0..=((drift * self.phrases.len() as f32 - 1.).round()) as usize);
-                let index = (self.next + drift) % self.phrases.len();
-                self.phrases[index]
-            };
+            let next = self.next_index(phrase_drift);
+            let index = self.phrases[next];
             self.index = Some(index);
             if let Some(phrase) = &pads.inner[index as usize].phrase {
-                if let Some(phrase) = phrase.generate_active(&mut self.active, step, bias, drift, pads)? {
+                if let Some(phrase) = phrase.generate_active(&mut self.active, step, bias, drift, reverse, pads)? {
                     self.active = Some(phrase);
                 }
             }
@@ -202,3 +783,235 @@ impl Pool {
         Ok(())
     }
 }
+
+impl Default for Pool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(phrases: &[u8], repeats: u16) -> Section {
+        Section {
+            phrases: phrases.to_vec(),
+            order: super::super::PoolOrder::Sequential,
+            repeats,
+            drift: 0.,
+            phrase_drift: 0.,
+            bias: 0.,
+            prob: 1.,
+        }
+    }
+
+    #[test]
+    fn song_plays_sections_in_order_for_their_passes() {
+        let mut song = Song::new();
+        assert!(song.start().is_none());
+        song.sections = vec![section(&[0, 1], 1), section(&[2], 2), section(&[], 1)];
+        assert_eq!(song.start().unwrap().phrases, [0, 1]);
+        // phrases of each section entered once its passes complete
+        let mut entered = Vec::new();
+        for _ in 0..7 {
+            entered.push(song.advance().map(|v| v.phrases));
+        }
+        assert_eq!(entered, [None, Some(vec![2]), None, Some(vec![]), Some(vec![0, 1]), None, Some(vec![2])]);
+        assert_eq!(song.position(), Some((1, 3, 0, 2)));
+        song.advance();
+        assert_eq!(song.position(), Some((1, 3, 1, 2)));
+        song.stop();
+        assert!(song.position().is_none() && song.advance().is_none());
+    }
+
+    #[test]
+    fn shuffle_visits_each_index_once_per_cycle() {
+        let mut pool = Pool::new();
+        pool.order = super::super::PoolOrder::Shuffle;
+        pool.phrases = vec![3, 1, 4, 1, 5];
+        for _ in 0..20 {
+            let mut cycle = (0..5).map(|_| pool.next_index(0.)).collect::<Vec<_>>();
+            cycle.sort();
+            assert_eq!(cycle, [0, 1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn shuffle_refills_when_pool_changes() {
+        let mut pool = Pool::new();
+        pool.order = super::super::PoolOrder::Shuffle;
+        pool.phrases = vec![0; 5];
+        pool.next_index(0.);
+        // shrunk mid-cycle; fresh bag of new length
+        pool.phrases.truncate(2);
+        let mut cycle = (0..2).map(|_| pool.next_index(0.)).collect::<Vec<_>>();
+        cycle.sort();
+        assert_eq!(cycle, [0, 1]);
+    }
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut v = id.to_vec();
+        v.extend((body.len() as u32).to_le_bytes());
+        v.extend(body);
+        if body.len() % 2 == 1 {
+            v.push(0);
+        }
+        v
+    }
+
+    fn fmt(tag: u16, rate: u32, bits: u16) -> Vec<u8> {
+        let mut v = tag.to_le_bytes().to_vec();
+        v.extend(1u16.to_le_bytes());
+        v.extend(rate.to_le_bytes());
+        v.extend((rate * bits as u32 / 8).to_le_bytes());
+        v.extend((bits / 8).to_le_bytes());
+        v.extend(bits.to_le_bytes());
+        v
+    }
+
+    fn riff(magic: &[u8; 4], chunks: &[Vec<u8>]) -> Cursor<Vec<u8>> {
+        let body = chunks.concat();
+        let mut v = magic.to_vec();
+        v.extend((body.len() as u32 + 4).to_le_bytes());
+        v.extend(b"WAVE");
+        v.extend(body);
+        Cursor::new(v)
+    }
+
+    /// preloaded wav of `words` zeroed words, as assigned
+    fn image(tag: u16, rate: u32, bits: u16, words: usize) -> super::super::Wav {
+        let data = riff(b"RIFF", &[chunk(b"fmt ", &fmt(tag, rate, bits)), chunk(b"data", &vec![0; words * bits as usize / 8])]);
+        super::super::Wav {
+            tempo: None,
+            steps: None,
+            path: Path::new("memory.wav").into(),
+            len: 0,
+            data: Some(data.into_inner().into()),
+            stream: None,
+        }
+    }
+
+    /// preloaded wav of `words` zeroed words
+    fn memory(tag: u16, rate: u32, bits: u16, words: usize) -> Wav {
+        Wav::open(&image(tag, rate, bits, words)).unwrap()
+    }
+
+    /// index, pan, velocity and file position of event's onset
+    fn held(event: &mut Event) -> (u8, f32, f32, u64) {
+        let (Event::Hold(onset, ..) | Event::Loop(onset, ..)) = event else {
+            panic!("no onset held");
+        };
+        (onset.index, onset.pan, onset.vel, onset.wav.pos().unwrap())
+    }
+
+    #[test]
+    fn hold_and_loop_recast_keeping_onset() {
+        let mut kit = pads::Kit::<4>::new();
+        kit.inner[1].onsets[0] = Some(super::super::Onset { wav: image(1, 44100, 16, 4096), start: 64 });
+        let mut event = Event::Sync;
+        event.trans(&super::super::Event::Hold { index: 1, vel: 1. }, 3, 0., &kit).unwrap();
+        let pan = pads::Kit::<4>::generate_pan(1u8);
+        assert_eq!(held(&mut event), (1, pan, 1., 64));
+        // read position moved by playing
+        let (Event::Hold(onset, ..) | Event::Loop(onset, ..)) = &mut event else { unreachable!() };
+        onset.wav.seek(1000).unwrap();
+        // hold to loop keeps onset and the hold's step
+        event.trans(&super::super::Event::Loop { index: 1, len: Fraction::new(1, 2), vel: 0.5 }, 5, 0., &kit).unwrap();
+        assert!(matches!(event, Event::Loop(_, 3, len) if f32::from(len) == 0.5));
+        assert_eq!(held(&mut event), (1, pan, 0.5, 1000));
+        // loop to hold keeps onset, stepping from new step
+        event.trans(&super::super::Event::Hold { index: 1, vel: 0.75 }, 7, 0., &kit).unwrap();
+        assert!(matches!(event, Event::Hold(_, 7)));
+        assert_eq!(held(&mut event), (1, pan, 0.75, 1000));
+    }
+
+    #[test]
+    fn span_follows_file_rate_and_width() {
+        let slow = memory(1, 22050, 16, 8);
+        let fast = memory(1, 44100, 16, 8);
+        let wide = memory(3, 22050, 32, 8);
+        assert_eq!(slow.span(0.5), 22050);
+        assert_eq!(fast.span(0.5), 2 * slow.span(0.5));
+        assert_eq!(wide.span(0.5), 2 * slow.span(0.5));
+        // word-aligned
+        assert_eq!(slow.span(1.5 / 22050.), 2);
+        assert_eq!(wide.span(1.5 / 22050.), 4);
+    }
+
+    #[test]
+    fn float_and_int_wavs_round_trip_through_grain() {
+        let source = [0.5, -0.25, 0.125, -1., 0.75, 0., -0.5, 1.];
+        let int = source.iter().flat_map(|v| ((v * i16::MAX as f32) as i16).to_le_bytes()).collect::<Vec<_>>();
+        let float = source.iter().flat_map(|v: &f32| v.to_le_bytes()).collect::<Vec<_>>();
+        for (name, tag, bits, pcm) in [("int", 1, 16, int), ("float", 3, 32, float)] {
+            let path = std::env::temp_dir().join(format!("round-trip-{}-{}.wav", name, std::process::id()));
+            std::fs::write(&path, riff(b"RIFF", &[chunk(b"fmt ", &fmt(tag, 44100, bits)), chunk(b"data", &pcm)]).into_inner()).unwrap();
+            let mut wav = super::super::Wav {
+                tempo: None,
+                steps: None,
+                path: path.clone().into(),
+                len: Header::open(&path).unwrap().len,
+                data: None,
+                stream: None,
+            };
+            wav.preload(&[]).unwrap();
+            std::fs::remove_file(&path).ok();
+            let mut kit = pads::Kit::<1>::new();
+            kit.inner[0].onsets[0] = Some(super::super::Onset { wav, start: 0 });
+            let mut onset = kit.onset_seek(0u8, false, 0., 1.).unwrap();
+            // one grain over every word at file speed
+            let width = bits as usize / 8;
+            onset.launch(super::super::Interp::Linear, 1., source.len() * width, 0, false, false).unwrap();
+            let grain = (0..source.len()).map(|_| onset.sample(super::super::Interp::Linear, 0)).collect::<Vec<_>>();
+            for (v, expected) in grain.iter().zip(source) {
+                assert!((v - expected).abs() < 1e-4, "{name}: {grain:?} != {source:?}");
+            }
+            // next grain from pcm start again
+            assert_eq!(onset.wav.pos().unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn header_skips_chunks_before_data() {
+        let mut wav = riff(b"RIFF", &[
+            chunk(b"LIST", b"INFOISFT\x05\x00\x00\x00test\x00"),
+            chunk(b"fmt ", &fmt(1, 22050, 16)),
+            chunk(b"bext", &[0; 7]),
+            chunk(b"data", &[1, 0, 2, 0, 3, 0]),
+        ]);
+        let header = Header::parse(&mut wav).unwrap();
+        assert!(matches!(header.format, SampleFormat::I16));
+        assert_eq!(header.rate, 22050);
+        assert_eq!(header.len, 6);
+        assert_eq!(wav.position(), header.offset);
+        assert_eq!(&wav.get_ref()[header.offset as usize..], &[1, 0, 2, 0, 3, 0]);
+    }
+
+    #[test]
+    fn header_reads_rf64_length() {
+        let mut ds64 = 0u64.to_le_bytes().to_vec();
+        ds64.extend(8u64.to_le_bytes());
+        ds64.extend(0u64.to_le_bytes());
+        let mut data = b"data".to_vec();
+        data.extend(u32::MAX.to_le_bytes());
+        data.extend([0; 8]);
+        let mut wav = riff(b"RF64", &[chunk(b"ds64", &ds64), chunk(b"fmt ", &fmt(3, 48000, 32)), data]);
+        let header = Header::parse(&mut wav).unwrap();
+        assert!(matches!(header.format, SampleFormat::F32));
+        assert_eq!(header.len, 8);
+    }
+
+    #[test]
+    fn header_rejects_missing_data_and_short_files() {
+        let mut wav = riff(b"RIFF", &[chunk(b"fmt ", &fmt(1, 44100, 16))]);
+        assert_eq!(Header::parse(&mut wav).err().unwrap().to_string(), "missing data chunk");
+        let mut wav = riff(b"RIFF", &[chunk(b"data", &[0; 4])]);
+        assert_eq!(Header::parse(&mut wav).err().unwrap().to_string(), "missing fmt chunk");
+        for len in [0, 3, 12, 20] {
+            let mut wav = riff(b"RIFF", &[chunk(b"fmt ", &fmt(1, 44100, 16))]);
+            wav.get_mut().truncate(len);
+            assert!(Header::parse(&mut wav).is_err());
+        }
+    }
+}
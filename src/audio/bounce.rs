@@ -0,0 +1,175 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    sync::{atomic::{AtomicBool, AtomicU32, Ordering}, Arc},
+    thread::JoinHandle,
+};
+
+/// 16-bit pcm wav writer thread, started and joined off audio callback
+pub struct Bounce {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<(), std::io::Error>>>,
+}
+
+/// audio callback's end of bounce, copying into preallocated ring without blocking
+pub struct Tap {
+    tx: rtrb::Producer<i16>,
+    format: Arc<Format>,
+}
+
+/// stream format as last pushed, for header written on stop
+#[derive(Default)]
+struct Format {
+    sample_rate: AtomicU32,
+    channels: AtomicU32,
+}
+
+impl Bounce {
+    /// spawn writer thread over file, returning tap to hand audio callback
+    pub fn new(file: File) -> (Self, Tap) {
+        let (tx, rx) = rtrb::RingBuffer::new(super::BOUNCE_RING);
+        let format = Arc::new(Format::default());
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let format = format.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || write(file, rx, &format, &stop))
+        };
+        (Self { stop, handle: Some(handle) }, Tap { tx, format })
+    }
+
+    /// stop writer once tap dropped or BOUNCE_WAIT passed, waiting on header
+    pub fn finish(mut self) -> Result<(), std::io::Error> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<(), std::io::Error> {
+        self.stop.store(true, Ordering::Relaxed);
+        match self.handle.take() {
+            Some(handle) => handle.join().unwrap_or_else(|_| Err(std::io::Error::other("bounce writer panicked"))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Bounce {
+    fn drop(&mut self) {
+        self.join().ok();
+    }
+}
+
+impl Tap {
+    /// convert and copy mixed samples into ring; frames not fitting are dropped
+    pub fn push(&mut self, mix: &[f32], sample_rate: u32, channels: usize) {
+        self.format.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.format.channels.store(channels as u32, Ordering::Relaxed);
+        let len = mix.len().min(self.tx.slots()) / channels * channels;
+        if let Ok(chunk) = self.tx.write_chunk_uninit(len) {
+            chunk.fill_from_iter(mix.iter().map(|v| (v.clamp(-1., 1.) * i16::MAX as f32) as i16));
+        }
+    }
+}
+
+fn write(file: File, mut rx: rtrb::Consumer<i16>, format: &Format, stop: &AtomicBool) -> Result<(), std::io::Error> {
+    let mut writer = BufWriter::new(file);
+    // sizes and format patched on stop
+    header(&mut writer, 0, 0, 0)?;
+    let mut len = 0u32;
+    let mut deadline = None;
+    loop {
+        // checked before draining, so words pushed before tap dropped are written
+        let abandoned = rx.is_abandoned();
+        if let Ok(chunk) = rx.read_chunk(rx.slots()) {
+            let (a, b) = chunk.as_slices();
+            for word in a.iter().chain(b) {
+                writer.write_all(&word.to_le_bytes())?;
+            }
+            len = len.saturating_add(2 * chunk.len() as u32);
+            chunk.commit_all();
+        }
+        // outlive tap, so ring freed here rather than on audio callback; unless callback stalled
+        if abandoned || stop.load(Ordering::Relaxed) && *deadline.get_or_insert_with(|| std::time::Instant::now() + super::BOUNCE_WAIT) <= std::time::Instant::now() {
+            break;
+        }
+        std::thread::sleep(super::STREAM_POLL);
+    }
+    writer.seek(SeekFrom::Start(0))?;
+    header(&mut writer, format.sample_rate.load(Ordering::Relaxed), format.channels.load(Ordering::Relaxed) as u16, len)?;
+    writer.flush()
+}
+
+/// length of canonical pcm header
+pub const HEADER_LEN: usize = 44;
+
+/// in-memory mono wav of 16 bit pcm
+pub(crate) fn image(sample_rate: u32, pcm: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    if pcm.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "empty decoded stream"));
+    }
+    let mut wav = Vec::with_capacity(HEADER_LEN + pcm.len());
+    header(&mut wav, sample_rate, 1, pcm.len() as u32)?;
+    wav.extend_from_slice(pcm);
+    Ok(wav)
+}
+
+/// canonical 44-byte pcm header for data of len bytes
+fn header(writer: &mut impl Write, sample_rate: u32, channels: u16, len: u32) -> Result<(), std::io::Error> {
+    let align = channels * 2;
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&len.saturating_add(36).to_le_bytes())?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * align as u32).to_le_bytes())?;
+    writer.write_all(&align.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&len.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("bounce-{}-{}.wav", name, std::process::id()))
+    }
+
+    #[test]
+    fn tap_frames_written_once_finished() {
+        let path = path("frames");
+        let (bounce, mut tap) = Bounce::new(File::create(&path).unwrap());
+        // tap pushed and dropped on its own thread, as by callback
+        std::thread::spawn(move || {
+            for _ in 0..4 {
+                tap.push(&[0.5, -0.5, 2., -2.], 48000, 2);
+            }
+        }).join().unwrap();
+        bounce.finish().unwrap();
+        let header = super::super::active::Header::open(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(header.rate, 48000);
+        assert_eq!(u16::from_le_bytes([bytes[22], bytes[23]]), 2);
+        assert_eq!(header.len, 4 * 4 * 2);
+        let words = bytes[HEADER_LEN..].chunks_exact(2).map(|v| i16::from_le_bytes([v[0], v[1]])).collect::<Vec<_>>();
+        // clipped into range
+        assert_eq!(&words[..4], [i16::MAX / 2, -i16::MAX / 2, i16::MAX, -i16::MAX]);
+        assert_eq!(words.len(), 16);
+    }
+
+    #[test]
+    fn finish_returns_while_tap_held() {
+        let path = path("held");
+        let (bounce, mut tap) = Bounce::new(File::create(&path).unwrap());
+        tap.push(&[0.; 8], 44100, 1);
+        // callback stalled with tap; writer gives up after BOUNCE_WAIT
+        bounce.finish().unwrap();
+        let header = super::super::active::Header::open(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(header.len, 16);
+        drop(tap);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::tui;
+use std::{
+    sync::mpsc::Sender,
+    thread::Thread,
+    time::Instant,
+};
+use midly::live::{LiveEvent, SystemRealtime};
+
+/// midi realtime messages sent from own thread, each at its due time rather than
+/// in bursts as callbacks render them
+pub struct ClockOut {
+    tx: rtrb::Producer<(Instant, SystemRealtime)>,
+    thread: Thread,
+}
+
+impl ClockOut {
+    /// spawn sender thread over `send`, which returns false on failure
+    pub fn new(mut send: impl FnMut(&[u8]) -> bool + Send + 'static, tui_tx: Sender<tui::Cmd>) -> Self {
+        let (tx, mut rx) = rtrb::RingBuffer::<(Instant, SystemRealtime)>::new(super::CLOCK_QUEUE);
+        let handle = std::thread::spawn(move || loop {
+            match rx.peek() {
+                Ok(&(due, message)) => {
+                    let now = Instant::now();
+                    if due > now {
+                        // wakes may be early; recheck
+                        std::thread::park_timeout(due - now);
+                        continue;
+                    }
+                    rx.pop().ok();
+                    let mut bytes = Vec::new();
+                    LiveEvent::Realtime(message).write_std(&mut bytes).ok();
+                    if !send(&bytes) {
+                        // tui may have exited before audio
+                        tui_tx.send(tui::Cmd::Error("failed to send midi clock".into())).ok();
+                    }
+                }
+                Err(_) if rx.is_abandoned() => return,
+                Err(_) => std::thread::park(),
+            }
+        });
+        Self {
+            tx,
+            thread: handle.thread().clone(),
+        }
+    }
+
+    /// queue message for `due` without blocking; dropped if queue full
+    pub fn send(&mut self, due: Instant, message: SystemRealtime) -> bool {
+        let sent = self.tx.push((due, message)).is_ok();
+        self.thread.unpark();
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn pulses_sent_at_due_times() {
+        let (sent_tx, sent_rx) = std::sync::mpsc::channel();
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let mut clock_out = ClockOut::new(move |bytes| sent_tx.send((Instant::now(), bytes.to_vec())).is_ok(), tui_tx);
+        // queued at once, as by one callback
+        let start = Instant::now();
+        let spacing = Duration::from_millis(20);
+        for i in 0..4 {
+            assert!(clock_out.send(start + spacing * i, SystemRealtime::TimingClock));
+        }
+        for i in 0..4 {
+            let (at, bytes) = sent_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+            assert_eq!(bytes, [0xf8]);
+            let due = start + spacing * i;
+            assert!(at >= due && at - due < Duration::from_millis(10), "pulse {} sent {:?} late", i, at.saturating_duration_since(due));
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use super::{active::{self, Header}, Rd};
+use std::{
+    fs::File, io::{BufReader, Read}, path::Path
+};
+
+/// scan wav pcm for transients as energy rises above mean rise
+pub fn scan(path: impl AsRef<Path>) -> Result<Rd, std::io::Error> {
+    let mut file = active::open(path)?;
+    let header = Header::parse(&mut file)?;
+    let stride = header.format.width();
+    let mut reader = BufReader::new(file).take(header.len);
+    // energy rise per hop
+    let mut rises = Vec::new();
+    let mut last = 0.;
+    let mut hop = vec![0u8; super::DETECT_HOP * stride];
+    loop {
+        let mut n = 0;
+        while n < hop.len() {
+            match reader.read(&mut hop[n..])? {
+                0 => break,
+                v => n += v,
+            }
+        }
+        let words = n / stride;
+        if words == 0 {
+            break;
+        }
+        let energy = hop[..words * stride]
+            .chunks_exact(stride)
+            .map(|v| header.format.decode(v).powi(2))
+            .sum::<f32>() / words as f32;
+        rises.push((energy - last).max(0.));
+        last = energy;
+    }
+    let mean = rises.iter().sum::<f32>() / rises.len().max(1) as f32;
+    let spacing = (header.rate as usize * super::DETECT_SPACING as usize / 1000).div_ceil(super::DETECT_HOP).max(1);
+    // peak pick, keeping strongest rise within spacing
+    let mut peaks: Vec<usize> = Vec::new();
+    for (i, &rise) in rises.iter().enumerate() {
+        if rise <= 0. || rise < mean * super::DETECT_THRESHOLD {
+            continue;
+        }
+        match peaks.last_mut() {
+            Some(last) if i - *last < spacing => if rise > rises[*last] {
+                *last = i;
+            }
+            _ => peaks.push(i),
+        }
+    }
+    Ok(Rd {
+        tempo: None,
+        steps: None,
+        onsets: peaks.into_iter().map(|v| (v * super::DETECT_HOP * stride) as u64).collect(),
+    })
+}
+
+/// scan wav and write detected onsets to .rd alongside it
+pub fn write(path: impl AsRef<Path>) -> color_eyre::Result<Rd> {
+    let path = path.as_ref();
+    let rd = scan(path)?;
+    let file = File::create_new(path.with_extension("rd"))?;
+    serde_json::to_writer(file, &rd)?;
+    Ok(rd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn click_train_marks_each_click_once() {
+        // click every quarter second, each echoed at half level 10ms later
+        let mut words = vec![0i16; 44100 * 2];
+        let clicks = (0..8).map(|i| 2000 + i * 11025).collect::<Vec<_>>();
+        for &click in &clicks {
+            for (echo, level) in [(0, 16000), (441, 8000)] {
+                for j in 0..64 {
+                    words[click + echo + j] = if j % 2 == 0 { level } else { -level };
+                }
+            }
+        }
+        let pcm = words.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>();
+        let path = std::env::temp_dir().join(format!("clicks-{}.wav", std::process::id()));
+        std::fs::write(&path, super::super::bounce::image(44100, &pcm).unwrap()).unwrap();
+        let rd = write(&path).unwrap();
+        // written alongside, never over an existing one
+        let written: Rd = serde_json::from_reader(File::open(path.with_extension("rd")).unwrap()).unwrap();
+        assert!(write(&path).is_err());
+        std::fs::remove_file(&path).ok();
+        std::fs::remove_file(path.with_extension("rd")).ok();
+        assert_eq!(written.onsets, rd.onsets);
+        assert!(rd.tempo.is_none() && rd.steps.is_none());
+        // within a hop of each click, in bytes
+        assert_eq!(rd.onsets.len(), clicks.len());
+        for (onset, click) in rd.onsets.iter().zip(clicks) {
+            assert!(onset.abs_diff(click as u64 * 2) <= (super::super::DETECT_HOP * 2) as u64, "{onset} far from {}", click * 2);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use super::active::Header;
+use std::{io::Cursor, path::Path};
+
+fn invalid(e: claxon::Error) -> std::io::Error {
+    match e {
+        claxon::Error::IoError(e) => e,
+        e => std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+/// by extension, ignoring case
+pub fn is_flac(path: impl AsRef<Path>) -> bool {
+    path.as_ref().extension().is_some_and(|v| v.eq_ignore_ascii_case("flac"))
+}
+
+/// decode to in-memory mono 16 bit wav, so pcm offsets address it as any wav
+pub fn decode(path: impl AsRef<Path>) -> Result<Vec<u8>, std::io::Error> {
+    let mut reader = claxon::FlacReader::open(path).map_err(invalid)?;
+    let info = reader.streaminfo();
+    let channels = info.channels as usize;
+    let shift = 32 - info.bits_per_sample;
+    let mut pcm = Vec::with_capacity(info.samples.unwrap_or_default() as usize * 2);
+    let mut frame = Vec::with_capacity(channels);
+    for sample in reader.samples() {
+        // scale to full i32 range before downmix
+        frame.push(i64::from(sample.map_err(invalid)? << shift));
+        if frame.len() == channels {
+            let word = ((frame.drain(..).sum::<i64>() / channels as i64) >> 16) as i16;
+            pcm.extend(word.to_le_bytes());
+        }
+    }
+    super::bounce::image(info.sample_rate, &pcm)
+}
+
+/// header of decoded wav from streaminfo, decoding only if length unknown
+pub fn header(path: impl AsRef<Path>) -> Result<Header, std::io::Error> {
+    let info = claxon::FlacReader::open(&path).map_err(invalid)?.streaminfo();
+    match info.samples {
+        Some(samples) if samples > 0 => Ok(Header {
+            format: super::active::SampleFormat::I16,
+            rate: info.sample_rate,
+            offset: super::bounce::HEADER_LEN as u64,
+            len: samples * 2,
+        }),
+        _ => Header::parse(&mut Cursor::new(decode(path)?)),
+    }
+}
+
+/// flac of 16 bit channels in verbatim frames, as test input
+#[cfg(test)]
+pub(super) fn image(rate: u32, channels: &[&[i16]]) -> Vec<u8> {
+    struct Bits(Vec<u8>, u32);
+    impl Bits {
+        fn put(&mut self, value: u64, len: u32) {
+            for i in (0..len).rev() {
+                if self.1.is_multiple_of(8) {
+                    self.0.push(0);
+                }
+                *self.0.last_mut().unwrap() |= (((value >> i) & 1) as u8) << (7 - self.1 % 8);
+                self.1 += 1;
+            }
+        }
+    }
+    fn crc(bytes: &[u8], poly: u16, width: u32) -> u16 {
+        let top = 1 << (width - 1);
+        let mask = ((1u32 << width) - 1) as u16;
+        bytes.iter().fold(0u16, |mut crc, v| {
+            crc ^= u16::from(*v) << (width - 8);
+            for _ in 0..8 {
+                crc = if crc & top != 0 { (crc << 1) ^ poly } else { crc << 1 } & mask;
+            }
+            crc
+        })
+    }
+    const BLOCK: usize = 1024;
+    let samples = channels[0].len();
+    let mut bits = Bits(b"fLaC".to_vec(), 32);
+    // last metadata block, streaminfo
+    bits.put(0x80, 8);
+    bits.put(34, 24);
+    bits.put(BLOCK as u64, 16);
+    bits.put(BLOCK as u64, 16);
+    // frame sizes unknown
+    bits.put(0, 48);
+    bits.put(u64::from(rate), 20);
+    bits.put(channels.len() as u64 - 1, 3);
+    bits.put(15, 5);
+    bits.put(samples as u64, 36);
+    // no md5
+    bits.put(0, 64);
+    bits.put(0, 64);
+    for (index, start) in (0..samples).step_by(BLOCK).enumerate() {
+        let len = BLOCK.min(samples - start);
+        let frame = bits.0.len();
+        bits.put(0b11111111111110, 14);
+        bits.put(0, 2);
+        // 16 bit block size at header end, rate from streaminfo
+        bits.put(0b0111, 4);
+        bits.put(0, 4);
+        bits.put(channels.len() as u64 - 1, 4);
+        bits.put(0b1000, 4);
+        bits.put(index as u64, 8);
+        bits.put(len as u64 - 1, 16);
+        let crc8 = crc(&bits.0[frame..], 0x07, 8);
+        bits.put(u64::from(crc8), 8);
+        for channel in channels {
+            // verbatim subframe
+            bits.put(0b00000010, 8);
+            for v in &channel[start..start + len] {
+                bits.put(u64::from(*v as u16), 16);
+            }
+        }
+        let crc16 = crc(&bits.0[frame..], 0x8005, 16);
+        bits.put(u64::from(crc16), 16);
+    }
+    bits.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_to_mono_wav_matching_header() {
+        let left: Vec<i16> = (0..3000).map(|i| (i * 7 % 2000) as i16 - 1000).collect();
+        let right: Vec<i16> = left.iter().map(|v| v / 2).collect();
+        let path = std::env::temp_dir().join(format!("decode-{}.flac", std::process::id()));
+        std::fs::write(&path, image(22050, &[&left, &right])).unwrap();
+        let wav = decode(&path).unwrap();
+        let parsed = Header::parse(&mut Cursor::new(&wav)).unwrap();
+        let header = header(&path).unwrap();
+        assert_eq!((parsed.rate, parsed.offset, parsed.len), (22050, header.offset, header.len));
+        assert_eq!(header.len, 6000);
+        let words: Vec<i16> = wav[parsed.offset as usize..].chunks_exact(2).map(|v| i16::from_le_bytes([v[0], v[1]])).collect();
+        for (i, word) in words.iter().enumerate() {
+            let mix = (i32::from(left[i]) + i32::from(right[i])) / 2;
+            assert!((i32::from(*word) - mix).abs() <= 1, "word {i}: {word} != {mix}");
+        }
+        std::fs::remove_file(path).ok();
+    }
+}
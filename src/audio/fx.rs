@@ -0,0 +1,546 @@
+use std::collections::VecDeque;
+
+/// zero subnormal feedback state, which otherwise stalls cpu as tails decay
+fn flush(v: f32) -> f32 {
+    if v.abs() < f32::MIN_POSITIVE { 0. } else { v }
+}
+
+pub struct Filter {
+    /// normalized cutoff, exponential over 20hz to 20khz
+    pub cutoff: f32,
+    /// normalized resonance
+    pub resonance: f32,
+    /// integrator states per channel
+    state: Vec<[f32; 2]>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self {
+            cutoff: 1.,
+            resonance: 0.,
+            state: Vec::new(),
+        }
+    }
+
+    /// low-pass interleaved buffer in place via state-variable filter
+    pub fn process(&mut self, buffer: &mut [f32], channels: usize, sample_rate: u32) {
+        if self.cutoff >= 1. {
+            // bypass fully open filter
+            self.state.clear();
+            return;
+        }
+        self.state.resize(channels, [0.; 2]);
+        let hz = (20. * 1000f32.powf(self.cutoff)).min(sample_rate as f32 * 0.49);
+        let g = (std::f32::consts::PI * hz / sample_rate as f32).tan();
+        let k = 2. - 1.95 * self.resonance;
+        let a1 = 1. / (1. + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        for frame in buffer.chunks_exact_mut(channels) {
+            for (v0, [ic1, ic2]) in frame.iter_mut().zip(self.state.iter_mut()) {
+                let v3 = *v0 - *ic2;
+                let v1 = a1 * *ic1 + a2 * v3;
+                let v2 = *ic2 + a2 * *ic1 + a3 * v3;
+                *ic1 = flush(2. * v1 - *ic1);
+                *ic2 = flush(2. * v2 - *ic2);
+                *v0 = flush(v2);
+            }
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Delay {
+    /// delay length in beats
+    pub beats: f32,
+    pub feedback: f32,
+    pub wet: f32,
+    /// interleaved delay line
+    line: VecDeque<f32>,
+}
+
+impl Delay {
+    pub fn new() -> Self {
+        Self {
+            beats: super::DELAY_BEAT.into(),
+            feedback: 0.,
+            wet: 0.,
+            line: VecDeque::new(),
+        }
+    }
+
+    /// mix tempo-synced echoes into interleaved buffer in place
+    pub fn process(&mut self, buffer: &mut [f32], channels: usize, sample_rate: u32, tempo: f32) {
+        if tempo <= 0. || self.wet <= 0. {
+            // flush when stopped or dry
+            self.line.clear();
+            return;
+        }
+        let len = ((60. / tempo * self.beats * sample_rate as f32) as usize).max(1) * channels;
+        self.line.resize(len, 0.);
+        for v in buffer.iter_mut() {
+            let tap = self.line.pop_front().unwrap_or(0.);
+            self.line.push_back(flush(*v + tap * self.feedback));
+            *v += tap * self.wet;
+        }
+    }
+}
+
+impl Default for Delay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Crush {
+    /// bit depth, 16 bypasses
+    pub depth: u8,
+    /// samples held per downsampled frame, 1 bypasses
+    pub rate: u8,
+    /// held frame per channel
+    held: Vec<f32>,
+    /// frames since last hold
+    count: u8,
+}
+
+impl Crush {
+    pub fn new() -> Self {
+        Self {
+            depth: 16,
+            rate: 1,
+            held: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// quantize and downsample interleaved buffer in place
+    pub fn process(&mut self, buffer: &mut [f32], channels: usize) {
+        if self.depth >= 16 && self.rate <= 1 {
+            return;
+        }
+        self.held.resize(channels, 0.);
+        let half = (1u32 << (self.depth.clamp(1, 16) - 1)) as f32;
+        for frame in buffer.chunks_exact_mut(channels) {
+            if self.count == 0 {
+                for (held, v) in self.held.iter_mut().zip(frame.iter()) {
+                    // mid-rise quantize to 2^depth levels, leaving full depth untouched
+                    *held = if self.depth >= 16 { *v } else { ((v * half).floor().clamp(-half, half - 1.) + 0.5) / half };
+                }
+            }
+            self.count = (self.count + 1) % self.rate.max(1);
+            frame.copy_from_slice(&self.held);
+        }
+    }
+}
+
+impl Default for Crush {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// lfo waveform
+#[derive(Copy, Clone, Default)]
+pub enum Shape {
+    #[default]
+    Sine,
+    Triangle,
+    Square,
+}
+
+/// lfo cycle rate
+#[derive(Copy, Clone)]
+pub enum Rate {
+    /// cycle length in steps, locked to clock
+    Synced(super::Fraction),
+    /// cycles per second, ignoring clock
+    Free(f32),
+}
+
+pub struct Lfo {
+    pub shape: Shape,
+    pub rate: Rate,
+    /// position within cycle
+    phase: f32,
+}
+
+impl Lfo {
+    pub fn new(rate: Rate) -> Self {
+        Self {
+            shape: Shape::Sine,
+            rate,
+            phase: 0.,
+        }
+    }
+
+    /// lock synced phase to clock, peaking on cycle start
+    pub fn sync(&mut self, clock: f32) {
+        if let Rate::Synced(period) = self.rate {
+            self.phase = (clock / f32::from(period)).rem_euclid(1.);
+        }
+    }
+
+    /// phase advance per frame
+    fn inc(&self, sample_rate: u32, tempo: f32) -> f32 {
+        match self.rate {
+            Rate::Synced(period) => {
+                let period = f32::from(period);
+                if period > 0. { tempo * super::STEP_DIV as f32 / (60. * sample_rate as f32 * period) } else { 0. }
+            }
+            Rate::Free(hz) => hz / sample_rate as f32,
+        }
+    }
+
+    /// unipolar value, 1 at peak, then advance one frame
+    fn tick(&mut self, inc: f32) -> f32 {
+        let v = match self.shape {
+            Shape::Sine => 0.5 + 0.5 * (self.phase * std::f32::consts::TAU).cos(),
+            Shape::Triangle => (1. - 2. * self.phase).abs(),
+            Shape::Square => if self.phase < 0.5 { 1. } else { 0. },
+        };
+        self.phase = (self.phase + inc).fract();
+        v
+    }
+}
+
+pub struct Tremolo {
+    /// gain dip at trough, 0 bypasses
+    pub depth: f32,
+    pub lfo: Lfo,
+}
+
+impl Tremolo {
+    pub fn new() -> Self {
+        Self {
+            depth: 0.,
+            lfo: Lfo::new(Rate::Synced(super::Fraction::new(4, 1))),
+        }
+    }
+
+    /// modulate interleaved buffer gain in place
+    pub fn process(&mut self, buffer: &mut [f32], channels: usize, sample_rate: u32, tempo: f32) {
+        if self.depth <= 0. {
+            return;
+        }
+        let inc = self.lfo.inc(sample_rate, tempo);
+        for frame in buffer.chunks_exact_mut(channels) {
+            let gain = 1. - self.depth * (1. - self.lfo.tick(inc));
+            frame.iter_mut().for_each(|v| *v *= gain);
+        }
+    }
+}
+
+impl Default for Tremolo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct AutoPan {
+    /// balance swing toward either side, 0 bypasses
+    pub depth: f32,
+    pub lfo: Lfo,
+}
+
+impl AutoPan {
+    pub fn new() -> Self {
+        Self {
+            depth: 0.,
+            lfo: Lfo::new(Rate::Synced(super::Fraction::new(8, 1))),
+        }
+    }
+
+    /// sweep balance of first two channels in place, after width spread
+    pub fn process(&mut self, buffer: &mut [f32], channels: usize, sample_rate: u32, tempo: f32) {
+        if self.depth <= 0. || channels < 2 {
+            return;
+        }
+        let inc = self.lfo.inc(sample_rate, tempo);
+        for frame in buffer.chunks_exact_mut(channels) {
+            // bipolar, right at peak
+            let pan = (self.lfo.tick(inc) * 2. - 1.) * self.depth;
+            frame[0] *= (1. - pan).min(1.);
+            frame[1] *= (1. + pan).min(1.);
+        }
+    }
+}
+
+impl Default for AutoPan {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// metronome blip, decaying sine pitched up on bar downbeats
+pub struct Click {
+    pub on: bool,
+    /// output gain
+    pub level: f32,
+    /// steps per bar
+    pub bar: u16,
+    hz: f32,
+    phase: f32,
+    env: f32,
+}
+
+impl Click {
+    pub fn new() -> Self {
+        Self {
+            on: false,
+            level: 0.5,
+            bar: super::CLICK_BAR,
+            hz: super::CLICK_HZ,
+            phase: 0.,
+            env: 0.,
+        }
+    }
+
+    /// start blip on step, accented on first of bar; forced even if off
+    pub fn trigger(&mut self, step: u32, force: bool) {
+        if !self.on && !force {
+            return;
+        }
+        self.hz = if step.is_multiple_of(self.bar.max(1) as u32) {
+            super::CLICK_ACCENT_HZ
+        } else {
+            super::CLICK_HZ
+        };
+        self.phase = 0.;
+        self.env = 1.;
+    }
+
+    /// add blip to all channels of interleaved buffer
+    pub fn process(&mut self, buffer: &mut [f32], channels: usize, sample_rate: u32) {
+        if self.env <= 0. {
+            return;
+        }
+        let inc = self.hz / sample_rate as f32;
+        let dec = 1. / (super::CLICK_LEN * sample_rate as f32);
+        for frame in buffer.chunks_exact_mut(channels) {
+            let v = (self.phase * std::f32::consts::TAU).sin() * self.env * self.env * self.level;
+            frame.iter_mut().for_each(|s| *s += v);
+            self.phase = (self.phase + inc).fract();
+            self.env = (self.env - dec).max(0.);
+        }
+    }
+}
+
+impl Default for Click {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Duck {
+    /// gain dip on trigger, 0 bypasses
+    pub depth: f32,
+    /// recovery time in seconds
+    pub release: f32,
+    /// remaining dip, decaying from 1
+    env: f32,
+}
+
+impl Duck {
+    pub fn new() -> Self {
+        Self {
+            depth: 0.,
+            release: 0.2,
+            env: 0.,
+        }
+    }
+
+    pub fn trigger(&mut self) {
+        self.env = 1.;
+    }
+
+    /// dip interleaved buffer gain in place, recovering linearly
+    pub fn process(&mut self, buffer: &mut [f32], channels: usize, sample_rate: u32) {
+        if self.depth <= 0. || self.env <= 0. {
+            self.env = 0.;
+            return;
+        }
+        let dec = 1. / (self.release.max(0.001) * sample_rate as f32);
+        for frame in buffer.chunks_exact_mut(channels) {
+            let gain = 1. - self.depth * self.env;
+            frame.iter_mut().for_each(|v| *v *= gain);
+            self.env = (self.env - dec).max(0.);
+        }
+    }
+}
+
+impl Default for Duck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_repeats_impulse_at_beat_spacing() {
+        let mut delay = Delay::new();
+        delay.beats = 0.5;
+        delay.feedback = 0.5;
+        delay.wet = 1.;
+        // half a beat at 120 bpm and 1000 hz is 250 frames
+        let mut buffer = vec![0.; 1000 * 2];
+        buffer[..2].fill(1.);
+        delay.process(&mut buffer, 2, 1000, 120.);
+        let repeats = buffer.chunks(2).enumerate().filter(|(_, v)| v[0] != 0.).map(|(i, v)| (i, v[0])).collect::<Vec<_>>();
+        assert_eq!(repeats, [(0, 1.), (250, 1.), (500, 0.5), (750, 0.25)]);
+    }
+
+    #[test]
+    fn delay_flushes_when_stopped() {
+        let mut delay = Delay::new();
+        delay.wet = 1.;
+        let mut buffer = vec![0.; 64];
+        buffer[0] = 1.;
+        delay.process(&mut buffer, 1, 1000, 120.);
+        delay.process(&mut buffer, 1, 1000, 0.);
+        let mut buffer = vec![0.; 2000];
+        delay.process(&mut buffer, 1, 1000, 120.);
+        assert!(buffer.iter().all(|v| *v == 0.));
+    }
+
+    #[test]
+    fn decaying_tails_flush_to_zero_not_subnormal() {
+        // echo each 2 frames at 1000 hz, halving, past f32's normal range
+        let mut delay = Delay::new();
+        delay.beats = 0.004;
+        delay.feedback = 0.5;
+        delay.wet = 1.;
+        let mut buffer = vec![0.; 1000];
+        buffer[0] = 1.;
+        delay.process(&mut buffer, 1, 1000, 120.);
+        assert!(buffer.iter().chain(&delay.line).all(|v| !v.is_subnormal()));
+        assert!(buffer[..200].iter().any(|v| *v != 0.) && delay.line.iter().all(|v| *v == 0.));
+        // impulse ringing out through filter
+        let mut filter = Filter::new();
+        filter.cutoff = 0.5;
+        filter.resonance = 0.5;
+        let mut buffer = vec![0.; 44100];
+        buffer[0] = 1.;
+        filter.process(&mut buffer, 1, 44100);
+        assert!(buffer.iter().all(|v| !v.is_subnormal()));
+        assert_eq!(filter.state, [[0.; 2]]);
+    }
+
+    /// rms of stereo sine at `hz` through filter at `cutoff`, after it settles
+    fn filtered_rms(cutoff: f32, hz: f32) -> f32 {
+        let mut filter = Filter::new();
+        filter.cutoff = cutoff;
+        let mut buffer = (0..44100).flat_map(|i| [(i as f32 * hz / 44100. * std::f32::consts::TAU).sin(); 2]).collect::<Vec<_>>();
+        filter.process(&mut buffer, 2, 44100);
+        let tail = &buffer[buffer.len() / 2..];
+        (tail.iter().map(|v| v * v).sum::<f32>() / tail.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn filter_low_cutoff_attenuates_high_tone() {
+        let dry = std::f32::consts::FRAC_1_SQRT_2;
+        // near 200hz cutoff
+        let cutoff = (200f32 / 20.).log(1000.);
+        assert!(filtered_rms(cutoff, 8000.) < dry * 0.01);
+        assert!((filtered_rms(cutoff, 50.) - dry).abs() < dry * 0.1);
+        // fully open passes untouched
+        assert!((filtered_rms(1., 8000.) - dry).abs() < 1e-3);
+    }
+
+    /// ramp over full range, past either end
+    fn ramp() -> Vec<f32> {
+        (0..4096).map(|i| i as f32 / 2048. * 1.1 - 1.1).collect()
+    }
+
+    #[test]
+    fn crush_depth_4_takes_16_levels() {
+        let mut crush = Crush::new();
+        crush.depth = 4;
+        let mut buffer = ramp();
+        crush.process(&mut buffer, 1);
+        let mut levels = buffer.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+        levels.sort();
+        levels.dedup();
+        assert_eq!(levels.len(), 16);
+    }
+
+    #[test]
+    fn crush_depth_16_bit_transparent() {
+        // held frames at full depth pass through unquantized
+        let mut crush = Crush::new();
+        crush.rate = 2;
+        let mut buffer = ramp();
+        crush.process(&mut buffer, 2);
+        for (frames, source) in buffer.chunks(4).zip(ramp().chunks(4)) {
+            assert_eq!(frames, [&source[..2], &source[..2]].concat());
+        }
+        crush.rate = 1;
+        let mut buffer = ramp();
+        crush.process(&mut buffer, 1);
+        assert_eq!(buffer, ramp());
+    }
+
+    #[test]
+    fn tremolo_depth_0_transparent_and_full_depth_reaches_zero() {
+        let mut tremolo = Tremolo::new();
+        let mut buffer = ramp();
+        tremolo.process(&mut buffer, 2, 1000, 120.);
+        assert_eq!(buffer, ramp());
+        // 10 hz cycle over 100 frames, trough mid cycle
+        tremolo.depth = 1.;
+        for shape in [Shape::Sine, Shape::Triangle, Shape::Square] {
+            tremolo.lfo = Lfo { shape, ..Lfo::new(Rate::Free(10.)) };
+            let mut buffer = vec![1.; 200];
+            tremolo.process(&mut buffer, 2, 1000, 120.);
+            let (min, max) = buffer.iter().fold((1f32, 0f32), |(lo, hi), v| (lo.min(*v), hi.max(*v)));
+            assert!(min < 1e-3 && max == 1.);
+            assert!(buffer[98..104].iter().any(|v| *v < 1e-3) && buffer[0] == 1.);
+            assert_eq!(buffer[0], buffer[1]);
+        }
+        // synced cycle of 4 steps peaks on its clocks
+        tremolo.lfo = Lfo::new(Rate::Synced(super::super::Fraction::new(4, 1)));
+        for (clock, gain) in [(6., 0.), (8., 1.)] {
+            tremolo.lfo.sync(clock);
+            let mut buffer = vec![1.; 2];
+            tremolo.process(&mut buffer, 2, 1000, 120.);
+            assert!((buffer[0] - gain).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn auto_pan_balance_swings_over_its_period() {
+        // frames where balance crosses center toward right
+        let crossings = |rate, frames: usize| {
+            let mut pan = AutoPan::new();
+            pan.depth = 1.;
+            pan.lfo = Lfo { shape: Shape::Triangle, ..Lfo::new(rate) };
+            let mut buffer = vec![1.; frames * 2];
+            pan.process(&mut buffer, 2, 1000, 120.);
+            let balance = buffer.chunks(2).map(|v| v[1] - v[0]).collect::<Vec<_>>();
+            assert!(balance.iter().any(|v| *v > 0.99) && balance.iter().any(|v| *v < -0.99));
+            balance.windows(2).enumerate().filter(|(_, v)| v[0] < 0. && v[1] >= 0.).map(|(i, _)| i + 1).collect::<Vec<_>>()
+        };
+        // free running 10 hz, center crossed rising three quarters into each cycle
+        let free = crossings(Rate::Free(10.), 400);
+        assert_eq!(free.len(), 4);
+        assert!(free.windows(2).all(|v| v[1].abs_diff(v[0]).abs_diff(100) <= 1));
+        // 8 steps of 125 frames at 120 bpm
+        let synced = crossings(Rate::Synced(super::super::Fraction::new(8, 1)), 3000);
+        assert_eq!(synced.len(), 3);
+        assert!(synced.windows(2).all(|v| v[1].abs_diff(v[0]).abs_diff(1000) <= 1));
+        // mono left alone
+        let mut pan = AutoPan::new();
+        pan.depth = 1.;
+        let mut buffer = vec![1.; 64];
+        pan.process(&mut buffer, 1, 1000, 120.);
+        assert!(buffer.iter().all(|v| *v == 1.));
+    }
+}
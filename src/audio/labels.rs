@@ -0,0 +1,77 @@
+use super::{active::Header, Rd};
+use std::{fs::File, path::Path};
+
+/// start seconds of each audacity label line, skipping spectral selection lines
+pub fn parse(text: &str) -> Result<Vec<f32>, std::io::Error> {
+    text.lines()
+        .filter(|v| !v.trim().is_empty() && !v.starts_with('\\'))
+        .map(|v| {
+            let start = v.split('\t').next().unwrap_or_default().trim();
+            start.parse::<f32>().map_err(|_| std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid label start `{}`", start),
+            ))
+        })
+        .collect()
+}
+
+/// convert label starts to sorted, deduped pcm byte offsets of wav
+pub fn import(wav: impl AsRef<Path>, labels: impl AsRef<Path>) -> Result<Rd, std::io::Error> {
+    let header = Header::open(wav)?;
+    let stride = header.format.width() as u64;
+    let mut onsets = parse(&std::fs::read_to_string(labels)?)?
+        .into_iter()
+        .map(|v| (v.max(0.) as f64 * header.rate as f64) as u64 * stride)
+        .filter(|v| *v < header.len)
+        .collect::<Vec<_>>();
+    onsets.sort();
+    onsets.dedup();
+    Ok(Rd {
+        tempo: None,
+        steps: None,
+        onsets,
+    })
+}
+
+/// import labels and write onsets to .rd alongside wav
+pub fn write(wav: impl AsRef<Path>, labels: impl AsRef<Path>) -> color_eyre::Result<Rd> {
+    let wav = wav.as_ref();
+    let rd = import(wav, labels)?;
+    let file = File::create_new(wav.with_extension("rd"))?;
+    serde_json::to_writer(file, &rd)?;
+    Ok(rd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// mono 16-bit wav of `words` at 1000hz, and labels file, in temp dir
+    fn files(name: &str, words: usize, labels: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("labels-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wav = dir.join("take.wav");
+        std::fs::write(&wav, super::super::bounce::image(1000, &vec![0; words * 2]).unwrap()).unwrap();
+        let txt = dir.join("take.txt");
+        std::fs::write(&txt, labels).unwrap();
+        (wav, txt)
+    }
+
+    #[test]
+    fn two_lines_convert_to_sorted_offsets() {
+        let (wav, txt) = files("two", 1000, "0.500000\t0.500000\thit\n0.250000\t0.300000\tsnare\n");
+        let rd = import(&wav, &txt).unwrap();
+        std::fs::remove_dir_all(wav.parent().unwrap()).ok();
+        // seconds at 1000hz, two bytes per word
+        assert_eq!(rd.onsets, [500, 1000]);
+    }
+
+    #[test]
+    fn duplicate_and_outside_labels_dropped() {
+        let labels = "0.1\t0.1\ta\n\\\t100.0\t200.0\n0.1\t0.2\tb\n2.0\t2.0\tpast end\n";
+        let (wav, txt) = files("dedup", 1000, labels);
+        let rd = import(&wav, &txt).unwrap();
+        std::fs::remove_dir_all(wav.parent().unwrap()).ok();
+        assert_eq!(rd.onsets, [200]);
+    }
+}
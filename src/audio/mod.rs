@@ -3,43 +3,429 @@ use color_eyre::Result;
 
 pub mod pads;
 pub mod active;
+pub mod fx;
+pub mod detect;
+pub mod bounce;
+pub mod clock_out;
+pub mod labels;
+pub mod smf;
+pub mod stream;
+pub mod flac;
+pub mod ogg;
 
 pub const PAD_COUNT: usize = 8;
-pub const SAMPLE_RATE: u16 = 48000;
+/// in-memory scenes recalled by tap + pad, bank a's pads first
+pub const SCENE_SLOTS: usize = 2 * PAD_COUNT;
+/// default grain length in words
 pub const GRAIN_LEN: usize = 1024;
+pub const GRAIN_LEN_MIN: usize = 64;
+pub const GRAIN_LEN_MAX: usize = 8192;
+/// latest off-step position within step pair
+pub const SWING_MAX: f32 = 0.75;
+/// retrigger period of held onsets while stuttering, in steps
+pub const STUTTER: Fraction = Fraction::new(1, 2);
+/// tremolo cycle lengths in steps, selected by cc
+pub const TREMOLO_PERIODS: [Fraction; 8] = [
+    Fraction::new(1, 4), Fraction::new(1, 2), Fraction::new(1, 1), Fraction::new(2, 1),
+    Fraction::new(3, 1), Fraction::new(4, 1), Fraction::new(8, 1), Fraction::new(16, 1),
+];
+/// delay lengths in beats, selected by cc
+pub const DELAY_BEATS: [Fraction; 8] = [
+    Fraction::new(1, 4), Fraction::new(1, 3), Fraction::new(1, 2), Fraction::new(2, 3),
+    Fraction::new(3, 4), Fraction::new(1, 1), Fraction::new(3, 2), Fraction::new(2, 1),
+];
+/// delay length before any cc
+pub const DELAY_BEAT: Fraction = Fraction::new(3, 4);
+/// free-running auto-pan rate bounds in hz
+pub const PAN_HZ_MIN: f32 = 0.05;
+pub const PAN_HZ_MAX: f32 = 10.;
+/// longest sidechain duck recovery in seconds
+pub const DUCK_RELEASE_MAX: f32 = 1.;
+/// metronome blip pitch in hz, on steps and bar downbeats
+pub const CLICK_HZ: f32 = 1000.;
+pub const CLICK_ACCENT_HZ: f32 = 1600.;
+/// metronome blip length in seconds
+pub const CLICK_LEN: f32 = 0.02;
+/// default and longest metronome bar in steps
+pub const CLICK_BAR: u16 = 16;
+pub const CLICK_BAR_MAX: u16 = 32;
+/// most humanized read offset in steps, either side of grid
+pub const HUMANIZE_STEPS: f32 = 0.25;
+/// most humanized gain cut
+pub const HUMANIZE_GAIN: f32 = 0.25;
+/// most bars of record count-in
+pub const PREROLL_MAX: u8 = 2;
+/// onset start nudge in words per encoder tick
+pub const NUDGE_WORDS: i64 = 32;
+/// max file length in bytes read into memory rather than streamed
+pub const PRELOAD_LEN: u64 = 64 * 1024 * 1024;
+/// bytes buffered around each streamed wav reader's read position
+pub const STREAM_LEN: u64 = 2 * 1024 * 1024;
+/// bytes read on load either side of each streamed onset start, covering its attack until buffered
+pub const STREAM_HEAD: u64 = 64 * 1024;
+/// readers of one streamed wav buffered at once; others read its heads only
+pub const STREAM_VOICES: usize = 8;
+/// max bytes read ahead per streamed onset per io pass
+pub const STREAM_CHUNK: u64 = 64 * 1024;
+/// io thread wait between passes once all streams full
+pub const STREAM_POLL: std::time::Duration = std::time::Duration::from_millis(10);
+/// bounced samples buffered between callback and writer thread
+pub const BOUNCE_RING: usize = 1 << 20;
+/// longest wait on stopping a bounce for callback to release its tap
+pub const BOUNCE_WAIT: std::time::Duration = std::time::Duration::from_secs(1);
+/// fraction of callback period waited on worker's render before its bank sits out
+pub const WORKER_WAIT: f32 = 0.5;
+/// default crossfade between grains and across seeks and loop wraps, in frames
+pub const LOOP_FADE: usize = 64;
+/// onset attack length in output frames
+pub const ATTACK_LEN: usize = 32;
+/// onset release length in output frames
+pub const RELEASE_LEN: usize = 128;
+/// onset detection energy window in words
+pub const DETECT_HOP: usize = 256;
+/// onset detection min energy rise over mean rise
+pub const DETECT_THRESHOLD: f32 = 4.;
+/// onset detection min spacing in ms
+pub const DETECT_SPACING: u32 = 50;
+/// recent inter-step intervals in tempo median; tempo sent once half full
+pub const TEMPO_WINDOW: usize = 8;
+/// taps to set tempo
+pub const TAP_COUNT: usize = 4;
+/// cc offset of 14-bit lsb from its msb, for ccs below it
+pub const LSB_OFFSET: u8 = 32;
+/// midi all notes off cc, handled as panic
+pub const ALL_NOTES_OFF: u8 = 123;
+/// gap between taps starting over
+pub const TAP_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(2000);
+/// wait between attempts to rebuild stream after device lost
+pub const RECONNECT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(1000);
+/// internal clock tempo when none received
+pub const DEFAULT_TEMPO: f32 = 120.;
+/// bounds of derived, tapped and assigned tempo in bpm
+pub const TEMPO_MIN: f32 = 20.;
+pub const TEMPO_MAX: f32 = 400.;
+/// aftertouch approach toward target per output buffer
+pub const TOUCH_SLEW: f32 = 0.2;
+/// level meter updates per second
+pub const LEVEL_RATE: u32 = 30;
+/// default root against which scene onset paths are stored
+pub const ONSETS_DIR: &str = "onsets";
+pub const SCENES_DIR: &str = "scenes";
+/// scene saved on exit within scenes dir, offered for restore on launch
+pub const AUTOSAVE_NAME: &str = "autosave.sd";
+/// midi note of pad index 0 in exported phrases
+pub const EXPORT_NOTE: u8 = 36;
+/// pending commands to audio thread before sends fail
+pub const CMD_CAPACITY: usize = 1024;
+/// midi clocks per quarter sent, and expected unless configured
 pub const PPQ: u8 = 24;
+/// midi realtime messages queued for sending before dropped
+pub const CLOCK_QUEUE: usize = 256;
 pub const STEP_DIV: u8 = 4;
+/// default loop grid, and unit of loop lengths
 pub const LOOP_DIV: u8 = 8;
+/// selectable loop grids: quarter, eighth, eighth triplet, sixteenth
+pub const LOOP_DIVS: [u8; 4] = [4, 8, 12, 16];
 pub const MAX_PHRASE_LEN: u16 = 2u16.pow(PAD_COUNT as u32 - 1);
 
 pub enum Cmd<const N: usize> {
     Clock,
     Stop,
+    /// jump to step
+    Seek(u16),
     AssignTempo(f32),
     OffsetSpeed(f32),
-    SaveScene(std::fs::File),
+    AssignInterp(Interp),
+    /// grain length in words, kept even within GRAIN_LEN_MIN..=GRAIN_LEN_MAX
+    AssignGrainLen(usize),
+    /// off-step position within step pair, 0.5 straight
+    AssignSwing(f32),
+    AssignLimit(bool),
+    AssignMaster(bool),
+    /// silence both banks, dropping held and playing events
+    Panic,
+    AssignClick(bool),
+    AssignClickLevel(f32),
+    /// steps per bar, first accented
+    AssignClickBar(u16),
+    /// start bouncing output to file, or stop if none
+    AssignBounce(Option<bounce::Tap>),
+    /// save to file, as bincode if binary else json
+    SaveScene(std::fs::File, bool),
     LoadScene(Box<pads::Scene<N>>),
+    /// replace header and contents of onsets of same wav, leaving actives to finish on old
+    ReloadWav(Box<Wav>),
+    /// clone bank's kit slot into other bank's same slot
+    CopyKit(Bank, u8),
+    /// save bank's loaded kit to file as json
+    SaveKit(Bank, std::fs::File),
+    /// replace bank's kit slot, leaving loaded kit
+    StoreKit(Bank, u8, Box<pads::Kit<N>>),
+    /// exchange bank states, including loaded kits and actives
+    SwapBanks,
+    /// save to file as json, then signal completion
+    Autosave(std::fs::File, std::sync::mpsc::Sender<()>),
     Bank(Bank, BankCmd),
 }
 
+/// lock-free producer of commands to audio thread
+pub struct CmdTx<const N: usize> {
+    tx: rtrb::Producer<Cmd<N>>,
+    /// commands dropped since queue last took one
+    dropped: u32,
+    tui_tx: Option<std::sync::mpsc::Sender<crate::tui::Cmd>>,
+}
+
+impl<const N: usize> CmdTx<N> {
+    /// warn tui of commands dropped while queue full
+    pub fn assign_tui(&mut self, tui_tx: std::sync::mpsc::Sender<crate::tui::Cmd>) {
+        self.tui_tx = Some(tui_tx);
+    }
+
+    /// queue command; while audio thread falls behind, drop it with a warning rather than fail caller
+    pub fn send(&mut self, cmd: Cmd<N>) -> Result<()> {
+        match self.tx.push(cmd) {
+            Ok(()) => {
+                if self.dropped > 0 {
+                    self.warn(format!("audio command queue drained; dropped {} commands", self.dropped));
+                    self.dropped = 0;
+                }
+                Ok(())
+            }
+            Err(_) if self.tx.is_abandoned() => Err(color_eyre::Report::msg("audio thread exited")),
+            Err(_) => {
+                if self.dropped == 0 {
+                    self.warn("audio command queue full; dropping commands".into());
+                }
+                self.dropped += 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn warn(&self, message: String) {
+        if let Some(tui_tx) = self.tui_tx.as_ref() {
+            // tui may have exited
+            tui_tx.send(crate::tui::Cmd::Error(message)).ok();
+        }
+    }
+}
+
+/// lock-free consumer of commands in audio callback
+pub struct CmdRx<const N: usize>(rtrb::Consumer<Cmd<N>>);
+
+impl<const N: usize> CmdRx<N> {
+    pub fn try_recv(&mut self) -> Option<Cmd<N>> {
+        self.0.pop().ok()
+    }
+}
+
+/// single-producer single-consumer command queue of CMD_CAPACITY
+pub fn cmd_queue<const N: usize>() -> (CmdTx<N>, CmdRx<N>) {
+    let (tx, rx) = rtrb::RingBuffer::new(CMD_CAPACITY);
+    (CmdTx { tx, dropped: 0, tui_tx: None }, CmdRx(rx))
+}
+
 pub enum BankCmd {
     AssignGain(f32),
     AssignSpeed(f32),
     AssignDrift(f32),
+    /// randomness of next pool phrase, independent of event drift
+    AssignPhraseDrift(f32),
+    AssignPhraseReverse(bool),
+    AssignPoolOrder(PoolOrder),
+    AssignPadMode(PadMode),
+    AssignScale(Scale),
+    /// probability stamped onto subsequently recorded events
+    AssignProb(f32),
     AssignBias(f32),
     AssignWidth(f32),
     AssignReverse(bool),
+    AssignPadGain(u8, f32),
+    AssignCutoff(f32),
+    AssignResonance(f32),
+    AssignFeedback(f32),
+    AssignWet(f32),
+    AssignDelayBeats(Fraction),
+    AssignCrushDepth(u8),
+    AssignCrushRate(u8),
+    AssignTremoloDepth(f32),
+    AssignTremoloPeriod(Fraction),
+    AssignTremoloShape(fx::Shape),
+    AssignPanDepth(f32),
+    AssignPanRate(fx::Rate),
+    AssignDuckDepth(f32),
+    /// per-event timing and gain variance, 0 on grid
+    AssignHumanize(f32),
+    /// clear record, counting in given bars before capturing again
+    ArmRecord(u8),
+    /// duck recovery in seconds
+    AssignDuckRelease(f32),
+    AssignTouch(Touch, f32),
+    /// retrigger held onsets from start every fraction of a step
+    AssignStutter(Option<Fraction>),
+    /// hold read position, looping current grain
+    AssignFrozen(bool),
 
     AssignKit(u8),
     LoadKit(u8),
     AssignOnset(u8, bool, Box<Onset>),
+    /// shift pad's onset start by words, from next trigger
+    NudgeOnset(u8, bool, i64),
 
     ForceEvent(Event),
     PushEvent(Event),
     TakeRecord(Option<u8>),
+    /// install phrase built outside record on pad, playing it alone from pool
+    AssignPhrase(u8, Box<Phrase>),
     BakeRecord(u16),
     ClearPool,
     PushPool(u8),
+    /// append pool and phrase params to song as section played once
+    PushSection,
+    /// play last section once more per song pass
+    RepeatSection,
+    /// start song from first section, or stop and clear it if playing
+    ToggleSong,
+    ClearPhrase(u8),
+}
+
+/// order in which pool phrases play
+#[derive(Copy, Clone, Default)]
+pub enum PoolOrder {
+    /// pool order, offset by phrase drift
+    #[default]
+    Sequential,
+    /// each phrase once per cycle in random order
+    Shuffle,
+    /// any phrase each time
+    Random,
+}
+
+impl PoolOrder {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Sequential => Self::Shuffle,
+            Self::Shuffle => Self::Random,
+            Self::Random => Self::Sequential,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sequential => "sequential",
+            Self::Shuffle => "shuffle",
+            Self::Random => "random",
+        }
+    }
+}
+
+/// mapping of pads to onsets
+#[derive(Copy, Clone, Default)]
+pub enum PadMode {
+    /// each pad plays its own onsets
+    #[default]
+    Kit,
+    /// every pad plays root pad's onsets, a scale degree per pad from root
+    Chromatic(u8),
+}
+
+impl PadMode {
+    pub fn root(&self) -> Option<u8> {
+        match self {
+            Self::Kit => None,
+            Self::Chromatic(root) => Some(*root),
+        }
+    }
+
+    /// speed factor of onset triggered from pad
+    pub fn pitch(&self, index: u8, scale: Scale) -> f32 {
+        match self {
+            Self::Kit => 1.,
+            Self::Chromatic(root) => 2f32.powf(scale.degree(index as i32 - *root as i32) as f32 / 12.),
+        }
+    }
+}
+
+/// tones chromatic pads step through
+#[derive(Copy, Clone, Default)]
+pub enum Scale {
+    #[default]
+    Chromatic,
+    Major,
+    Minor,
+    Pentatonic,
+}
+
+impl Scale {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Chromatic => Self::Major,
+            Self::Major => Self::Minor,
+            Self::Minor => Self::Pentatonic,
+            Self::Pentatonic => Self::Chromatic,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Chromatic => "chromatic",
+            Self::Major => "major",
+            Self::Minor => "minor",
+            Self::Pentatonic => "pentatonic",
+        }
+    }
+
+    /// semitones above octave root
+    fn tones(&self) -> &'static [i32] {
+        match self {
+            Self::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Self::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Self::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Self::Pentatonic => &[0, 2, 4, 7, 9],
+        }
+    }
+
+    /// semitones of scale degree `offset` pads from root, wrapping into octaves
+    pub fn degree(&self, offset: i32) -> i32 {
+        let tones = self.tones();
+        let len = tones.len() as i32;
+        offset.div_euclid(len) * 12 + tones[offset.rem_euclid(len) as usize]
+    }
+}
+
+/// grain resampling quality
+#[derive(Copy, Clone, Default)]
+pub enum Interp {
+    /// two-point linear
+    #[default]
+    Linear,
+    /// four-point catmull-rom
+    Hermite,
+}
+
+impl Interp {
+    /// guard words read before and after the fractional index
+    pub fn guards(&self) -> (usize, usize) {
+        match self {
+            Interp::Linear => (0, 1),
+            Interp::Hermite => (1, 2),
+        }
+    }
+
+    /// interpolate between `x[pre]` and `x[pre + 1]` by `t`
+    pub fn kernel(&self, x: &[f32], t: f32) -> f32 {
+        match self {
+            Interp::Linear => x[0] + (x[1] - x[0]) * t,
+            Interp::Hermite => {
+                let c0 = x[1];
+                let c1 = 0.5 * (x[2] - x[0]);
+                let c2 = x[0] - 2.5 * x[1] + 2. * x[2] - 0.5 * x[3];
+                let c3 = 0.5 * (x[3] - x[0]) + 1.5 * (x[1] - x[2]);
+                ((c3 * t + c2) * t + c1) * t + c0
+            }
+        }
+    }
 }
 
 #[derive(Copy, Clone, serde::Serialize, serde::Deserialize)]
@@ -49,7 +435,7 @@ pub struct Fraction {
 }
 
 impl Fraction {
-    pub fn new(numerator: u8, denominator: u8) -> Self {
+    pub const fn new(numerator: u8, denominator: u8) -> Self {
         Self {
             numerator,
             denominator,
@@ -63,20 +449,131 @@ impl From<Fraction> for f32 {
     }
 }
 
-#[derive(Clone, serde::Deserialize)]
+/// aftertouch modulation target
+#[derive(Copy, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub enum Touch {
+    #[default]
+    Speed,
+    Width,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Rd {
     pub tempo: Option<f32>,
     pub steps: Option<u16>,
     pub onsets: Vec<u64>,
 }
 
+impl Rd {
+    /// load .rd alongside wav
+    pub fn load(wav: impl AsRef<std::path::Path>) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(wav.as_ref().with_extension("rd"))?)?)
+    }
+}
+
+static ONSETS: std::sync::OnceLock<Box<std::path::Path>> = std::sync::OnceLock::new();
+static SCENES: std::sync::OnceLock<Box<std::path::Path>> = std::sync::OnceLock::new();
+
+/// tempo clamped to TEMPO_MIN..=TEMPO_MAX, if finite and positive
+pub fn clamp_tempo(tempo: f32) -> Option<f32> {
+    (tempo.is_finite() && tempo > 0.).then(|| tempo.clamp(TEMPO_MIN, TEMPO_MAX))
+}
+
+/// `pulses` onsets spread as evenly as integers allow over `steps`, first on step 0, as by
+/// bjorklund's algorithm
+pub fn euclid(pulses: u16, steps: u16) -> impl Iterator<Item = bool> {
+    let pulses = pulses.min(steps) as usize;
+    let mut groups = vec![vec![true]; pulses];
+    let mut rest = vec![vec![false]; steps as usize - pulses];
+    // deal remainder onto leading groups until at most one remainder is left
+    while rest.len() > 1 && !groups.is_empty() {
+        let len = groups.len().min(rest.len());
+        let tail = if groups.len() > len { groups.split_off(len) } else { rest.split_off(len) };
+        for (group, v) in groups.iter_mut().zip(rest) {
+            group.extend(v);
+        }
+        rest = tail;
+    }
+    groups.into_iter().chain(rest).flatten()
+}
+
+/// override onset and scene roots; ignored once either read
+pub fn assign_dirs(onsets: Option<&std::path::Path>, scenes: Option<&std::path::Path>) {
+    if let Some(v) = onsets {
+        ONSETS.set(v.into()).ok();
+    }
+    if let Some(v) = scenes {
+        SCENES.set(v.into()).ok();
+    }
+}
+
+pub fn onsets_dir() -> &'static std::path::Path {
+    ONSETS.get_or_init(|| std::path::Path::new(ONSETS_DIR).into())
+}
+
+pub fn scenes_dir() -> &'static std::path::Path {
+    SCENES.get_or_init(|| std::path::Path::new(SCENES_DIR).into())
+}
+
+pub fn autosave_path() -> std::path::PathBuf {
+    scenes_dir().join(AUTOSAVE_NAME)
+}
+
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Wav {
     pub tempo: Option<f32>,
     pub steps: Option<u16>,
+    /// stored relative to onsets_dir()
+    #[serde(with = "onset_path")]
     pub path: Box<std::path::Path>,
     /// pcm length in bytes
     pub len: u64,
+    /// preloaded file contents, if any
+    #[serde(skip)]
+    pub data: Option<std::sync::Arc<[u8]>>,
+    /// opened for streaming, if not preloaded
+    #[serde(skip)]
+    pub stream: Option<stream::Handle>,
+}
+
+impl Wav {
+    /// read file into memory, if under preload threshold; flac and ogg always decoded into memory.
+    /// otherwise open for streaming, reading heads at `starts` and at onsets of its rd
+    pub fn preload(&mut self, starts: &[u64]) -> Result<(), std::io::Error> {
+        if let Some(data) = active::decode(&self.path) {
+            self.data = Some(data?.into());
+        } else if std::fs::metadata(&self.path)?.len() <= PRELOAD_LEN {
+            self.data = Some(std::fs::read(&self.path)?.into());
+        } else {
+            let mut starts = starts.to_vec();
+            starts.extend(Rd::load(&self.path).map(|v| v.onsets).unwrap_or_default());
+            self.stream = Some(stream::Handle::open(&self.path, &starts)?);
+        }
+        Ok(())
+    }
+}
+
+mod onset_path {
+    use std::path::{Component, Path};
+
+    pub fn serialize<S: serde::Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        // paths outside root kept as-is
+        let rel = path.strip_prefix(super::onsets_dir()).unwrap_or(path);
+        serde::Serialize::serialize(rel, serializer)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Box<Path>, D::Error> {
+        let path: std::path::PathBuf = serde::Deserialize::deserialize(deserializer)?;
+        // migrate absolute and cwd-relative paths from before root, keeping all after root
+        let name = super::onsets_dir().file_name().unwrap_or(super::ONSETS_DIR.as_ref());
+        let root = path.components().position(|v| v == Component::Normal(name));
+        let rel: std::path::PathBuf = match root {
+            Some(index) => path.components().skip(index + 1).collect(),
+            None if path.is_absolute() => return Ok(path.into()),
+            None => path,
+        };
+        Ok(super::onsets_dir().join(rel).into())
+    }
 }
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
@@ -88,14 +585,45 @@ pub struct Onset {
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub enum Event {
     Sync,
-    Hold { index: u8 },
-    Loop { index: u8, len: Fraction },
+    Hold {
+        index: u8,
+        #[serde(default = "Event::default_vel")]
+        vel: f32,
+    },
+    Loop {
+        index: u8,
+        len: Fraction,
+        #[serde(default = "Event::default_vel")]
+        vel: f32,
+    },
+}
+
+impl Event {
+    fn default_vel() -> f32 {
+        1.
+    }
 }
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 struct Stamped {
     event: Event,
     step: u16,
+    /// chance event fires each pass, else rests
+    #[serde(default = "Stamped::default_prob")]
+    prob: f32,
+    /// fire only on pass i of every n, one-based, e.g. (1, 4) on first of four
+    #[serde(default)]
+    condition: Option<(u8, u8)>,
+}
+
+impl Stamped {
+    fn default_prob() -> f32 {
+        1.
+    }
+
+    fn fires(&self, pass: u32) -> bool {
+        self.condition.is_none_or(|(i, n)| pass % n.max(1) as u32 == i.saturating_sub(1) as u32)
+    }
 }
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
@@ -105,11 +633,33 @@ pub struct Phrase {
 }
 
 impl Phrase {
-    fn generate_active<const N: usize>(&self, active: &mut Option<active::Phrase>, step: u16, bias: f32, drift: f32, pads: &pads::Kit<N>) -> Result<Option<active::Phrase>> {
+    /// phrase of `len` steps from events at steps, e.g. as programmed in step grid
+    pub fn new(events: impl IntoIterator<Item = (u16, Event)>, len: u16) -> Self {
+        let mut events = events
+            .into_iter()
+            .filter(|(step, _)| *step < len)
+            .map(|(step, event)| Stamped { event, step, prob: Stamped::default_prob(), condition: None })
+            .collect::<Vec<_>>();
+        events.sort_by_key(|v| v.step);
+        Self { events, len }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn generate_active<const N: usize>(&self, active: &mut Option<active::Phrase>, step: u16, bias: f32, drift: f32, reverse: bool, pads: &pads::Kit<N>) -> Result<Option<active::Phrase>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        // reversed phrases open on last event, closing on any leading gap
+        let immediate = if reverse {
+            !self.events.is_empty()
+        } else {
+            self.events.first().is_some_and(|v| v.step == 0)
+        };
         if let Some(active) = active.as_mut() {
-            if self.events.first().is_some_and(|v| v.step == 0) {
+            active.pass = active.pass.wrapping_add(1);
+            if immediate {
                 // phrase events start on first step
-                if let Some(event_rem) = self.generate_stamped(&mut active.active, 0, step, bias, drift, pads)? {
+                if let Some(event_rem) = self.generate_stamped(&mut active.active, 0, active.pass, step, bias, drift, reverse, pads)? {
                     active.next = 1;
                     active.event_rem = event_rem;
                     active.phrase_rem = self.len;
@@ -122,15 +672,16 @@ impl Phrase {
                 active.event_rem = event_rem;
                 active.phrase_rem = self.len;
             }
-        } else if self.events.first().is_some_and(|v| v.step == 0) {
+        } else if immediate {
             // phrase events start on first step
             let mut active = active::Event::Sync;
-            if let Some(event_rem) = self.generate_stamped(&mut active, 0, step, bias, drift, pads)? {
+            if let Some(event_rem) = self.generate_stamped(&mut active, 0, 0, step, bias, drift, reverse, pads)? {
                 return Ok(Some(active::Phrase {
                     next: 1,
                     event_rem,
                     phrase_rem: self.len,
                     active,
+                    pass: 0,
                 }));
             }
         } else {
@@ -141,17 +692,238 @@ impl Phrase {
                 event_rem,
                 phrase_rem: self.len,
                 active: active::Event::Sync,
+                pass: 0,
             }));
         }
         Ok(None)
     }
 
-    fn generate_stamped<const N: usize>(&self, active: &mut active::Event, index: usize, step: u16, bias: f32, drift: f32, pads: &pads::Kit<N>) -> Result<Option<u16>> {
+    #[allow(clippy::too_many_arguments)]
+    fn generate_stamped<const N: usize>(&self, active: &mut active::Event, index: usize, pass: u32, step: u16, bias: f32, drift: f32, reverse: bool, pads: &pads::Kit<N>) -> Result<Option<u16>> {
+        if self.events.is_empty() {
+            return Ok(None);
+        }
+        let index = if !reverse {
+            index
+        } else if let Some(index) = self.events.len().checked_sub(index + 1) {
+            index
+        } else {
+            // rest through leading gap once all events played
+            active.trans(&Event::Sync, step, bias, pads)?;
+            return Ok(Some(self.events[0].step));
+        };
         let drift = rand::random_range(0..=((drift * self.events.len() as f32 - 1.).round()) as usize);
         let index = (index + drift) % self.events.len();
         let stamped = &self.events[index];
         let event_rem = self.events.get(index + 1).map(|v| v.step).unwrap_or(self.len) - stamped.step;
-        active.trans(&stamped.event, step, bias, pads)?;
+        if stamped.fires(pass) && rand::random_bool(stamped.prob.clamp(0., 1.) as f64) {
+            active.trans(&stamped.event, step, bias, pads)?;
+        } else {
+            active.trans(&Event::Sync, step, bias, pads)?;
+        }
         Ok(Some(event_rem))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// resample words at speed as a grain indexes them, from first word with leading guards
+    pub(super) fn resample(interp: Interp, words: &[f32], speed: f32, len: usize) -> Vec<f32> {
+        let (pre, post) = interp.guards();
+        (0..len).map(|i| {
+            let read_idx = pre as f32 + i as f32 * speed;
+            let base = read_idx as usize - pre;
+            interp.kernel(&words[base..=base + pre + post], read_idx.fract())
+        }).collect()
+    }
+
+    fn assert_near(out: &[f32], reference: &[f32]) {
+        for (v, r) in out.iter().zip(reference) {
+            assert!((v - r).abs() < 1e-5, "{:?} != {:?}", out, reference);
+        }
+    }
+
+    #[test]
+    fn interp_matches_reference() {
+        // x^2 at whole words, read at half speed
+        let words = (0..8).map(|v| (v * v) as f32).collect::<Vec<_>>();
+        let linear = resample(Interp::Linear, &words, 0.5, 8);
+        assert_near(&linear, &[0., 0.5, 1., 2.5, 4., 6.5, 9., 12.5]);
+        // catmull-rom is exact on quadratics
+        let hermite = resample(Interp::Hermite, &words, 0.5, 8);
+        assert_near(&hermite, &[1., 2.25, 4., 6.25, 9., 12.25, 16., 20.25]);
+    }
+
+    #[test]
+    fn scale_pads_take_distinct_pitches() {
+        for scale in [Scale::Chromatic, Scale::Major, Scale::Minor, Scale::Pentatonic] {
+            let pitches = (0..16).map(|i| PadMode::Chromatic(4).pitch(i, scale)).collect::<Vec<_>>();
+            // rising pad by pad, root at unity
+            assert!(pitches.windows(2).all(|v| v[1] > v[0]), "{}: {:?}", scale.name(), pitches);
+            assert_eq!(pitches[4], 1.);
+        }
+        // pentatonic wraps into next octave after five pads, and below root
+        assert_eq!(Scale::Pentatonic.degree(5), 12);
+        assert_eq!(Scale::Pentatonic.degree(-1), -3);
+        assert_eq!(Scale::Major.degree(9), 16);
+    }
+
+    #[test]
+    fn cmd_flood_drops_without_blocking() {
+        let (mut tx, mut rx) = cmd_queue::<1>();
+        let (tui_tx, tui_rx) = std::sync::mpsc::channel();
+        tx.assign_tui(tui_tx);
+        // cc flood far beyond capacity with nothing draining, so a blocking send never returns
+        let sent = CMD_CAPACITY * 50;
+        for i in 0..sent {
+            tx.send(Cmd::Bank(Bank::A, BankCmd::AssignGain(i as f32))).unwrap();
+        }
+        assert_eq!(tx.dropped as usize, sent - CMD_CAPACITY);
+        // earliest kept, rest dropped
+        let received = std::iter::from_fn(|| rx.try_recv())
+            .map(|v| match v {
+                Cmd::Bank(Bank::A, BankCmd::AssignGain(v)) => v as usize,
+                _ => panic!("unexpected command"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(received, (0..CMD_CAPACITY).collect::<Vec<_>>());
+        // warned per full spell, not per dropped command
+        tx.send(Cmd::Stop).unwrap();
+        let warnings = tui_rx
+            .try_iter()
+            .map(|v| match v {
+                crate::tui::Cmd::Error(v) => v,
+                _ => panic!("unexpected tui command"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(warnings, [
+            "audio command queue full; dropping commands".to_string(),
+            format!("audio command queue drained; dropped {} commands", sent - CMD_CAPACITY),
+        ]);
+        // full with audio thread gone is an error
+        let (mut tx, rx) = cmd_queue::<1>();
+        drop(rx);
+        for _ in 0..CMD_CAPACITY {
+            tx.send(Cmd::Stop).unwrap();
+        }
+        assert!(tx.send(Cmd::Stop).is_err());
+    }
+
+    fn wav(path: &str) -> String {
+        format!(r#"{{"tempo":null,"steps":null,"path":{:?},"len":0}}"#, path)
+    }
+
+    #[test]
+    fn onset_paths_independent_of_working_dir() {
+        let path = onsets_dir().join("kit").join("kick.wav");
+        let saved = serde_json::to_string(&Wav { tempo: None, steps: None, path: path.clone().into(), len: 0, data: None, stream: None }).unwrap();
+        // stored relative to root, so resolves under whichever root loads it
+        assert_eq!(saved, wav("kit/kick.wav"));
+        let loaded: Wav = serde_json::from_str(&saved).unwrap();
+        assert_eq!(&*loaded.path, path.as_path());
+    }
+
+    #[test]
+    fn onset_paths_migrate_from_before_root() {
+        let expected = onsets_dir().join("kit").join("kick.wav");
+        // absolute and cwd-relative, as saved from other working dirs
+        for old in ["/home/someone/project/onsets/kit/kick.wav", "../project/onsets/kit/kick.wav"] {
+            let loaded: Wav = serde_json::from_str(&wav(old)).unwrap();
+            assert_eq!(&*loaded.path, expected.as_path());
+        }
+        // outside any root, kept as-is
+        let loaded: Wav = serde_json::from_str(&wav("/mnt/samples/kick.wav")).unwrap();
+        assert_eq!(&*loaded.path, std::path::Path::new("/mnt/samples/kick.wav"));
+    }
+
+    #[test]
+    fn interp_passes_whole_words() {
+        let words = [0.3, -0.7, 0.2, 0.9, -0.4, 0.1];
+        for interp in [Interp::Linear, Interp::Hermite] {
+            let (pre, post) = interp.guards();
+            let out = resample(interp, &words, 1., words.len() - pre - post);
+            assert_near(&out, &words[pre..]);
+        }
+    }
+
+    #[test]
+    fn conditional_event_fires_on_its_pass_of_n() {
+        let stamped = |condition| Stamped { event: Event::Sync, step: 0, prob: 1., condition };
+        let passes = |stamped: Stamped| (0..16).filter(|v| stamped.fires(*v)).collect::<Vec<_>>();
+        assert_eq!(passes(stamped(Some((1, 4)))), [0, 4, 8, 12]);
+        assert_eq!(passes(stamped(Some((3, 4)))), [2, 6, 10, 14]);
+        // scenes saved before conditions fire every pass
+        let old: Stamped = serde_json::from_str(r#"{"event":{"Hold":{"index":1,"vel":1.0}},"step":3,"prob":0.5}"#).unwrap();
+        assert!(old.condition.is_none() && old.step == 3 && old.prob == 0.5);
+        assert_eq!(passes(old), (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn probability_0_never_fires_and_1_always_does() {
+        let pcm = [8192i16; 256].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>();
+        let wav = Wav {
+            tempo: None,
+            steps: None,
+            path: std::path::Path::new("memory.wav").into(),
+            len: pcm.len() as u64,
+            data: Some(bounce::image(44100, &pcm).unwrap().into()),
+            stream: None,
+        };
+        let mut pads = pads::Kit::<1>::new();
+        pads.inner[0].onsets[0] = Some(Onset { wav, start: 0 });
+        let phrase = |prob| Phrase {
+            events: vec![Stamped { event: Event::Hold { index: 0, vel: 1. }, step: 0, prob, condition: None }],
+            len: 4,
+        };
+        for (prob, fires) in [(0., false), (1., true)] {
+            let phrase = phrase(prob);
+            for _ in 0..100 {
+                let mut active = active::Event::Sync;
+                // timing kept whether or not event fires
+                assert_eq!(phrase.generate_stamped(&mut active, 0, 0, 0, 0.5, 0., false, &pads).unwrap(), Some(4));
+                assert_eq!(matches!(active, active::Event::Hold(..)), fires);
+            }
+        }
+        // events saved before probability always fire
+        let old: Stamped = serde_json::from_str(r#"{"event":"Sync","step":3}"#).unwrap();
+        assert_eq!(old.prob, 1.);
+    }
+
+    fn pattern(pulses: u16, steps: u16) -> String {
+        euclid(pulses, steps).map(|v| if v { 'x' } else { '.' }).collect()
+    }
+
+    #[test]
+    fn empty_phrase_generates_nothing() {
+        let pads = pads::Kit::<1>::new();
+        let mut active = None;
+        assert!(Phrase::new([], 0).generate_active(&mut active, 0, 0.5, 0., false, &pads).unwrap().is_none());
+        // eventless phrase rests through its length, either direction
+        for reverse in [false, true] {
+            let phrase = Phrase::new([], 8);
+            let generated = phrase.generate_active(&mut active, 0, 0.5, 1., reverse, &pads).unwrap().unwrap();
+            assert!(matches!(generated.active, active::Event::Sync));
+            assert_eq!(generated.phrase_rem, 8);
+            let mut event = active::Event::Sync;
+            assert!(phrase.generate_stamped(&mut event, 0, 0, 0, 0.5, 1., reverse, &pads).unwrap().is_none());
+        }
+        // pool of no phrases generates none
+        let mut pool = active::Pool::new();
+        pool.generate_phrase(0, 0.5, 1., 1., false, &pads).unwrap();
+        assert!(pool.active.is_none());
+    }
+
+    #[test]
+    fn euclid_matches_bjorklund() {
+        assert_eq!(pattern(3, 8), "x..x..x.");
+        assert_eq!(pattern(5, 8), "x.xx.xx.");
+        assert_eq!(pattern(4, 12), "x..x..x..x..");
+        assert_eq!(pattern(7, 16), "x..x.x.x..x.x.x.");
+        // no pulses, more pulses than steps, no steps
+        assert_eq!(pattern(0, 4), "....");
+        assert_eq!(pattern(6, 4), "xxxx");
+        assert_eq!(pattern(2, 0), "");
+    }
+}
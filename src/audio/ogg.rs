@@ -0,0 +1,132 @@
+use std::{fs::File, path::Path};
+
+/// by extension, ignoring case
+pub fn is_ogg(path: impl AsRef<Path>) -> bool {
+    path.as_ref().extension().is_some_and(|v| v.eq_ignore_ascii_case("ogg"))
+}
+
+/// decode vorbis to in-memory mono 16 bit wav; lossy, so never streamed
+pub fn decode(path: impl AsRef<Path>) -> Result<Vec<u8>, std::io::Error> {
+    let invalid = |e: lewton::VorbisError| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string());
+    let mut reader = lewton::inside_ogg::OggStreamReader::new(File::open(path)?).map_err(invalid)?;
+    let channels = reader.ident_hdr.audio_channels.max(1) as usize;
+    let mut pcm = Vec::new();
+    while let Some(packet) = reader.read_dec_packet_itl().map_err(invalid)? {
+        for frame in packet.chunks_exact(channels) {
+            let word = (frame.iter().map(|v| i32::from(*v)).sum::<i32>() / channels as i32) as i16;
+            pcm.extend(word.to_le_bytes());
+        }
+    }
+    super::bounce::image(reader.ident_hdr.audio_sample_rate, &pcm)
+}
+
+/// mono vorbis of `packets` short blocks, each sounding its two lowest bins, as test input
+#[cfg(test)]
+pub(super) fn image(rate: u32, packets: usize) -> Vec<u8> {
+    /// vorbis packs fields from least significant bit
+    #[derive(Default)]
+    struct Bits(Vec<u8>, u32);
+    impl Bits {
+        fn put(&mut self, value: u32, len: u32) -> &mut Self {
+            for i in 0..len {
+                if self.1.is_multiple_of(8) {
+                    self.0.push(0);
+                }
+                *self.0.last_mut().unwrap() |= (((value >> i) & 1) as u8) << (self.1 % 8);
+                self.1 += 1;
+            }
+            self
+        }
+
+        fn header(kind: u8) -> Self {
+            let mut bits = Self::default();
+            for v in [kind].iter().chain(b"vorbis") {
+                bits.put(u32::from(*v), 8);
+            }
+            bits
+        }
+
+        /// two one-bit codewords, with values 0 and `delta` if given
+        fn codebook(&mut self, delta: Option<u32>) {
+            self.put(0x564342, 24).put(1, 16).put(2, 24).put(0, 1).put(0, 1).put(0, 5).put(0, 5);
+            match delta {
+                None => self.put(0, 4),
+                // minimum 0, one bit multiplicands 0 and 1
+                Some(delta) => self.put(1, 4).put(0, 32).put(delta, 32).put(0, 4).put(0, 1).put(0, 1).put(1, 1),
+            };
+        }
+    }
+    let mut ident = Bits::header(1);
+    // version, channels, rate, bitrates, 256 word blocks, framing
+    ident.put(0, 32).put(1, 8).put(rate, 32).put(0, 32).put(0, 32).put(0, 32).put(0x88, 8).put(1, 8);
+    let mut comment = Bits::header(3);
+    comment.put(0, 32).put(0, 32).put(1, 8);
+    let mut setup = Bits::header(5);
+    // classbook, then residue book of 1/8 steps
+    setup.put(1, 8);
+    setup.codebook(None);
+    setup.codebook(Some(785 << 21 | 1));
+    // time domain, then floor 1 of its two end points alone
+    setup.put(0, 6).put(0, 16);
+    setup.put(0, 6).put(1, 16).put(0, 5).put(0, 2).put(8, 4);
+    // residue 1 over bins 0 and 1 in one partition, one class read on first pass
+    setup.put(0, 6).put(1, 16).put(0, 24).put(2, 24).put(1, 24).put(0, 6).put(0, 8).put(1, 3).put(0, 1).put(1, 8);
+    // mapping, then short block mode
+    setup.put(0, 6).put(0, 16).put(0, 1).put(0, 1).put(0, 2).put(0, 8).put(0, 8).put(0, 8);
+    setup.put(0, 6).put(0, 1).put(0, 16).put(0, 16).put(0, 8).put(1, 1);
+    // audio, full floor, class 0, both bins at 1/8
+    let audio = Bits::default().put(0, 1).put(1, 1).put(255, 8).put(255, 8).put(0, 1).put(1, 1).put(1, 1).0.clone();
+
+    fn page(ogg: &mut Vec<u8>, kind: u8, granule: u64, sequence: u32, packets: &[&[u8]]) {
+        let start = ogg.len();
+        ogg.extend(b"OggS\0");
+        ogg.push(kind);
+        ogg.extend(granule.to_le_bytes());
+        ogg.extend(1u32.to_le_bytes());
+        ogg.extend(sequence.to_le_bytes());
+        ogg.extend([0; 4]);
+        let lacing: Vec<u8> = packets
+            .iter()
+            .flat_map(|v| std::iter::repeat_n(255, v.len() / 255).chain([(v.len() % 255) as u8]))
+            .collect();
+        ogg.push(lacing.len() as u8);
+        ogg.extend(lacing);
+        for packet in packets {
+            ogg.extend(*packet);
+        }
+        let crc = ogg[start..].iter().fold(0u32, |mut crc, v| {
+            crc ^= u32::from(*v) << 24;
+            for _ in 0..8 {
+                crc = if crc & 1 << 31 != 0 { (crc << 1) ^ 0x04c11db7 } else { crc << 1 };
+            }
+            crc
+        });
+        ogg[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+    }
+    let mut ogg = Vec::new();
+    page(&mut ogg, 2, 0, 0, &[&ident.0]);
+    page(&mut ogg, 0, 0, 1, &[&comment.0, &setup.0]);
+    // first block only primes overlap
+    let samples = (packets as u64 - 1) * 128;
+    page(&mut ogg, 4, samples, 2, &vec![&audio[..]; packets]);
+    ogg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_to_mono_wav_of_its_samples() {
+        let path = std::env::temp_dir().join(format!("decode-{}.ogg", std::process::id()));
+        std::fs::write(&path, image(22050, 40)).unwrap();
+        let wav = decode(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let header = super::super::active::Header::parse(&mut std::io::Cursor::new(&wav)).unwrap();
+        assert_eq!((header.rate, header.len), (22050, 39 * 128 * 2));
+        let words: Vec<i16> = wav[header.offset as usize..].chunks_exact(2).map(|v| i16::from_le_bytes([v[0], v[1]])).collect();
+        // same block each packet, unclipped
+        assert!(words.iter().any(|v| v.abs() > 1000) && words.iter().all(|v| v.abs() < i16::MAX));
+        assert!(words.windows(129).all(|v| v[0] == v[128]));
+    }
+}
@@ -1,20 +1,42 @@
-use crate::input::Bank;
-use super::active;
+use crate::{input::Bank, tui};
+use super::{active, fx};
 
-use std::{fs::File, io::{Read, Write}};
+use std::{collections::HashMap, io::Write, path::Path, sync::atomic::Ordering};
 use cpal::{FromSample, SizedSample};
 use color_eyre::Result;
+use midly::live::SystemRealtime;
 
-#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Pad {
     pub onsets: [Option<super::Onset>; 2],
     pub phrase: Option<super::Phrase>,
+    #[serde(default = "Pad::default_gain")]
+    pub gain: f32,
+}
+
+impl Pad {
+    fn default_gain() -> f32 {
+        1.
+    }
+}
+
+impl Default for Pad {
+    fn default() -> Self {
+        Self {
+            onsets: [None, None],
+            phrase: None,
+            gain: Self::default_gain(),
+        }
+    }
 }
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Kit<const N: usize> {
     #[serde(with = "serde_arrays")]
     pub inner: [Pad; N],
+    /// pad whose onsets all pads play, if chromatic
+    #[serde(skip)]
+    pub root: Option<u8>,
 }
 
 impl<const N: usize> Kit<N> {
@@ -23,7 +45,7 @@ impl<const N: usize> Kit<N> {
     }
 
     pub fn new() -> Self {
-        Self { inner: core::array::from_fn(|_| Pad::default()) }
+        Self { inner: core::array::from_fn(|_| Pad::default()), root: None }
     }
 
     pub fn onset(
@@ -31,19 +53,25 @@ impl<const N: usize> Kit<N> {
         index: impl Into<usize> + Copy,
         alt: bool,
         pan: f32,
+        vel: f32,
     ) -> Result<active::Onset, std::io::Error> {
-        let super::Onset { wav, start, .. } = self.inner[index.into()].onsets[alt as usize].as_ref().unwrap();
-        let wav = active::Wav {
-            tempo: wav.tempo,
-            steps: wav.steps,
-            file: File::open(wav.path.clone())?,
-            len: wav.len,
-        };
+        let super::Onset { wav, start, .. } = self.inner[self.root.map_or(index.into(), usize::from)].onsets[alt as usize]
+            .as_ref()
+            .ok_or(std::io::Error::new(std::io::ErrorKind::NotFound, "no onset assigned to pad"))?;
+        let wav = active::Wav::open(wav)?;
         Ok(active::Onset {
             index: index.into() as u8,
+            alt,
             pan,
             wav,
             start: *start,
+            vel,
+            jitter: None,
+            grain: None,
+            prev: None,
+            restart: None,
+            env: 0.,
+            target: 1.,
         })
     }
 
@@ -52,25 +80,67 @@ impl<const N: usize> Kit<N> {
         index: impl Into<usize> + Copy,
         alt: bool,
         pan: f32,
+        vel: f32,
     ) -> Result<active::Onset, std::io::Error> {
-        let super::Onset { wav, start, .. } = self.inner[index.into()].onsets[alt as usize].as_ref().unwrap();
-        let mut wav = active::Wav {
-            tempo: wav.tempo,
-            steps: wav.steps,
-            file: File::open(wav.path.clone())?,
-            len: wav.len,
-        };
+        let super::Onset { wav, start, .. } = self.inner[self.root.map_or(index.into(), usize::from)].onsets[alt as usize]
+            .as_ref()
+            .ok_or(std::io::Error::new(std::io::ErrorKind::NotFound, "no onset assigned to pad"))?;
+        let mut wav = active::Wav::open(wav)?;
         wav.seek(*start as i64)?;
         Ok(active::Onset {
             index: index.into() as u8,
+            alt,
             pan,
             wav,
             start: *start,
+            vel,
+            jitter: None,
+            grain: None,
+            prev: None,
+            restart: None,
+            env: 0.,
+            target: 1.,
         })
     }
 
+    /// load from .kit json
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(path)?))?)
+    }
+
+    pub fn save(&self, file: std::fs::File) -> Result<()> {
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// drop onsets of unreadable wavs, returning their distinct paths
+    pub fn validate(&mut self) -> Vec<Box<Path>> {
+        validate(self.inner.iter_mut())
+    }
+
+    /// preload all onset wavs, sharing contents between onsets of same file
+    pub fn preload(&mut self) {
+        preload(self.inner.iter_mut())
+    }
+
+    /// replace onsets' wav of same path, keeping starts within it
+    pub fn reload(&mut self, wav: &super::Wav) {
+        let onsets = self.inner.iter_mut()
+            .flat_map(|v| v.onsets.iter_mut().flatten())
+            .filter(|v| v.wav.path == wav.path);
+        for onset in onsets {
+            onset.wav = wav.clone();
+            if onset.start >= wav.len {
+                onset.start = 0;
+            }
+        }
+    }
+
     pub fn generate_alt(&self, index: impl Into<usize>, bias: f32) -> Option<bool> {
-        match self.inner[index.into()].onsets {
+        let index = self.root.map_or(index.into(), usize::from);
+        match self.inner[index].onsets {
             [None, None] => None,
             [Some(_), None] => Some(false),
             [None, Some(_)] => Some(true),
@@ -79,7 +149,13 @@ impl<const N: usize> Kit<N> {
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+impl<const N: usize> Default for Kit<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Scene<const N: usize> {
     #[serde(with = "serde_arrays")]
     pub kit_a: [Kit<N>; N],
@@ -88,13 +164,93 @@ pub struct Scene<const N: usize> {
 }
 
 impl<const N: usize> Scene<N> {
-    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             kit_a: core::array::from_fn(|_| Kit::<N>::new()),
             kit_b: core::array::from_fn(|_| Kit::<N>::new()),
         }
     }
+
+    /// load from .sdb bincode or .sd json, by extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let reader = std::io::BufReader::new(std::fs::File::open(&path)?);
+        if path.as_ref().extension().is_some_and(|v| v == "sdb") {
+            Ok(bincode::deserialize_from(reader)?)
+        } else {
+            Ok(serde_json::from_reader(reader)?)
+        }
+    }
+
+    pub fn save(&self, file: std::fs::File, binary: bool) -> Result<()> {
+        let mut writer = std::io::BufWriter::new(file);
+        if binary {
+            bincode::serialize_into(&mut writer, self)?;
+        } else {
+            serde_json::to_writer_pretty(&mut writer, self)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// drop onsets of unreadable wavs, returning their distinct paths
+    pub fn validate(&mut self) -> Vec<Box<Path>> {
+        validate(self.kit_a.iter_mut().chain(self.kit_b.iter_mut()).flat_map(|v| v.inner.iter_mut()))
+    }
+
+    /// clone bank's stored kit slot, not its loaded kit, into other bank's same slot
+    pub fn copy_kit(&mut self, bank: Bank, index: u8) {
+        let index = index as usize;
+        match bank {
+            Bank::A => self.kit_b[index] = self.kit_a[index].clone(),
+            Bank::B => self.kit_a[index] = self.kit_b[index].clone(),
+        }
+    }
+
+    /// preload all onset wavs, sharing contents between onsets of same file
+    pub fn preload(&mut self) {
+        preload(self.kit_a.iter_mut().chain(self.kit_b.iter_mut()).flat_map(|v| v.inner.iter_mut()))
+    }
+}
+
+impl<const N: usize> Default for Scene<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn validate<'a>(pads: impl Iterator<Item = &'a mut Pad>) -> Vec<Box<Path>> {
+    let mut missing: Vec<Box<Path>> = Vec::new();
+    for onset in pads.flat_map(|v| v.onsets.iter_mut()) {
+        if let Some(path) = onset.as_ref().map(|v| &v.wav.path) {
+            if missing.contains(path) || std::fs::File::open(path).is_err() {
+                if !missing.contains(path) {
+                    missing.push(path.clone());
+                }
+                *onset = None;
+            }
+        }
+    }
+    missing
+}
+
+fn preload<'a>(pads: impl Iterator<Item = &'a mut Pad>) {
+    let onsets: Vec<_> = pads.flat_map(|v| v.onsets.iter_mut().flatten()).collect();
+    // streamed files read around every onset start of theirs
+    let mut starts: HashMap<Box<Path>, Vec<u64>> = HashMap::new();
+    for onset in onsets.iter() {
+        starts.entry(onset.wav.path.clone()).or_default().push(onset.start);
+    }
+    let mut cache: HashMap<Box<Path>, super::Wav> = HashMap::new();
+    for onset in onsets {
+        if let Some(wav) = cache.get(&onset.wav.path) {
+            onset.wav.data = wav.data.clone();
+            onset.wav.stream = wav.stream.clone();
+        } else {
+            // unreadable files dropped on trigger
+            let _ = onset.wav.preload(&starts[&onset.wav.path]);
+            cache.insert(onset.wav.path.clone(), onset.wav.clone());
+        }
+    }
 }
 
 struct Mod<T: Copy + std::ops::Mul> {
@@ -112,13 +268,65 @@ impl<T: Copy + std::ops::Mul> Mod<T> {
     }
 }
 
+/// smoothed modulation amount
+#[derive(Default)]
+struct Slew {
+    value: f32,
+    target: f32,
+}
+
+impl Slew {
+    /// advance one output buffer toward target
+    fn tick(&mut self) -> f32 {
+        self.value += (self.target - self.value) * super::TOUCH_SLEW;
+        self.value
+    }
+}
+
 struct BankHandler<const N: usize> {
     gain: f32,
     speed: Mod<f32>,
     drift: f32,
+    phrase_drift: f32,
+    /// trigger probability of recorded events
+    prob: f32,
+    /// step through phrase events last to first
+    phrase_reverse: bool,
     bias: f32,
     width: f32,
+    /// read offset and gain variance per event, 0 on grid
+    humanize: f32,
+    /// aftertouch speed modulation
+    touch_speed: Slew,
+    /// aftertouch width modulation
+    touch_width: Slew,
     reverse: Option<f32>,
+    /// read direction of last grain
+    read_reverse: bool,
+    /// loop current grain rather than advance
+    frozen: bool,
+    mode: super::PadMode,
+    scale: super::Scale,
+    stutter: Option<super::Fraction>,
+    /// read position in steps, advanced between clocks
+    stutter_pos: f32,
+    /// step of last clock
+    stutter_step: f32,
+    crush: fx::Crush,
+    filter: fx::Filter,
+    delay: fx::Delay,
+    tremolo: fx::Tremolo,
+    auto_pan: fx::AutoPan,
+    /// dip from other bank's triggers
+    duck: fx::Duck,
+    /// onset triggered since last read, to duck other bank
+    fired: bool,
+    song: active::Song,
+    /// song section entered or left since last sent to tui
+    song_changed: bool,
+    scratch: Vec<f32>,
+    /// peak magnitude since last level update
+    peak: f32,
 
     kit: Kit<N>,
     input: active::Input,
@@ -132,9 +340,33 @@ impl<const N: usize> BankHandler<N> {
             gain: 1.,
             speed: Mod::new(1., 1.),
             drift: 0.,
+            phrase_drift: 0.,
+            prob: 1.,
+            phrase_reverse: false,
             bias: 0.,
             width: 1.,
+            humanize: 0.,
+            touch_speed: Slew::default(),
+            touch_width: Slew::default(),
             reverse: None,
+            read_reverse: false,
+            frozen: false,
+            mode: super::PadMode::Kit,
+            scale: super::Scale::Chromatic,
+            stutter: None,
+            stutter_pos: 0.,
+            stutter_step: 0.,
+            crush: fx::Crush::new(),
+            filter: fx::Filter::new(),
+            delay: fx::Delay::new(),
+            tremolo: fx::Tremolo::new(),
+            auto_pan: fx::AutoPan::new(),
+            duck: fx::Duck::new(),
+            fired: false,
+            song: active::Song::new(),
+            song_changed: false,
+            scratch: Vec::new(),
+            peak: 0.,
 
             kit: Kit::new(),
             input: active::Input::new(),
@@ -143,10 +375,59 @@ impl<const N: usize> BankHandler<N> {
         }
     }
 
-    fn read_attenuated<T>(&mut self, tempo: f32, buffer: &mut [T], channels: usize) -> Result<()>
-    where
-        T: SizedSample + FromSample<f32>,
-    {
+    #[allow(clippy::too_many_arguments)]
+    fn read_attenuated(&mut self, interp: super::Interp, grain_len: usize, loop_fade: usize, sample_rate: u32, tempo: f32, buffer: &mut [f32], channels: usize) -> Result<()> {
+        // process bank in isolation before summing
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.resize(buffer.len(), 0.);
+        scratch.fill(0.);
+        if let Err(e) = self.read_active(interp, grain_len, loop_fade, sample_rate, tempo, &mut scratch, channels) {
+            // silence bank rather than stall stream
+            self.input.active = active::Event::Sync;
+            self.record.active = None;
+            self.pool.active = None;
+            self.scratch = scratch;
+            return Err(e);
+        }
+        self.tremolo.process(&mut scratch, channels, sample_rate, tempo);
+        self.auto_pan.process(&mut scratch, channels, sample_rate, tempo);
+        self.duck.process(&mut scratch, channels, sample_rate);
+        self.crush.process(&mut scratch, channels);
+        self.filter.process(&mut scratch, channels, sample_rate);
+        self.delay.process(&mut scratch, channels, sample_rate, tempo);
+        for (out, v) in buffer.iter_mut().zip(scratch.iter()) {
+            *out += v;
+            self.peak = self.peak.max(v.abs());
+        }
+        self.scratch = scratch;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn read_active(&mut self, interp: super::Interp, grain_len: usize, loop_fade: usize, sample_rate: u32, tempo: f32, buffer: &mut [f32], channels: usize) -> Result<()> {
+        // aftertouch doubles speed, widens to full
+        let speed = self.speed.net() * (1. + self.touch_speed.tick());
+        let width = self.width + (1. - self.width) * self.touch_width.tick();
+        if tempo > 0. {
+            // advance within step, short of next clock
+            let frames = buffer.len() / channels;
+            let step_frames = sample_rate as f32 * 60. / (tempo * super::STEP_DIV as f32);
+            let pos = (self.stutter_pos + frames as f32 / step_frames).min(self.stutter_step + 0.999);
+            self.stutter(pos)?;
+        }
+        // drop releases shadowed by another active
+        let mut audible = false;
+        let actives = [
+            Some(&mut self.input.active),
+            self.record.active.as_mut().map(|v| &mut v.active),
+            self.pool.active.as_mut().map(|v| &mut v.active),
+        ];
+        for active in actives.into_iter().flatten() {
+            if audible && active.release().is_some() {
+                *active = active::Event::Sync;
+            }
+            audible |= !matches!(active, active::Event::Sync);
+        }
         let active = if !matches!(self.input.active, active::Event::Sync) {
             &mut self.input.active
         } else if self.record.active.as_ref().is_some_and(|v| !matches!(v.active, active::Event::Sync)) {
@@ -157,84 +438,97 @@ impl<const N: usize> BankHandler<N> {
             &mut active::Event::Sync
         };
         if tempo > 0. {
+            let reverse = self.reverse.is_some();
+            if reverse != self.read_reverse {
+                if let active::Event::Hold(onset, ..) | active::Event::Loop(onset, ..) = active {
+                    // crossfade from outgoing direction, turning around at word sounding
+                    let pos = onset.pos()? as i64;
+                    let rem = Self::grain(onset, grain_len, speed * self.mode.pitch(onset.index, self.scale), tempo, sample_rate).1 as i64;
+                    // reversed grains read down from one grain above
+                    onset.jump(if reverse { pos - rem } else { pos }, true)?;
+                }
+                self.read_reverse = reverse;
+            }
             if let active::Event::Hold(onset, ..) = active {
-                return Self::read_grain(onset, self.gain, self.speed.net(), self.width, self.reverse.is_some(), tempo, buffer, channels);
+                let gain = self.gain * self.kit.inner[onset.index as usize].gain * onset.vel;
+                let speed = speed * self.mode.pitch(onset.index, self.scale);
+                Self::read_grain(onset, interp, grain_len, loop_fade, sample_rate, gain, speed, width, self.read_reverse, self.frozen, tempo, buffer, channels)?;
             } else if let active::Event::Loop(onset, _, len) = active {
-                let wav = &mut onset.wav;
-                let pos = wav.pos()?;
+                // pitched loops span more or less file in same steps
+                let pitch = self.mode.pitch(onset.index, self.scale);
+                let pos = onset.pos()?;
+                let wav = &onset.wav;
                 let len = if let Some(steps) = wav.steps {
-                    (f32::from(*len) * wav.len as f32 / steps as f32) as u64 & !1
+                    (f32::from(*len) * pitch * wav.len as f32 / steps as f32) as u64 & wav.format.align()
                 } else {
-                    (f32::from(*len) * super::SAMPLE_RATE as f32 * 60. / tempo * super::LOOP_DIV as f32) as u64 & !1
+                    wav.span(f32::from(*len) * pitch * 60. / tempo * super::LOOP_DIV as f32)
                 };
                 let end = onset.start + len;
                 if pos > end || pos < onset.start && pos + wav.len > end {
-                    if self.reverse.is_some() {
-                        wav.seek(end as i64)?;
-                    } else {
-                        wav.seek(onset.start as i64)?;
-                    }
+                    // crossfade outgoing words into loop
+                    let offset = if self.read_reverse { end } else { onset.start };
+                    onset.jump(offset as i64, true)?;
                 }
-                return Self::read_grain(onset, self.gain, self.speed.net(), self.width, self.reverse.is_some(), tempo, buffer, channels);
+                let gain = self.gain * self.kit.inner[onset.index as usize].gain * onset.vel;
+                Self::read_grain(onset, interp, grain_len, loop_fade, sample_rate, gain, speed * pitch, width, self.read_reverse, self.frozen, tempo, buffer, channels)?;
             }
+            if active.release().is_some_and(|v| v.env <= 0.) {
+                *active = active::Event::Sync;
+            }
+        } else if active.release().is_some() {
+            *active = active::Event::Sync;
         }
         Ok(())
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn read_grain<T>(onset: &mut active::Onset, gain: f32, speed: f32, width: f32, reverse: bool, tempo: f32, buffer: &mut [T], channels: usize) -> Result<()>
-    where
-        T: SizedSample + FromSample<f32>,
-    {
+    /// pad index and alt of sounding onset, by read priority
+    fn playing(&self) -> Option<(u8, bool)> {
+        let actives = [
+            Some(&self.input.active),
+            self.record.active.as_ref().map(|v| &v.active),
+            self.pool.active.as_ref().map(|v| &v.active),
+        ];
+        actives.into_iter().flatten().find_map(|v| match v {
+            active::Event::Hold(onset, ..) | active::Event::Loop(onset, ..) => Some((onset.index, onset.alt)),
+            _ => None,
+        })
+    }
+
+    /// net read speed and grain length in bytes
+    fn grain(onset: &active::Onset, grain_len: usize, speed: f32, tempo: f32, sample_rate: u32) -> (f32, usize) {
         let speed = if let Some(t) = onset.wav.tempo {
             tempo * super::STEP_DIV as f32 / t * speed
         } else {
             speed
         };
-        let rem = (super::GRAIN_LEN as f32 * 2. * speed) as usize & !1;
-        let mut read = vec![0u8; rem + 2];
-        let mut slice = &mut read[..];
-        let wav = &mut onset.wav;
-        // read grain
-        while !slice.is_empty() {
-            let n = wav.file.read(slice)?;
-            if n == 0 {
-                wav.seek(0)?;
-            }
-            slice = &mut slice[n..];
-        }
-        // resync from reading extra word for interpolation
-        let pos = wav.pos()?;
-        if reverse {
-            wav.seek(pos as i64 - rem as i64 * 2 - 2)?;
-        } else {
-            wav.seek(pos as i64 - 2)?;
-        }
-        // resample via linear interpolation
+        // match file rate to output rate
+        let speed = speed * onset.wav.rate as f32 / sample_rate as f32;
+        let format = onset.wav.format;
+        let rem = (grain_len as f32 * format.width() as f32 * speed) as usize & format.align() as usize;
+        (speed, rem)
+    }
+
+    /// mix grains of `grain_len` frames into buffer, each overlapping the next by `loop_fade` frames across callbacks
+    #[allow(clippy::too_many_arguments)]
+    fn read_grain(onset: &mut active::Onset, interp: super::Interp, grain_len: usize, loop_fade: usize, sample_rate: u32, gain: f32, speed: f32, width: f32, reverse: bool, frozen: bool, tempo: f32, buffer: &mut [f32], channels: usize) -> Result<()> {
+        let (speed, rem) = Self::grain(onset, grain_len, speed, tempo, sample_rate);
+        let fade = loop_fade.min(grain_len);
         for i in 0..buffer.len() / channels {
-            let read_idx = if reverse {
-                (rem / 2 - 1) as f32- i as f32 * speed
-            } else {
-                i as f32 * speed
-            };
-            let mut i16_buffer = [0u8; 2];
-            // FIXME: support alternative channel counts?
-            assert!(channels == 2);
-            // handle float shenanigans(?)
-            let sample = if read_idx as usize * 2 + 4 < read.len() {
-                i16_buffer.copy_from_slice(&read[read_idx as usize * 2..][0..2]);
-                let word_a = i16::from_le_bytes(i16_buffer) as f32 / i16::MAX as f32 * read_idx.fract();
-                i16_buffer.copy_from_slice(&read[read_idx as usize * 2..][2..4]);
-                let word_b = i16::from_le_bytes(i16_buffer) as f32 / i16::MAX as f32 * (1. - read_idx.fract());
-                word_a + word_b
-            } else {
-                i16_buffer.copy_from_slice(&read[read_idx as usize * 2..][0..2]);
-                i16::from_le_bytes(i16_buffer) as f32 / i16::MAX as f32
-            };
+            if onset.restart.is_some() || onset.grain.as_ref().is_none_or(|v| v.age() >= grain_len) {
+                onset.launch(interp, speed, rem, fade, reverse, frozen)?;
+            }
+            let sample = onset.sample(interp, fade) * onset.tick_env();
             let l = sample * (1. + width * ((onset.pan - 0.5).abs() - 1.)) * gain;
             let r = sample * (1. + width * ((onset.pan + 0.5).abs() - 1.)) * gain;
-            buffer[i * channels] = buffer[i * channels].add_amp(T::from_sample(l).to_signed_sample());
-            buffer[i * channels + 1] = buffer[i * channels + 1].add_amp(T::from_sample(r).to_signed_sample());
+            let frame = &mut buffer[i * channels..][..channels];
+            if let [mono] = frame {
+                // fold stereo down to mono
+                *mono += (l + r) / 2.;
+            } else {
+                // spread across first two channels
+                frame[0] += l;
+                frame[1] += r;
+            }
         }
         Ok(())
     }
@@ -244,22 +538,95 @@ impl<const N: usize> BankHandler<N> {
             super::BankCmd::AssignGain(v) => self.gain = v,
             super::BankCmd::AssignSpeed(v) => self.speed.base = v,
             super::BankCmd::AssignDrift(v) => self.drift = v,
+            super::BankCmd::AssignPhraseDrift(v) => self.phrase_drift = v,
+            super::BankCmd::AssignPhraseReverse(v) => self.phrase_reverse = v,
+            super::BankCmd::AssignPoolOrder(v) => self.pool.order = v,
+            super::BankCmd::AssignScale(v) => self.scale = v,
+            super::BankCmd::AssignPadMode(v) => {
+                self.mode = v;
+                self.kit.root = v.root();
+            }
+            super::BankCmd::AssignProb(v) => self.prob = v,
             super::BankCmd::AssignBias(v) => self.bias = v,
             super::BankCmd::AssignWidth(v) => self.width = v,
+            super::BankCmd::AssignTouch(super::Touch::Speed, v) => self.touch_speed.target = v,
+            super::BankCmd::AssignTouch(super::Touch::Width, v) => self.touch_width.target = v,
+            super::BankCmd::AssignStutter(v) => self.stutter = v,
+            super::BankCmd::AssignFrozen(v) => self.frozen = v,
+            super::BankCmd::AssignPadGain(index, v) => self.kit.inner[index as usize].gain = v,
+            super::BankCmd::AssignCutoff(v) => self.filter.cutoff = v,
+            super::BankCmd::AssignResonance(v) => self.filter.resonance = v,
+            super::BankCmd::AssignFeedback(v) => self.delay.feedback = v,
+            super::BankCmd::AssignWet(v) => self.delay.wet = v,
+            super::BankCmd::AssignDelayBeats(v) => self.delay.beats = v.into(),
+            super::BankCmd::AssignCrushDepth(v) => self.crush.depth = v,
+            super::BankCmd::AssignCrushRate(v) => self.crush.rate = v,
+            super::BankCmd::AssignTremoloDepth(v) => self.tremolo.depth = v,
+            super::BankCmd::AssignTremoloPeriod(v) => self.tremolo.lfo.rate = fx::Rate::Synced(v),
+            super::BankCmd::AssignTremoloShape(v) => self.tremolo.lfo.shape = v,
+            super::BankCmd::AssignPanDepth(v) => self.auto_pan.depth = v,
+            super::BankCmd::AssignPanRate(v) => self.auto_pan.lfo.rate = v,
+            super::BankCmd::AssignDuckDepth(v) => self.duck.depth = v,
+            super::BankCmd::AssignHumanize(v) => self.humanize = v,
+            // armed by audio handler, which knows bar length
+            super::BankCmd::ArmRecord(_) => (),
+            super::BankCmd::AssignDuckRelease(v) => self.duck.release = v,
             super::BankCmd::AssignReverse(v) => self.assign_reverse(clock, v),
             super::BankCmd::AssignKit(index) => kits[index as usize] = self.kit.clone(),
-            super::BankCmd::LoadKit(index) => self.kit = kits[index as usize].clone(),
+            super::BankCmd::LoadKit(index) => {
+                self.kit = kits[index as usize].clone();
+                self.kit.root = self.mode.root();
+            }
             super::BankCmd::AssignOnset(index, alt, onset) => self.assign_onset(clock, index, alt, *onset)?,
+            super::BankCmd::NudgeOnset(index, alt, delta) => self.nudge_onset(index, alt, delta)?,
             super::BankCmd::ForceEvent(event) => self.force_event(clock, event)?,
             super::BankCmd::PushEvent(event) => self.push_event(quant, clock, event)?,
             super::BankCmd::TakeRecord(index) => self.take_record(index),
+            super::BankCmd::AssignPhrase(index, phrase) => self.assign_phrase(index, *phrase),
             super::BankCmd::BakeRecord(len) => self.bake_record(clock, len)?,
             super::BankCmd::ClearPool => self.clear_pool(),
             super::BankCmd::PushPool(index) => self.pool.phrases.push(index),
+            super::BankCmd::PushSection => {
+                self.song.sections.push(active::Section {
+                    phrases: self.pool.phrases.clone(),
+                    order: self.pool.order,
+                    repeats: 1,
+                    drift: self.drift,
+                    phrase_drift: self.phrase_drift,
+                    bias: self.bias,
+                    prob: self.prob,
+                });
+                self.song_changed = true;
+            }
+            super::BankCmd::RepeatSection => if let Some(section) = self.song.sections.last_mut() {
+                section.repeats += 1;
+                self.song_changed = true;
+            }
+            super::BankCmd::ToggleSong => {
+                if self.song.index.is_some() {
+                    self.song.stop();
+                    self.song.sections.clear();
+                } else if let Some(section) = self.song.start() {
+                    self.enter_section(section);
+                }
+                self.song_changed = true;
+            }
+            super::BankCmd::ClearPhrase(index) => self.clear_phrase(index),
         }
         Ok(())
     }
 
+    /// onset's read offset in steps, drawn with its gain variance once per event
+    fn jitter(onset: &mut active::Onset, humanize: f32) -> f32 {
+        *onset.jitter.get_or_insert_with(|| {
+            if humanize <= 0. {
+                return 0.;
+            }
+            onset.vel *= 1. - rand::random_range(0.0..=super::HUMANIZE_GAIN) * humanize;
+            rand::random_range(-1.0..=1.) * super::HUMANIZE_STEPS * humanize
+        })
+    }
+
     fn assign_reverse(&mut self, clock: f32, reverse: bool) {
         if reverse {
             self.reverse = Some(clock);
@@ -270,11 +637,29 @@ impl<const N: usize> BankHandler<N> {
 
     fn assign_onset(&mut self, clock: f32, index: u8, alt: bool, onset: super::Onset) -> Result<()> {
         self.kit.inner[index as usize].onsets[alt as usize] = Some(onset);
-        self.input.active.trans(&super::Event::Hold { index }, clock as u16, self.bias, &self.kit)?;
+        self.input.active.trans(&super::Event::Hold { index, vel: 1. }, clock as u16, self.bias, &self.kit)?;
+        Ok(())
+    }
+
+    fn nudge_onset(&mut self, index: u8, alt: bool, delta: i64) -> Result<()> {
+        let Some(onset) = self.kit.inner[index as usize].onsets[alt as usize].as_mut() else {
+            return Ok(());
+        };
+        // preloaded header, so decoded onsets aren't decoded again
+        let format = match (&onset.wav.data, &onset.wav.stream) {
+            (Some(data), _) => active::Header::parse(&mut std::io::Cursor::new(data.clone()))?.format,
+            (None, Some(stream)) => stream.header().format,
+            (None, None) => return Ok(()),
+        };
+        let width = format.width() as i64;
+        let start = (onset.start as i64 + delta * width).clamp(0, (onset.wav.len as i64 - width).max(0));
+        onset.start = start as u64 & format.align();
         Ok(())
     }
 
-    fn clock(&mut self, clock: f32) -> Result<()> {
+    fn clock(&mut self, clock: f32, swing: f32) -> Result<()> {
+        // delay off-steps by reading behind clock
+        let lag = if clock as u32 % 2 == 1 { (swing - 0.5) * 2. } else { 0. };
         if let Some(input) = self.input.buffer.take() {
             self.process_input(clock, input)?;
         } else {
@@ -286,20 +671,23 @@ impl<const N: usize> BankHandler<N> {
             ];
             for active in actives.into_iter().flatten() {
                 match active {
-                    active::Event::Hold(onset, step) => {
-                        let wav = &mut onset.wav;
+                    // pitched reads drift from clock
+                    active::Event::Hold(onset, step) if self.stutter.is_none() && !self.frozen && self.mode.root().is_none() => {
+                        let jitter = Self::jitter(onset, self.humanize);
+                        let wav = &onset.wav;
                         if let Some(steps) = wav.steps {
-                            let clock = self.reverse.unwrap_or(clock);
-                            let offset = (wav.len as f32 / steps as f32 * (clock - *step as f32)) as i64 & !1;
-                            wav.seek(onset.start as i64 + offset)?;
+                            let clock = self.reverse.unwrap_or(clock) - lag;
+                            let offset = (wav.len as f32 / steps as f32 * (clock - *step as f32 - jitter).max(0.)) as i64 & wav.format.align() as i64;
+                            onset.jump(onset.start as i64 + offset, false)?;
                         }
                     }
-                    active::Event::Loop(onset, step, len) => {
-                        let wav = &mut onset.wav;
+                    active::Event::Loop(onset, step, len) if !self.frozen && self.mode.root().is_none() => {
+                        let jitter = Self::jitter(onset, self.humanize);
+                        let wav = &onset.wav;
                         if let Some(steps) = wav.steps {
-                            let clock = self.reverse.unwrap_or(clock);
-                            let offset = (wav.len as f32 / steps as f32 * ((clock - *step as f32).rem_euclid(f32::from(*len)))) as i64 & !1;
-                            wav.seek(onset.start as i64 + offset)?;
+                            let clock = self.reverse.unwrap_or(clock) - lag;
+                            let offset = (wav.len as f32 / steps as f32 * ((clock - *step as f32 - jitter).rem_euclid(f32::from(*len)))) as i64 & wav.format.align() as i64;
+                            onset.jump(onset.start as i64 + offset, false)?;
                         }
                     }
                     _ => (),
@@ -307,16 +695,45 @@ impl<const N: usize> BankHandler<N> {
             }
         }
         self.tick_phrases(clock)?;
+        self.stutter_step = clock;
+        self.tremolo.lfo.sync(clock);
+        self.auto_pan.lfo.sync(clock);
+        self.stutter(clock)?;
         if let Some(clock) = self.reverse.as_mut() {
             *clock -= 1.;
         }
         Ok(())
     }
 
-    fn stop(&mut self) {
+    /// retrigger held onsets if pos crosses a stutter period
+    fn stutter(&mut self, pos: f32) -> Result<()> {
+        let prev = std::mem::replace(&mut self.stutter_pos, pos);
+        let Some(period) = self.stutter.map(f32::from).filter(|&v| v > 0.) else {
+            return Ok(());
+        };
+        if (pos / period).floor() == (prev / period).floor() {
+            return Ok(());
+        }
+        let actives = [
+            Some(&mut self.input.active),
+            self.record.active.as_mut().map(|v| &mut v.active),
+            self.pool.active.as_mut().map(|v| &mut v.active),
+        ];
+        for active in actives.into_iter().flatten() {
+            if let active::Event::Hold(onset, ..) = active {
+                // crossfade from outgoing words into start
+                onset.jump(onset.start as i64, true)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// move clock from `from` to `to`, as on stop or song position
+    fn seek(&mut self, from: f32, to: f32) {
         if let Some(clock) = self.reverse.as_mut() {
-            *clock = 0.;
+            *clock = to;
         }
+        self.record.seek(from as u16, to as u16);
     }
 
     fn offset_speed(&mut self, v: f32) {
@@ -337,6 +754,15 @@ impl<const N: usize> BankHandler<N> {
         Ok(())
     }
 
+    /// drop input and phrase events, ending pool
+    fn panic(&mut self) {
+        self.input = active::Input::new();
+        self.clear_pool();
+        for active in [self.record.active.as_mut(), self.pool.active.as_mut()].into_iter().flatten() {
+            active.active = active::Event::Sync;
+        }
+    }
+
     fn take_record(&mut self, index: Option<u8>) {
         if let Some((phrase, active)) = self.record.take() {
             if let Some(index) = index {
@@ -350,12 +776,19 @@ impl<const N: usize> BankHandler<N> {
         }
     }
 
+    fn assign_phrase(&mut self, index: u8, phrase: super::Phrase) {
+        self.kit.inner[index as usize].phrase = Some(phrase);
+        // end playing phrase; new phrase generated next step
+        self.clear_pool();
+        self.pool.phrases.push(index);
+    }
+
     fn bake_record(&mut self, clock: f32, len: u16) -> Result<()> {
         if self.record.active.is_none() {
             self.record.bake(clock as u16);
         }
         self.record.trim(len);
-        self.record.generate_phrase(clock as u16, self.bias, self.drift, &self.kit)?;
+        self.record.generate_phrase(clock as u16, self.bias, self.drift, self.phrase_reverse, &self.kit)?;
         Ok(())
     }
 
@@ -367,9 +800,34 @@ impl<const N: usize> BankHandler<N> {
         }
     }
 
+    /// swap in section's pool and params, ending playing phrase
+    fn enter_section(&mut self, section: active::Section) {
+        self.clear_pool();
+        self.pool.phrases = section.phrases;
+        self.pool.order = section.order;
+        self.drift = section.drift;
+        self.phrase_drift = section.phrase_drift;
+        self.bias = section.bias;
+        self.prob = section.prob;
+        self.song_changed = true;
+    }
+
+    fn clear_phrase(&mut self, index: u8) {
+        self.kit.inner[index as usize].phrase = None;
+        self.pool.phrases.retain(|v| *v != index);
+        if self.pool.index == Some(index) {
+            // end playing phrase; next pool phrase, if any, generated next step
+            self.pool.index = None;
+            if let Some(active) = self.pool.active.as_mut() {
+                active.phrase_rem = 0;
+            }
+        }
+    }
+
     fn process_input(&mut self, clock: f32, event: super::Event) -> Result<()> {
+        self.fired |= !matches!(event, super::Event::Sync);
         self.input.active.trans(&event, clock as u16, self.bias, &self.kit)?;
-        self.record.push(event, clock as u16);
+        self.record.push(event, clock as u16, self.prob);
         if let Some(reverse) = &mut self.reverse {
             *reverse = clock;
         }
@@ -383,16 +841,17 @@ impl<const N: usize> BankHandler<N> {
             event_rem,
             phrase_rem,
             active,
+            pass,
         }) = self.record.active.as_mut() {
             *event_rem = event_rem.saturating_sub(1);
             *phrase_rem = phrase_rem.saturating_sub(1);
             if *phrase_rem == 0 {
                 // generate next phrase from record
-                self.record.generate_phrase(clock as u16, self.bias, self.drift, &self.kit)?;
+                self.record.generate_phrase(clock as u16, self.bias, self.drift, self.phrase_reverse, &self.kit)?;
             } else if *event_rem == 0 {
                 // generate next event from record
                 if let Some(phrase) = self.record.phrase.as_mut() {
-                    if let Some(rem) = phrase.generate_stamped(active, *next, clock as u16, self.bias, self.drift, &self.kit)? {
+                    if let Some(rem) = phrase.generate_stamped(active, *next, *pass, clock as u16, self.bias, self.drift, self.phrase_reverse, &self.kit)? {
                         *next += 1;
                         *event_rem = rem;
                     }
@@ -405,16 +864,22 @@ impl<const N: usize> BankHandler<N> {
             event_rem,
             phrase_rem,
             active,
+            pass,
         }) = self.pool.active.as_mut() {
             *event_rem = event_rem.saturating_sub(1);
             *phrase_rem = phrase_rem.saturating_sub(1);
             if *phrase_rem == 0 {
+                // song moves on once section's passes done
+                if let Some(section) = self.song.advance() {
+                    self.enter_section(section);
+                }
+                self.song_changed |= self.song.index.is_some();
                 // generate next phrase from pool
-                self.pool.generate_phrase(clock as u16, self.bias, self.drift, &self.kit)?;
+                self.pool.generate_phrase(clock as u16, self.bias, self.drift, self.phrase_drift, self.phrase_reverse, &self.kit)?;
             } else if *event_rem == 0 {
                 // generate next event from pool
                 if let Some(phrase) = self.pool.index.and_then(|v| self.kit.inner[v as usize].phrase.as_ref()) {
-                    if let Some(rem) = phrase.generate_stamped(active, *next, clock as u16, self.bias, self.drift, &self.kit)? {
+                    if let Some(rem) = phrase.generate_stamped(active, *next, *pass, clock as u16, self.bias, self.drift, self.phrase_reverse, &self.kit)? {
                         *next += 1;
                         *event_rem = rem;
                     }
@@ -422,76 +887,528 @@ impl<const N: usize> BankHandler<N> {
             }
         } else if !self.pool.phrases.is_empty() {
             // generate first phrase from pool
-            self.pool.generate_phrase(clock as u16, self.bias, self.drift, &self.kit)?;
+            self.pool.generate_phrase(clock as u16, self.bias, self.drift, self.phrase_drift, self.phrase_reverse, &self.kit)?;
         }
         Ok(())
     }
 }
 
+/// bank handed to worker with its params, and back with its render
+struct Job<const N: usize> {
+    bank: Box<BankHandler<N>>,
+    mix: Vec<f32>,
+    interp: super::Interp,
+    grain_len: usize,
+    loop_fade: usize,
+    sample_rate: u32,
+    tempo: f32,
+    channels: usize,
+    result: Result<()>,
+    /// callback thread awaiting render
+    caller: std::thread::Thread,
+}
+
+impl<const N: usize> Job<N> {
+    fn render(&mut self) {
+        self.mix.fill(0.);
+        self.result = self.bank.read_attenuated(self.interp, self.grain_len, self.loop_fade, self.sample_rate, self.tempo, &mut self.mix, self.channels);
+    }
+}
+
+/// job states; queued job taken by whichever of worker and callback swaps it to taken first
+const IDLE: u8 = 0;
+const QUEUED: u8 = 1;
+const TAKEN: u8 = 2;
+const DONE: u8 = 3;
+
+/// job slot, owned by worker while taken and by callback otherwise, handed over by state alone
+struct Handoff<const N: usize> {
+    state: std::sync::atomic::AtomicU8,
+    job: std::cell::UnsafeCell<Option<Job<N>>>,
+    exit: std::sync::atomic::AtomicBool,
+}
+
+// SAFETY: job only touched by the side owning it as given by state, whose release stores publish it
+unsafe impl<const N: usize> Sync for Handoff<N> where Job<N>: Send {}
+
+impl<const N: usize> Handoff<N> {
+    /// SAFETY: caller must own job as given by state
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn job(&self) -> &mut Option<Job<N>> {
+        unsafe { &mut *self.job.get() }
+    }
+}
+
+/// renders one bank on its own thread while the callback renders the other
+struct Worker<const N: usize> {
+    handoff: std::sync::Arc<Handoff<N>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+    /// left in place of bank while away, so none allocated per callback
+    spare: Option<Box<BankHandler<N>>>,
+    mix: Option<Vec<f32>>,
+    /// when callback stops waiting on render
+    deadline: std::time::Instant,
+}
+
+impl<const N: usize> Worker<N> {
+    fn new() -> Self {
+        let handoff = std::sync::Arc::new(Handoff { state: IDLE.into(), job: None.into(), exit: false.into() });
+        let shared = handoff.clone();
+        let thread = std::thread::spawn(move || loop {
+            if shared.exit.load(Ordering::Acquire) {
+                return;
+            }
+            if shared.state.compare_exchange(QUEUED, TAKEN, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+                // SAFETY: taken job owned by worker until done
+                let job = unsafe { shared.job() }.as_mut().unwrap();
+                job.render();
+                let caller = job.caller.clone();
+                shared.state.store(DONE, Ordering::Release);
+                caller.unpark();
+            }
+            // wakes may be spurious
+            std::thread::park();
+        });
+        Self {
+            handoff,
+            thread: Some(thread),
+            spare: Some(Box::new(BankHandler::new())),
+            mix: Some(Vec::new()),
+            deadline: std::time::Instant::now(),
+        }
+    }
+
+    /// hand bank to worker, leaving spare in its place until finish
+    #[allow(clippy::too_many_arguments)]
+    fn start(&mut self, bank: &mut Box<BankHandler<N>>, interp: super::Interp, grain_len: usize, loop_fade: usize, sample_rate: u32, tempo: f32, len: usize, channels: usize) {
+        let (Some(spare), Some(mut mix)) = (self.spare.take(), self.mix.take()) else {
+            return;
+        };
+        mix.resize(len, 0.);
+        let period = (len / channels) as f32 / sample_rate as f32;
+        self.deadline = std::time::Instant::now() + std::time::Duration::from_secs_f32(period * super::WORKER_WAIT);
+        // SAFETY: idle job owned by callback
+        *unsafe { self.handoff.job() } = Some(Job {
+            bank: std::mem::replace(bank, spare),
+            mix,
+            interp,
+            grain_len,
+            loop_fade,
+            sample_rate,
+            tempo,
+            channels,
+            result: Ok(()),
+            caller: std::thread::current(),
+        });
+        self.handoff.state.store(QUEUED, Ordering::Release);
+        if let Some(thread) = self.thread.as_ref() {
+            thread.thread().unpark();
+        }
+    }
+
+    /// take bank back from worker, restoring it and summing its render into buffer. rendered here
+    /// if worker hasn't started, else waited on until deadline, past which bank stays away and
+    /// its render is dropped by reclaim
+    fn finish(&mut self, bank: &mut Box<BankHandler<N>>, buffer: &mut [f32]) -> Result<()> {
+        if self.spare.is_some() {
+            // nothing in flight
+            return Ok(());
+        }
+        let inline = self.handoff.state.compare_exchange(QUEUED, TAKEN, Ordering::AcqRel, Ordering::Relaxed).is_ok();
+        if !inline {
+            while self.handoff.state.load(Ordering::Acquire) != DONE {
+                let now = std::time::Instant::now();
+                if now >= self.deadline {
+                    return Err(color_eyre::eyre::eyre!("render missed deadline"));
+                }
+                std::thread::park_timeout(self.deadline - now);
+            }
+        }
+        // SAFETY: job owned by callback once done, or once taken by callback
+        let mut job = unsafe { self.handoff.job() }.take().unwrap();
+        self.handoff.state.store(IDLE, Ordering::Release);
+        if inline {
+            job.render();
+        }
+        self.spare = Some(std::mem::replace(bank, job.bank));
+        for (out, v) in buffer.iter_mut().zip(job.mix.iter()) {
+            *out += v;
+        }
+        self.mix = Some(job.mix);
+        job.result
+    }
+
+    /// restore bank left away by a late render, dropping its render. worker has had a whole
+    /// callback period since, so waits only as long as a render overrunning by that much
+    fn reclaim(&mut self, bank: &mut Box<BankHandler<N>>) {
+        if self.spare.is_some() {
+            return;
+        }
+        while self.handoff.state.load(Ordering::Acquire) != DONE {
+            std::thread::park();
+        }
+        // SAFETY: done job owned by callback
+        let job = unsafe { self.handoff.job() }.take().unwrap();
+        self.handoff.state.store(IDLE, Ordering::Release);
+        self.spare = Some(std::mem::replace(bank, job.bank));
+        self.mix = Some(job.mix);
+    }
+}
+
+impl<const N: usize> Drop for Worker<N> {
+    fn drop(&mut self) {
+        // wake worker to see exit
+        self.handoff.exit.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            thread.join().ok();
+        }
+    }
+}
+
 pub struct AudioHandler<const N: usize> {
     quant: bool,
     clock: f32,
     tempo: f32,
+    sample_rate: u32,
+    interp: super::Interp,
+    /// grain length in words
+    grain_len: usize,
+    /// crossfade between grains and across seeks, in frames
+    loop_fade: usize,
+    swing: f32,
+    limit: bool,
+    /// render bank b on worker rather than after bank a
+    parallel: bool,
     scene: Scene<N>,
 
+    /// run internal clock, sending midi clock
+    master: bool,
+    /// output frames until next internal clock tick
+    tick_rem: f32,
+    /// internal clock ticks into step
+    ticks: u8,
+    /// output frames since last external clock step
+    slave_len: u32,
+    /// output frames since last level update
+    level_len: u32,
+    /// sounding onsets last sent to tui
+    playing: [Option<(u8, bool)>; 2],
+    /// output wav writer, if bouncing
+    bounce: Option<super::bounce::Tap>,
+    click: fx::Click,
+
     bank_a: BankHandler<N>,
-    bank_b: BankHandler<N>,
+    /// boxed to hand to worker without copying
+    bank_b: Box<BankHandler<N>>,
+    mix: Vec<f32>,
+    worker: Worker<N>,
 
-    cmd_rx: std::sync::mpsc::Receiver<super::Cmd<N>>,
+    cmd_rx: super::CmdRx<N>,
+    tui_tx: std::sync::mpsc::Sender<tui::Cmd>,
+    clock_out: Option<super::clock_out::ClockOut>,
 }
 
 impl<const N: usize> AudioHandler<N> {
-    pub fn new(cmd_rx: std::sync::mpsc::Receiver<super::Cmd<N>>) -> Self {
+    pub fn new(
+        cmd_rx: super::CmdRx<N>,
+        tui_tx: std::sync::mpsc::Sender<tui::Cmd>,
+        clock_out: Option<midir::MidiOutputConnection>,
+        sample_rate: u32,
+    ) -> Self {
         Self {
             quant: false,
             clock: 0.,
             tempo: 0.,
+            sample_rate,
+            interp: super::Interp::default(),
+            grain_len: super::GRAIN_LEN,
+            loop_fade: super::LOOP_FADE,
+            swing: 0.5,
+            limit: false,
+            parallel: true,
             scene: Scene::new(),
 
+            master: false,
+            tick_rem: 0.,
+            ticks: 0,
+            slave_len: u32::MAX,
+            level_len: 0,
+            playing: [None; 2],
+            bounce: None,
+            click: fx::Click::new(),
+
             bank_a: BankHandler::new(),
-            bank_b: BankHandler::new(),
+            bank_b: Box::new(BankHandler::new()),
+            mix: Vec::new(),
+            worker: Worker::new(),
 
+            clock_out: clock_out.map(|mut v| super::clock_out::ClockOut::new(move |bytes| v.send(bytes).is_ok(), tui_tx.clone())),
             cmd_rx,
+            tui_tx,
         }
     }
 
-    pub fn tick<T>(&mut self, buffer: &mut [T], channels: usize) -> Result<()>
+    /// rate of stream rebuilt on another device
+    pub fn assign_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// crossfade length in frames, as from config; kept within grain length as played
+    pub fn assign_loop_fade(&mut self, loop_fade: usize) {
+        self.loop_fade = loop_fade;
+    }
+
+    /// render banks on callback alone rather than bank b on worker, as for comparing them
+    pub fn assign_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    /// fill output buffer, silencing it and logging to tui on error
+    pub fn tick<T>(&mut self, buffer: &mut [T], channels: usize)
     where
         T: SizedSample + FromSample<f32>,
     {
-        while let Ok(cmd) = self.cmd_rx.try_recv() {
+        if let Err(e) = self.try_tick(buffer, channels) {
+            buffer.fill(T::EQUILIBRIUM);
+            // tui may have exited before audio
+            self.tui_tx.send(tui::Cmd::Error(format!("audio error: {}", e))).ok();
+        }
+    }
+
+    fn try_tick<T>(&mut self, buffer: &mut [T], channels: usize) -> Result<()>
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        // bank b back from late render before commands reach it
+        self.worker.reclaim(&mut self.bank_b);
+        while let Some(cmd) = self.cmd_rx.try_recv() {
             match cmd {
-                super::Cmd::Clock => self.clock()?,
+                super::Cmd::Clock => {
+                    self.slave_len = 0;
+                    self.clock()?;
+                }
                 super::Cmd::Stop => self.stop(),
-                super::Cmd::AssignTempo(v) => self.tempo = v,
+                super::Cmd::Seek(v) => self.seek(v as f32),
+                // keeps loop lengths and offsets finite
+                super::Cmd::AssignTempo(v) => if let Some(v) = super::clamp_tempo(v) {
+                    self.tempo = v;
+                }
                 super::Cmd::OffsetSpeed(v) => self.offset_speed(v),
-                super::Cmd::SaveScene(v) => self.save_scene(v)?,
+                super::Cmd::AssignInterp(v) => self.interp = v,
+                super::Cmd::AssignSwing(v) => self.swing = v.clamp(0.5, super::SWING_MAX),
+                super::Cmd::AssignGrainLen(v) => self.grain_len = v.clamp(super::GRAIN_LEN_MIN, super::GRAIN_LEN_MAX) & !1,
+                super::Cmd::AssignLimit(v) => self.limit = v,
+                super::Cmd::AssignMaster(v) => self.assign_master(v)?,
+                super::Cmd::Panic => {
+                    self.bank_a.panic();
+                    self.bank_b.panic();
+                }
+                super::Cmd::AssignClick(v) => self.click.on = v,
+                super::Cmd::AssignClickLevel(v) => self.click.level = v,
+                super::Cmd::AssignClickBar(v) => self.click.bar = v.clamp(1, super::CLICK_BAR_MAX),
+                // writer of previous take finishes once its tap dropped
+                super::Cmd::AssignBounce(tap) => self.bounce = tap,
+                super::Cmd::SaveScene(file, binary) => self.scene.save(file, binary)?,
                 super::Cmd::LoadScene(v) => self.scene = *v,
+                super::Cmd::ReloadWav(wav) => {
+                    for kit in self.scene.kit_a.iter_mut().chain(self.scene.kit_b.iter_mut()) {
+                        kit.reload(&wav);
+                    }
+                    self.bank_a.kit.reload(&wav);
+                    self.bank_b.kit.reload(&wav);
+                }
+                super::Cmd::CopyKit(bank, index) => self.scene.copy_kit(bank, index),
+                super::Cmd::SaveKit(Bank::A, file) => self.bank_a.kit.save(file)?,
+                super::Cmd::SaveKit(Bank::B, file) => self.bank_b.kit.save(file)?,
+                super::Cmd::StoreKit(Bank::A, index, kit) => self.scene.kit_a[index as usize] = *kit,
+                super::Cmd::StoreKit(Bank::B, index, kit) => self.scene.kit_b[index as usize] = *kit,
+                super::Cmd::SwapBanks => std::mem::swap(&mut self.bank_a, &mut *self.bank_b),
+                super::Cmd::Autosave(file, done) => {
+                    self.scene.save(file, false)?;
+                    done.send(()).ok();
+                }
+                super::Cmd::Bank(bank, super::BankCmd::ArmRecord(bars)) => {
+                    // capture from bar line `bars` bars on
+                    let bar = self.click.bar.max(1) as u32;
+                    let start = (self.clock as u32 / bar + u32::from(bars)) * bar;
+                    match bank {
+                        Bank::A => self.bank_a.record.arm(start as u16),
+                        Bank::B => self.bank_b.record.arm(start as u16),
+                    }
+                }
                 super::Cmd::Bank(bank, cmd) => match bank {
                     Bank::A => self.bank_a.cmd(self.quant, self.clock, &mut self.scene.kit_a, cmd)?,
                     Bank::B => self.bank_b.cmd(self.quant, self.clock, &mut self.scene.kit_b, cmd)?,
                 }
             }
         }
-        buffer.fill(T::EQUILIBRIUM);
-        self.bank_a.read_attenuated(self.tempo, buffer, channels)?;
-        self.bank_b.read_attenuated(self.tempo, buffer, channels)?;
+        self.tick_master(std::time::Instant::now(), buffer.len() / channels)?;
+        self.send_songs();
+        // bank a triggers duck bank b
+        if std::mem::take(&mut self.bank_a.fired) {
+            self.bank_b.duck.trigger();
+        }
+        self.bank_b.fired = false;
+        // mix banks at full precision
+        self.mix.resize(buffer.len(), 0.);
+        self.mix.fill(0.);
+        // banks independent until summed; render b on worker alongside a
+        if self.parallel {
+            self.worker.start(&mut self.bank_b, self.interp, self.grain_len, self.loop_fade, self.sample_rate, self.tempo, buffer.len(), channels);
+        }
+        // failed bank silenced without interrupting other
+        if let Err(e) = self.bank_a.read_attenuated(self.interp, self.grain_len, self.loop_fade, self.sample_rate, self.tempo, &mut self.mix, channels) {
+            self.tui_tx.send(tui::Cmd::Error(format!("bank a silenced: {}", e))).ok();
+        }
+        let b = if self.parallel {
+            self.worker.finish(&mut self.bank_b, &mut self.mix)
+        } else {
+            self.bank_b.read_attenuated(self.interp, self.grain_len, self.loop_fade, self.sample_rate, self.tempo, &mut self.mix, channels)
+        };
+        if let Err(e) = b {
+            self.tui_tx.send(tui::Cmd::Error(format!("bank b silenced: {}", e))).ok();
+        }
+        self.send_levels(buffer.len() / channels);
+        // after levels, so meters show banks alone
+        self.click.process(&mut self.mix, channels, self.sample_rate);
+        if self.limit {
+            // soft-clip sum into [-1, 1]
+            self.mix.iter_mut().for_each(|v| *v = v.tanh());
+        }
+        if let Some(bounce) = self.bounce.as_mut() {
+            bounce.push(&self.mix, self.sample_rate, channels);
+        }
+        for (out, v) in buffer.iter_mut().zip(self.mix.iter()) {
+            *out = T::from_sample(*v);
+        }
         Ok(())
     }
 
     fn clock(&mut self) -> Result<()> {
         self.quant = true;
-        self.bank_a.clock(self.clock)?;
-        self.bank_b.clock(self.clock)?;
+        // click counts in armed records, even if off
+        let counting = self.bank_a.record.start.is_some() || self.bank_b.record.start.is_some();
+        self.click.trigger(self.clock as u32, counting);
+        self.bank_a.clock(self.clock, self.swing)?;
+        self.bank_b.clock(self.clock, self.swing)?;
+        self.send_count_ins();
         self.clock += 1.;
         Ok(())
     }
 
     fn stop(&mut self) {
         self.quant = false;
-        self.bank_a.stop();
-        self.bank_b.stop();
-        self.clock = 0.;
+        self.seek(0.);
+    }
+
+    /// move both banks' clock, rebasing what they stamped against it
+    fn seek(&mut self, clock: f32) {
+        self.bank_a.seek(self.clock, clock);
+        self.bank_b.seek(self.clock, clock);
+        self.clock = clock;
+    }
+
+    fn assign_master(&mut self, master: bool) -> Result<()> {
+        self.master = master;
+        self.stop();
+        // tui may have exited before audio
+        self.tui_tx.send(tui::Cmd::Stop).ok();
+        self.ticks = 0;
+        self.tick_rem = 0.;
+        if master {
+            if self.tempo <= 0. {
+                self.tempo = super::DEFAULT_TEMPO;
+            }
+            self.tui_tx.send(tui::Cmd::Tempo(self.tempo)).ok();
+            self.send_realtime(std::time::Instant::now(), SystemRealtime::Start);
+        } else {
+            self.send_realtime(std::time::Instant::now(), SystemRealtime::Stop);
+        }
+        Ok(())
+    }
+
+    /// advance internal clock by output frames, unless slaved to external clock
+    fn tick_master(&mut self, now: std::time::Instant, frames: usize) -> Result<()> {
+        self.slave_len = self.slave_len.saturating_add(frames as u32);
+        if !self.master || self.slave_len < self.sample_rate || self.tempo <= 0. {
+            return Ok(());
+        }
+        let interval = self.sample_rate as f32 * 60. / (self.tempo * super::PPQ as f32);
+        while self.tick_rem <= frames as f32 {
+            // spaced by frame offset within buffer, trailing render by a callback
+            let offset = self.tick_rem.max(0.) / self.sample_rate as f32;
+            self.tick_rem += interval;
+            self.send_realtime(now + std::time::Duration::from_secs_f32(offset), SystemRealtime::TimingClock);
+            if self.ticks == 0 {
+                self.clock()?;
+                self.tui_tx.send(tui::Cmd::Clock).ok();
+            }
+            self.ticks = (self.ticks + 1) % (super::PPQ / super::STEP_DIV);
+        }
+        self.tick_rem -= frames as f32;
+        Ok(())
+    }
+
+    /// send bank peaks and changed sounding onsets to tui at most LEVEL_RATE times per second
+    fn send_levels(&mut self, frames: usize) {
+        self.level_len += frames as u32;
+        if self.level_len >= self.sample_rate / super::LEVEL_RATE {
+            self.level_len = 0;
+            // tui may have exited before audio
+            self.tui_tx.send(tui::Cmd::Levels(self.bank_a.peak, self.bank_b.peak)).ok();
+            self.bank_a.peak = 0.;
+            self.bank_b.peak = 0.;
+            let playing = [self.bank_a.playing(), self.bank_b.playing()];
+            for (bank, (v, last)) in [Bank::A, Bank::B].into_iter().zip(playing.into_iter().zip(self.playing.iter_mut())) {
+                if v != *last {
+                    *last = v;
+                    self.tui_tx.send(tui::Cmd::Playing(bank, v)).ok();
+                }
+            }
+        }
+    }
+
+    /// send changed song positions, and params of entered sections, to tui
+    fn send_songs(&mut self) {
+        for (bank, handler) in [(Bank::A, &mut self.bank_a), (Bank::B, &mut *self.bank_b)] {
+            if !std::mem::take(&mut handler.song_changed) {
+                continue;
+            }
+            let position = handler.song.position();
+            // tui may have exited before audio
+            self.tui_tx.send(tui::Cmd::Song(bank, position)).ok();
+            if position.is_some() {
+                let cc = |v: f32| (v * 127.).round() as u8;
+                self.tui_tx.send(tui::Cmd::Bank(bank, tui::BankCmd::AssignDrift(cc(handler.drift)))).ok();
+                self.tui_tx.send(tui::Cmd::Bank(bank, tui::BankCmd::AssignPhraseDrift(cc(handler.phrase_drift)))).ok();
+                self.tui_tx.send(tui::Cmd::Bank(bank, tui::BankCmd::AssignBias(cc(handler.bias)))).ok();
+            }
+        }
+    }
+
+    /// steps left of each counting-in record, ending count-in once reached
+    fn send_count_ins(&mut self) {
+        let step = self.clock as u16;
+        for (bank, handler) in [(Bank::A, &mut self.bank_a), (Bank::B, &mut *self.bank_b)] {
+            let Some(start) = handler.record.start else {
+                continue;
+            };
+            let rem = start.saturating_sub(step);
+            if rem == 0 {
+                handler.record.start = None;
+            }
+            self.tui_tx.send(tui::Cmd::CountIn(bank, (rem > 0).then_some(rem))).ok();
+        }
+    }
+
+    /// queue message to clock out at `due`, warning if queue full
+    fn send_realtime(&mut self, due: std::time::Instant, message: SystemRealtime) {
+        if let Some(clock_out) = self.clock_out.as_mut() {
+            if !clock_out.send(due, message) {
+                self.tui_tx.send(tui::Cmd::Error("midi clock queue full".into())).ok();
+            }
+        }
     }
 
     fn offset_speed(&mut self, v: f32) {
@@ -499,9 +1416,951 @@ impl<const N: usize> AudioHandler<N> {
         self.bank_b.offset_speed(v);
     }
 
-    fn save_scene(&mut self, mut file: std::fs::File) -> Result<()> {
-        let json = serde_json::to_string_pretty(&self.scene)?;
-        write!(file, "{}", json)?;
-        Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// preloaded mono wav at file rate over words, starting at first
+    fn assigned(rate: u32, words: &[i16]) -> super::super::Onset {
+        let pcm = words.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>();
+        super::super::Onset {
+            wav: super::super::Wav {
+                tempo: None,
+                steps: None,
+                path: Path::new("memory.wav").into(),
+                len: pcm.len() as u64,
+                data: Some(super::super::bounce::image(rate, &pcm).unwrap().into()),
+                stream: None,
+            },
+            start: 0,
+        }
+    }
+
+    /// preloaded mono onset at file rate over words, starting at first
+    fn onset(rate: u32, words: &[i16]) -> active::Onset {
+        let mut kit = Kit::<1>::new();
+        kit.inner[0].onsets[0] = Some(assigned(rate, words));
+        kit.onset_seek(0u8, false, 0., 1.).unwrap()
+    }
+
+    #[test]
+    fn humanize_draws_once_and_zero_stays_on_grid() {
+        let mut onset = onset(44100, &[0; 64]);
+        for _ in 0..8 {
+            assert_eq!(BankHandler::<1>::jitter(&mut onset, 0.), 0.);
+        }
+        assert_eq!(onset.vel, 1.);
+        // drawn on first sync of event, then kept
+        let mut onset = self::onset(44100, &[0; 64]);
+        let jitter = BankHandler::<1>::jitter(&mut onset, 1.);
+        let vel = onset.vel;
+        assert!(jitter.abs() <= super::super::HUMANIZE_STEPS);
+        assert!((1. - super::super::HUMANIZE_GAIN..=1.).contains(&vel));
+        for _ in 0..8 {
+            assert_eq!(BankHandler::<1>::jitter(&mut onset, 1.), jitter);
+        }
+        assert_eq!(onset.vel, vel);
+    }
+
+    #[test]
+    fn worker_returns_bank_rendered_by_either_thread() {
+        let mut worker = Worker::<1>::new();
+        let mut bank = Box::new(BankHandler::<1>::new());
+        bank.kit.inner[0].onsets[0] = Some(assigned(44100, &[8192; 4096]));
+        bank.force_event(0., super::super::Event::Hold { index: 0, vel: 1. }).unwrap();
+        let home: *const BankHandler<1> = &*bank;
+        let mut inline = 0;
+        for i in 0..200 {
+            let mut buffer = vec![0.; 64];
+            worker.start(&mut bank, super::super::Interp::default(), super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., buffer.len(), 2);
+            assert!(!std::ptr::eq(home, &*bank));
+            // worker given time to take job on odd passes only
+            if i % 2 == 1 {
+                std::thread::sleep(std::time::Duration::from_micros(200));
+            }
+            inline += (worker.handoff.state.load(Ordering::Acquire) == QUEUED) as u32;
+            worker.finish(&mut bank, &mut buffer).unwrap();
+            assert!(std::ptr::eq(home, &*bank));
+            assert!(buffer.iter().any(|v| *v != 0.));
+            assert_eq!(worker.handoff.state.load(Ordering::Acquire), IDLE);
+        }
+        assert!(inline > 0);
+        // finish without start leaves bank in place
+        let mut buffer = vec![0.; 64];
+        worker.finish(&mut bank, &mut buffer).unwrap();
+        assert!(std::ptr::eq(home, &*bank) && buffer.iter().all(|v| *v == 0.));
+    }
+
+    #[test]
+    fn late_render_sits_bank_out_until_reclaimed() {
+        let mut worker = Worker::<1>::new();
+        let mut bank = Box::new(BankHandler::<1>::new());
+        let home: *const BankHandler<1> = &*bank;
+        let mut buffer = vec![0.; 64];
+        worker.start(&mut bank, super::super::Interp::default(), super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., buffer.len(), 2);
+        // job held as if worker were stalled mid render
+        while worker.handoff.state.compare_exchange(QUEUED, TAKEN, Ordering::AcqRel, Ordering::Relaxed).is_err() {
+            if worker.handoff.state.load(Ordering::Acquire) == DONE {
+                worker.handoff.state.store(TAKEN, Ordering::Release);
+                break;
+            }
+        }
+        // returns at deadline though render never done
+        assert!(worker.finish(&mut bank, &mut buffer).is_err());
+        assert!(!std::ptr::eq(home, &*bank));
+        worker.handoff.state.store(DONE, Ordering::Release);
+        worker.reclaim(&mut bank);
+        assert!(std::ptr::eq(home, &*bank));
+        assert_eq!(worker.handoff.state.load(Ordering::Acquire), IDLE);
+        // worker thread joined
+        drop(worker);
+    }
+
+    #[test]
+    fn scene_drops_onsets_of_missing_wavs() {
+        let path = std::env::temp_dir().join(format!("present-{}.wav", std::process::id()));
+        std::fs::write(&path, super::super::bounce::image(44100, &[0; 64]).unwrap()).unwrap();
+        let mut present = assigned(44100, &[0; 32]);
+        present.wav.path = path.clone().into();
+        let missing = Path::new("/nonexistent/missing.wav");
+        let mut absent = assigned(44100, &[0; 32]);
+        absent.wav.path = missing.into();
+        let mut scene = Scene::<2>::new();
+        scene.kit_a[0].inner[0].onsets[0] = Some(present);
+        scene.kit_a[0].inner[1].onsets[0] = Some(absent.clone());
+        scene.kit_b[1].inner[0].onsets[1] = Some(absent);
+        // missing path named once, present onset kept
+        let dropped = scene.validate();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(dropped, [Box::from(missing)]);
+        assert!(scene.kit_a[0].inner[0].onsets[0].is_some());
+        assert!(scene.kit_a[0].inner[1].onsets[0].is_none());
+        assert!(scene.kit_b[1].inner[0].onsets[1].is_none());
+    }
+
+    #[test]
+    fn copied_kit_deep_equals_source_slot() {
+        let mut scene = Scene::<2>::new();
+        let kit = &mut scene.kit_a[1];
+        kit.inner[0].onsets[0] = Some(assigned(44100, &[8192; 64]));
+        kit.inner[1].onsets[1] = Some(assigned(44100, &[-8192; 64]));
+        kit.inner[1].gain = 0.5;
+        kit.inner[1].phrase = Some(super::super::Phrase::new([(0, super::super::Event::Hold { index: 0, vel: 1. })], 4));
+        kit.root = Some(1);
+        scene.copy_kit(Bank::A, 1);
+        let (a, b) = (&scene.kit_a[1], &scene.kit_b[1]);
+        assert_eq!(serde_json::to_value(a).unwrap(), serde_json::to_value(b).unwrap());
+        assert_eq!(a.root, b.root);
+        let data = |kit: &Kit<2>| kit.inner.iter().flat_map(|v| v.onsets.iter().flatten()).map(|v| v.wav.data.clone().unwrap()).collect::<Vec<_>>();
+        assert_eq!(data(a), data(b));
+        // other slots untouched, copy independent of source
+        assert!(scene.kit_b[0].inner.iter().all(|v| v.onsets.iter().all(Option::is_none)));
+        scene.kit_b[1].inner[1].gain = 1.;
+        assert_eq!(scene.kit_a[1].inner[1].gain, 0.5);
+        // back the other way
+        scene.copy_kit(Bank::B, 1);
+        assert_eq!(scene.kit_a[1].inner[1].gain, 1.);
+    }
+
+    #[test]
+    fn reloaded_wav_plays_new_contents() {
+        let path = std::env::temp_dir().join(format!("reload-{}.wav", std::process::id()));
+        // wav as rescanned from disk
+        let load = |word: i16| {
+            let pcm = [word; 4096].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>();
+            std::fs::write(&path, super::super::bounce::image(44100, &pcm).unwrap()).unwrap();
+            let mut wav = super::super::Wav {
+                tempo: None,
+                steps: None,
+                path: path.clone().into(),
+                len: active::Header::open(&path).unwrap().len,
+                data: None,
+                stream: None,
+            };
+            wav.preload(&[]).unwrap();
+            wav
+        };
+        let (mut tx, rx) = super::super::cmd_queue::<1>();
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, 44100);
+        let hold = || super::super::Cmd::Bank(Bank::A, super::super::BankCmd::ForceEvent(super::super::Event::Hold { index: 0, vel: 1. }));
+        tx.send(super::super::Cmd::AssignTempo(120.)).unwrap();
+        let onset = super::super::Onset { wav: load(8192), start: 0 };
+        tx.send(super::super::Cmd::Bank(Bank::A, super::super::BankCmd::AssignOnset(0, false, Box::new(onset)))).unwrap();
+        tx.send(hold()).unwrap();
+        // first pad panned hard left
+        let mut buffer = vec![0f32; 512];
+        handler.tick(&mut buffer, 2);
+        assert!(buffer[256..].iter().step_by(2).all(|v| *v > 0.));
+        // replaced contents heard once retriggered
+        tx.send(super::super::Cmd::ReloadWav(Box::new(load(-8192)))).unwrap();
+        tx.send(hold()).unwrap();
+        handler.tick(&mut buffer, 2);
+        std::fs::remove_file(&path).ok();
+        assert!(buffer[256..].iter().step_by(2).all(|v| *v < 0.));
+    }
+
+    #[test]
+    fn read_error_silences_only_its_bank() {
+        let (mut tx, rx) = super::super::cmd_queue::<1>();
+        let (tui_tx, tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, 44100);
+        let hold = |bank| super::super::Cmd::Bank(bank, super::super::BankCmd::ForceEvent(super::super::Event::Hold { index: 0, vel: 1. }));
+        tx.send(super::super::Cmd::AssignTempo(120.)).unwrap();
+        for bank in [Bank::A, Bank::B] {
+            tx.send(super::super::Cmd::Bank(bank, super::super::BankCmd::AssignOnset(0, false, Box::new(assigned(44100, &[8192; 4096]))))).unwrap();
+            tx.send(hold(bank)).unwrap();
+        }
+        let mut buffer = vec![0f32; 512];
+        handler.tick(&mut buffer, 2);
+        // bank a's wav failing mid hold
+        let active::Event::Hold(onset, _) = &mut handler.bank_a.input.active else { panic!("hold not active") };
+        onset.wav.file = active::Source::Fail;
+        onset.restart = Some(false);
+        tui_rx.try_iter().for_each(drop);
+        handler.tick(&mut buffer, 2);
+        assert!(matches!(handler.bank_a.input.active, active::Event::Sync));
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::Error(e) if e.starts_with("bank a silenced"))));
+        // bank b plays on alone
+        handler.tick(&mut buffer, 2);
+        assert!(matches!(handler.bank_b.input.active, active::Event::Hold(..)));
+        assert!(buffer[256..].iter().step_by(2).all(|v| *v > 0.));
+    }
+
+    #[test]
+    fn limiter_keeps_hot_banks_within_full_scale() {
+        // peak of full-scale onsets held on both banks, gained up, limited or not
+        let peak = |limit: bool| {
+            let (mut tx, rx) = super::super::cmd_queue::<1>();
+            let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+            let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, 44100);
+            tx.send(super::super::Cmd::AssignTempo(120.)).unwrap();
+            tx.send(super::super::Cmd::AssignLimit(limit)).unwrap();
+            for bank in [Bank::A, Bank::B] {
+                tx.send(super::super::Cmd::Bank(bank, super::super::BankCmd::AssignOnset(0, false, Box::new(assigned(44100, &[i16::MAX; 4096]))))).unwrap();
+                tx.send(super::super::Cmd::Bank(bank, super::super::BankCmd::AssignGain(4.))).unwrap();
+                tx.send(super::super::Cmd::Bank(bank, super::super::BankCmd::ForceEvent(super::super::Event::Hold { index: 0, vel: 1. }))).unwrap();
+            }
+            let mut peak = 0f32;
+            for _ in 0..16 {
+                let mut buffer = vec![0f32; 512];
+                handler.tick(&mut buffer, 2);
+                peak = buffer.iter().fold(peak, |peak, v| peak.max(v.abs()));
+            }
+            peak
+        };
+        assert!(peak(false) > 1.);
+        assert!(peak(true) <= 1.);
+    }
+
+    #[test]
+    fn clicks_land_on_steps_accenting_bars() {
+        let (mut tx, rx) = super::super::cmd_queue::<1>();
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, 44100);
+        tx.send(super::super::Cmd::AssignClick(true)).unwrap();
+        tx.send(super::super::Cmd::AssignClickBar(4)).unwrap();
+        // step every other buffer, each buffer longer than a click; zero crossings per buffer
+        let crossings = (0..16)
+            .map(|i| {
+                if i % 2 == 0 {
+                    tx.send(super::super::Cmd::Clock).unwrap();
+                }
+                let mut buffer = vec![0f32; 1024 * 2];
+                handler.tick(&mut buffer, 2);
+                buffer.iter().step_by(2).collect::<Vec<_>>().windows(2).filter(|v| (*v[0] < 0.) != (*v[1] < 0.)).count()
+            })
+            .collect::<Vec<_>>();
+        let clicked = crossings.iter().map(|v| *v > 0).collect::<Vec<_>>();
+        assert_eq!(clicked, (0..16).map(|i| i % 2 == 0).collect::<Vec<_>>());
+        // bar downbeats pitched up
+        let accented = crossings.iter().step_by(2).map(|v| *v > 45).collect::<Vec<_>>();
+        assert_eq!(accented, [true, false, false, false, true, false, false, false]);
+    }
+
+    #[test]
+    fn kit_round_trips_into_chosen_slot() {
+        let mut kit = Kit::<2>::new();
+        kit.inner[1].onsets[0] = Some(super::super::Onset {
+            wav: super::super::Wav {
+                tempo: Some(90.),
+                steps: Some(8),
+                path: super::super::onsets_dir().join("kits/snare.wav").into(),
+                len: 2048,
+                data: None,
+                stream: None,
+            },
+            start: 256,
+        });
+        kit.inner[1].gain = 0.75;
+        let path = std::env::temp_dir().join(format!("kit-round-trip-{}.kit", std::process::id()));
+        kit.save(std::fs::File::create(&path).unwrap()).unwrap();
+        let loaded = Kit::<2>::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let json = serde_json::to_string(&kit).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+        // stored into bank b's second slot only, leaving loaded kit
+        let (mut tx, rx) = super::super::cmd_queue::<2>();
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<2>::new(rx, tui_tx, None, 44100);
+        tx.send(super::super::Cmd::StoreKit(Bank::B, 1, Box::new(loaded))).unwrap();
+        handler.tick(&mut [0f32; 64], 2);
+        assert_eq!(serde_json::to_string(&handler.scene.kit_b[1]).unwrap(), json);
+        let empty = serde_json::to_string(&Kit::<2>::new()).unwrap();
+        for kit in handler.scene.kit_a.iter().chain([&handler.scene.kit_b[0], &handler.bank_b.kit]) {
+            assert_eq!(serde_json::to_string(kit).unwrap(), empty);
+        }
+    }
+
+    #[test]
+    fn count_in_ignores_events_before_bar() {
+        let (mut tx, rx) = super::super::cmd_queue::<1>();
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, 44100);
+        let bank = |cmd| super::super::Cmd::Bank(Bank::A, cmd);
+        tx.send(bank(super::super::BankCmd::AssignOnset(0, false, Box::new(assigned(44100, &[0; 4096]))))).unwrap();
+        for _ in 0..3 {
+            tx.send(super::super::Cmd::Clock).unwrap();
+        }
+        // one bar of count-in from step 3 ends on next bar line
+        tx.send(bank(super::super::BankCmd::ArmRecord(1))).unwrap();
+        handler.tick(&mut [0f32; 64], 2);
+        let bar = super::super::CLICK_BAR;
+        assert_eq!(handler.bank_a.record.start, Some(bar));
+        // event pushed every step
+        for _ in 3..bar + 8 {
+            tx.send(bank(super::super::BankCmd::PushEvent(super::super::Event::Hold { index: 0, vel: 1. }))).unwrap();
+            tx.send(super::super::Cmd::Clock).unwrap();
+        }
+        handler.tick(&mut [0f32; 64], 2);
+        assert_eq!(handler.bank_a.record.start, None);
+        // baked over all steps so far, events keep their steps
+        tx.send(bank(super::super::BankCmd::BakeRecord(bar + 8))).unwrap();
+        handler.tick(&mut [0f32; 64], 2);
+        let phrase = handler.bank_a.record.phrase.as_ref().unwrap();
+        let steps = phrase.events.iter().map(|v| v.step).collect::<Vec<_>>();
+        assert_eq!(steps, (bar..bar + 8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn empty_phrases_bake_and_play_silently() {
+        let (mut tx, rx) = super::super::cmd_queue::<1>();
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, 44100);
+        let bank = |cmd| super::super::Cmd::Bank(Bank::A, cmd);
+        tx.send(bank(super::super::BankCmd::AssignOnset(0, false, Box::new(assigned(44100, &[8192; 4096]))))).unwrap();
+        let mut buffer = vec![0f32; 64];
+        // nothing recorded, baked at zero length and at a bar, then pooled
+        for len in [0, super::super::CLICK_BAR] {
+            tx.send(bank(super::super::BankCmd::BakeRecord(len))).unwrap();
+            tx.send(bank(super::super::BankCmd::TakeRecord(Some(0)))).unwrap();
+            for _ in 0..2 * super::super::CLICK_BAR {
+                tx.send(super::super::Cmd::Clock).unwrap();
+                handler.tick(&mut buffer, 2);
+                assert!(buffer.iter().all(|v| *v == 0.));
+            }
+        }
+        assert!(handler.bank_a.kit.inner[0].phrase.as_ref().is_some_and(|v| v.events.is_empty()));
+    }
+
+    #[test]
+    fn swapped_banks_exchange_params_kits_and_events() {
+        let (mut tx, rx) = super::super::cmd_queue::<1>();
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, 44100);
+        let bank = |bank, cmd| super::super::Cmd::Bank(bank, cmd);
+        tx.send(super::super::Cmd::AssignTempo(120.)).unwrap();
+        for (b, word, v) in [(Bank::A, 8192, 0.25), (Bank::B, -8192, 0.75)] {
+            tx.send(bank(b, super::super::BankCmd::AssignOnset(0, false, Box::new(assigned(44100, &[word; 4096]))))).unwrap();
+            tx.send(bank(b, super::super::BankCmd::AssignGain(v))).unwrap();
+            tx.send(bank(b, super::super::BankCmd::AssignDrift(v))).unwrap();
+            tx.send(bank(b, super::super::BankCmd::AssignBias(v))).unwrap();
+            tx.send(bank(b, super::super::BankCmd::AssignCutoff(v))).unwrap();
+        }
+        // only bank a holding once bank b's audition released, panned hard left
+        tx.send(bank(Bank::B, super::super::BankCmd::ForceEvent(super::super::Event::Sync))).unwrap();
+        let mut buffer = vec![0f32; 512];
+        for _ in 0..20 {
+            handler.tick(&mut buffer, 2);
+        }
+        let before = buffer[510];
+        tx.send(super::super::Cmd::SwapBanks).unwrap();
+        handler.tick(&mut buffer, 2);
+        let (a, b) = (&handler.bank_a, &*handler.bank_b);
+        assert_eq!((a.gain, a.drift, a.bias, a.filter.cutoff), (0.75, 0.75, 0.75, 0.75));
+        assert_eq!((b.gain, b.drift, b.bias, b.filter.cutoff), (0.25, 0.25, 0.25, 0.25));
+        let word = |bank: &BankHandler<1>| bank.kit.inner[0].onsets[0].as_ref().unwrap().wav.data.as_ref().unwrap()[super::super::bounce::HEADER_LEN];
+        assert_eq!((word(a), word(b)), ((-8192i16).to_le_bytes()[0], 8192i16.to_le_bytes()[0]));
+        // hold carried across, playing on without a gap
+        assert!(matches!(a.input.active, active::Event::Sync) && matches!(b.input.active, active::Event::Hold(..)));
+        assert!(buffer.iter().step_by(2).all(|v| *v > 0.));
+        assert!((buffer[0] - before).abs() < 1e-3);
+    }
+
+    #[test]
+    fn bank_a_hit_ducks_bank_b_then_recovers() {
+        let (mut tx, rx) = super::super::cmd_queue::<1>();
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, 44100);
+        let bank = |bank, cmd| super::super::Cmd::Bank(bank, cmd);
+        tx.send(super::super::Cmd::AssignTempo(120.)).unwrap();
+        // silent bank a, so output is bank b's alone
+        tx.send(bank(Bank::A, super::super::BankCmd::AssignOnset(0, false, Box::new(assigned(44100, &[0; 4096]))))).unwrap();
+        tx.send(bank(Bank::B, super::super::BankCmd::AssignOnset(0, false, Box::new(assigned(44100, &[8192; 4096]))))).unwrap();
+        tx.send(bank(Bank::B, super::super::BankCmd::AssignDuckDepth(0.8))).unwrap();
+        tx.send(bank(Bank::B, super::super::BankCmd::AssignDuckRelease(0.05))).unwrap();
+        let mut buffer = vec![0f32; 512];
+        for _ in 0..8 {
+            handler.tick(&mut buffer, 2);
+        }
+        let level = buffer[510];
+        assert!(level > 0.);
+        tx.send(bank(Bank::A, super::super::BankCmd::PushEvent(super::super::Event::Hold { index: 0, vel: 1. }))).unwrap();
+        // dipped by depth on hit, recovering over release
+        let mut levels = Vec::new();
+        for _ in 0..16 {
+            handler.tick(&mut buffer, 2);
+            levels.extend(buffer.iter().step_by(2).map(|v| v / level));
+        }
+        assert!((levels[0] - 0.2).abs() < 0.01);
+        assert!(levels.windows(2).all(|v| v[1] >= v[0] - 1e-6));
+        let release = (0.05 * 44100.) as usize;
+        assert!((levels[release / 2] - 0.6).abs() < 0.01);
+        assert!(levels[release + 1..].iter().all(|v| (v - 1.).abs() < 1e-4));
+    }
+
+    #[test]
+    fn playing_reports_triggered_pad() {
+        let (mut tx, rx) = super::super::cmd_queue::<8>();
+        let (tui_tx, tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<8>::new(rx, tui_tx, None, 44100);
+        let bank = |cmd| super::super::Cmd::Bank(Bank::A, cmd);
+        tx.send(super::super::Cmd::AssignTempo(120.)).unwrap();
+        for index in [2, 5] {
+            tx.send(bank(super::super::BankCmd::AssignOnset(index, false, Box::new(assigned(44100, &[8192; 4096]))))).unwrap();
+        }
+        let mut buffer = vec![0f32; 512];
+        let mut playing = |handler: &mut AudioHandler<8>| {
+            for _ in 0..20 {
+                handler.tick(&mut buffer, 2);
+            }
+            tui_rx.try_iter().filter_map(|v| match v {
+                tui::Cmd::Playing(Bank::A, v) => Some(v),
+                _ => None,
+            }).last()
+        };
+        // auditions released
+        tx.send(bank(super::super::BankCmd::ForceEvent(super::super::Event::Sync))).unwrap();
+        playing(&mut handler);
+        for index in [5, 2] {
+            tx.send(bank(super::super::BankCmd::PushEvent(super::super::Event::Hold { index, vel: 1. }))).unwrap();
+            assert_eq!(playing(&mut handler), Some(Some((index, false))));
+        }
+        tx.send(bank(super::super::BankCmd::PushEvent(super::super::Event::Sync))).unwrap();
+        assert_eq!(playing(&mut handler), Some(None));
+    }
+
+    #[test]
+    fn panic_returns_banks_to_sync() {
+        let (mut tx, rx) = super::super::cmd_queue::<1>();
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, 44100);
+        for bank in [Bank::A, Bank::B] {
+            tx.send(super::super::Cmd::Bank(bank, super::super::BankCmd::AssignOnset(0, false, Box::new(assigned(44100, &[8192; 4096]))))).unwrap();
+            tx.send(super::super::Cmd::Bank(bank, super::super::BankCmd::ForceEvent(super::super::Event::Hold { index: 0, vel: 1. }))).unwrap();
+        }
+        let mut buffer = vec![0f32; 512];
+        handler.tick(&mut buffer, 2);
+        assert!(handler.bank_a.playing().is_some() && handler.bank_b.playing().is_some());
+        tx.send(super::super::Cmd::Panic).unwrap();
+        handler.tick(&mut buffer, 2);
+        for bank in [&handler.bank_a, &*handler.bank_b] {
+            assert!(matches!(bank.input.active, active::Event::Sync));
+            assert!(bank.playing().is_none());
+        }
+        handler.tick(&mut buffer, 2);
+        assert!(buffer.iter().all(|v| *v == 0.));
+    }
+
+    #[test]
+    fn grain_speed_follows_file_rate() {
+        let words = [0; 64];
+        let (low, low_rem) = BankHandler::<1>::grain(&onset(22050, &words), 1024, 1., 120., 44100);
+        let (high, high_rem) = BankHandler::<1>::grain(&onset(44100, &words), 1024, 1., 120., 44100);
+        // same seconds of source per output second, so same pitch
+        assert_eq!(low * 2., high);
+        assert_eq!(low_rem * 2, high_rem);
+        assert_eq!(high, 1.);
+    }
+
+    #[test]
+    fn scene_round_trips_json_and_bincode() {
+        let mut scene = Scene::<2>::new();
+        let pad = &mut scene.kit_b[1].inner[0];
+        pad.onsets[1] = Some(super::super::Onset {
+            wav: super::super::Wav {
+                tempo: Some(120.),
+                steps: Some(16),
+                path: super::super::onsets_dir().join("breaks/amen.wav").into(),
+                len: 4096,
+                data: None,
+                stream: None,
+            },
+            start: 512,
+        });
+        pad.phrase = Some(super::super::Phrase::new([(0, super::super::Event::Hold { index: 1, vel: 0.5 })], 4));
+        pad.gain = 0.25;
+        let dir = std::env::temp_dir().join(format!("scene-round-trip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let json = serde_json::to_string(&scene).unwrap();
+        // json to bincode and back
+        scene.save(std::fs::File::create(dir.join("a.sd")).unwrap(), false).unwrap();
+        let loaded = Scene::<2>::load(dir.join("a.sd")).unwrap();
+        loaded.save(std::fs::File::create(dir.join("a.sdb")).unwrap(), true).unwrap();
+        let loaded = Scene::<2>::load(dir.join("a.sdb")).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+    }
+
+    /// 8192 words of a sine at 0.05 radians per word
+    fn sine() -> Vec<i16> {
+        (0..8192).map(|i| ((i as f32 * 0.05).sin() * 16000.) as i16).collect()
+    }
+
+    /// mono grains of onset over buffers
+    fn render(onset: &mut active::Onset, grain_len: usize, frames: usize, buffers: usize, speed: f32, reverse: bool) -> Vec<f32> {
+        onset.env = 1.;
+        let mut out = Vec::new();
+        for _ in 0..buffers {
+            let mut buffer = vec![0.; frames];
+            BankHandler::<1>::read_grain(onset, super::super::Interp::Linear, grain_len, super::super::LOOP_FADE, 44100, 1., speed, 0., reverse, false, 120., &mut buffer, 1).unwrap();
+            out.extend(buffer);
+        }
+        out
+    }
+
+    fn play(grain_len: usize, frames: usize, buffers: usize, speed: f32, reverse: bool) -> (Vec<f32>, Vec<f32>) {
+        let words = sine();
+        let mut onset = onset(44100, &words);
+        if reverse {
+            onset.wav.seek(8000).unwrap();
+        }
+        let out = render(&mut onset, grain_len, frames, buffers, speed, reverse);
+        let source = words.iter().map(|&v| v as f32 / i16::MAX as f32).collect();
+        (out, source)
+    }
+
+    /// grains of encoded file preloaded as onset, and its decoded words
+    fn play_decoded(ext: &str, file: &[u8]) -> (Vec<f32>, Vec<f32>) {
+        let path = std::env::temp_dir().join(format!("grains-{}.{}", std::process::id(), ext));
+        std::fs::write(&path, file).unwrap();
+        let mut wav = super::super::Wav { tempo: None, steps: None, path: path.clone().into(), len: 0, data: None, stream: None };
+        // decoded into memory however short
+        wav.preload(&[]).unwrap();
+        std::fs::remove_file(&path).ok();
+        let data = wav.data.clone().unwrap();
+        assert!(wav.stream.is_none());
+        let mut kit = Kit::<1>::new();
+        kit.inner[0].onsets[0] = Some(super::super::Onset { wav, start: 0 });
+        let mut onset = kit.onset_seek(0u8, false, 0., 1.).unwrap();
+        let out = render(&mut onset, 100, 256, 8, 1., false);
+        let source = data[super::super::bounce::HEADER_LEN..]
+            .chunks_exact(2)
+            .map(|v| i16::from_le_bytes([v[0], v[1]]) as f32 / i16::MAX as f32)
+            .collect();
+        (out, source)
+    }
+
+    #[test]
+    fn flac_onset_plays_through_grains() {
+        let words = sine();
+        let (out, source) = play_decoded("flac", &super::super::flac::image(44100, &[&words]));
+        for (i, v) in out.iter().enumerate() {
+            let expected = words[i] as f32 / i16::MAX as f32;
+            assert!((v - expected).abs() < 1e-3, "frame {i}: {v} != {expected}");
+            assert_eq!(source[i], expected);
+        }
+    }
+
+    #[test]
+    fn ogg_onset_plays_through_grains() {
+        let (out, source) = play_decoded("ogg", &super::super::ogg::image(44100, 40));
+        assert!(out.iter().any(|v| v.abs() > 0.01));
+        for (i, v) in out.iter().enumerate() {
+            assert!((v - source[i]).abs() < 1e-3, "frame {i}: {v} != {}", source[i]);
+        }
+    }
+
+    #[test]
+    fn grains_continue_across_buffers() {
+        // grains shorter and longer than buffer, at file and half speed
+        for (grain_len, speed) in [(100, 1.), (700, 1.), (100, 0.5), (700, 0.5)] {
+            let (out, source) = play(grain_len, 256, 12, speed, false);
+            for (i, v) in out.iter().enumerate() {
+                let pos = i as f32 * speed;
+                let expected = source[pos as usize] + (source[pos as usize + 1] - source[pos as usize]) * pos.fract();
+                assert!((v - expected).abs() < 1e-3, "grain {grain_len} speed {speed} frame {i}: {v} != {expected}");
+            }
+            // no step larger than sine's own slope
+            let max = out.windows(2).map(|v| (v[1] - v[0]).abs()).fold(0., f32::max);
+            assert!(max <= 0.05 * speed + 1e-3, "grain {grain_len} speed {speed} jumps {max}");
+        }
+    }
+
+    /// frames between loop wraps of pad 0 looped over `len`
+    fn loop_period(len: super::super::Fraction) -> usize {
+        let mut bank = BankHandler::<1>::new();
+        bank.kit.inner[0].onsets[0] = Some(assigned(44100, &vec![0; 44100 * 2]));
+        bank.force_event(0., super::super::Event::Loop { index: 0, len, vel: 1. }).unwrap();
+        let mut pos = 0;
+        let mut wraps = Vec::new();
+        for i in 0..2000 {
+            bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut [0.; 64], 1).unwrap();
+            let active::Event::Loop(onset, ..) = &mut bank.input.active else {
+                panic!("loop ended");
+            };
+            let next = onset.pos().unwrap();
+            if next < pos {
+                wraps.push(i * 64);
+            }
+            pos = next;
+        }
+        assert!(wraps.len() > 2);
+        wraps[2] - wraps[1]
+    }
+
+    #[test]
+    fn stutter_restarts_hold_each_period_on_clock() {
+        let mut bank = BankHandler::<1>::new();
+        bank.kit.inner[0].onsets[0] = Some(assigned(44100, &vec![0; 44100 * 2]));
+        let mut kits = [Kit::<1>::new()];
+        bank.cmd(false, 0., &mut kits, super::super::BankCmd::AssignStutter(Some(super::super::Fraction::new(1, 4)))).unwrap();
+        bank.force_event(0., super::super::Event::Hold { index: 0, vel: 1. }).unwrap();
+        // 80 buffers of 64 frames a step
+        let tempo = 44100. * 60. / (5120. * super::super::STEP_DIV as f32);
+        let (mut pos, mut restarts) = (0, Vec::new());
+        for i in 0..400usize {
+            if i > 0 && i % 80 == 0 {
+                bank.clock((i / 80) as f32, 0.5).unwrap();
+            }
+            bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, tempo, &mut [0.; 64], 1).unwrap();
+            let active::Event::Hold(onset, ..) = &mut bank.input.active else {
+                panic!("hold ended");
+            };
+            let next = onset.pos().unwrap();
+            if next < pos {
+                restarts.push(i);
+            }
+            pos = next;
+        }
+        // every quarter step to within the buffer reaching it, on each clock exactly
+        assert_eq!(restarts.len(), 19);
+        for (restart, period) in restarts.iter().zip(1..) {
+            assert!(restart.abs_diff(period * 20) <= 1);
+            if period % 4 == 0 {
+                assert_eq!(*restart, period * 20);
+            }
+        }
+    }
+
+    #[test]
+    fn frozen_reads_keep_to_one_grain() {
+        for event in [super::super::Event::Hold { index: 0, vel: 1. }, super::super::Event::Loop { index: 0, len: super::super::Fraction::new(1, 1), vel: 1. }] {
+            let mut bank = BankHandler::<1>::new();
+            bank.kit.inner[0].onsets[0] = Some(assigned(44100, &sine()));
+            bank.force_event(0., event).unwrap();
+            let mut kits = [Kit::<1>::new()];
+            let mut out = [0.; 64];
+            bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut out, 1).unwrap();
+            bank.cmd(false, 0., &mut kits, super::super::BankCmd::AssignFrozen(true)).unwrap();
+            let (mut positions, mut played) = (Vec::new(), Vec::new());
+            for i in 0..240 {
+                // clocks leave frozen read in place
+                if i % 20 == 0 {
+                    bank.clock((i / 20) as f32, 0.5).unwrap();
+                }
+                let mut out = [0.; 64];
+                bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut out, 1).unwrap();
+                let (active::Event::Hold(onset, ..) | active::Event::Loop(onset, ..)) = &mut bank.input.active else {
+                    panic!("event ended");
+                };
+                // past grain launched before freeze
+                if i >= 40 {
+                    positions.push(onset.pos().unwrap());
+                    played.extend(out);
+                }
+            }
+            // within one grain of 16 bit words, repeating each grain
+            let (min, max) = (positions.iter().min().unwrap(), positions.iter().max().unwrap());
+            assert!(max - min < 2 * super::super::GRAIN_LEN as u64);
+            assert!(played.iter().zip(&played[super::super::GRAIN_LEN..]).all(|(a, b)| (a - b).abs() < 1e-4));
+            assert!(played.iter().any(|v| v.abs() > 0.1));
+        }
+    }
+
+    #[test]
+    fn hold_attacks_from_zero_and_releases_on_sync() {
+        let mut bank = BankHandler::<1>::new();
+        bank.kit.inner[0].onsets[0] = Some(assigned(44100, &[8192; 4096]));
+        bank.force_event(0., super::super::Event::Hold { index: 0, vel: 1. }).unwrap();
+        let mut out = [0.; 64];
+        bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut out, 1).unwrap();
+        // rises over attack, then holds
+        let attack = super::super::ATTACK_LEN;
+        assert!(out[0] > 0. && out[0] < out[attack - 1] / attack as f32 * 1.5);
+        assert!(out[..attack].windows(2).all(|v| v[1] > v[0]));
+        assert!(out[attack..].iter().all(|v| *v == out[attack - 1]));
+        // sync releases to silence over release, then ends event
+        bank.force_event(1., super::super::Event::Sync).unwrap();
+        let mut out = [0.; super::super::RELEASE_LEN + 64];
+        bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut out, 1).unwrap();
+        assert!(out[..super::super::RELEASE_LEN].windows(2).all(|v| v[1] < v[0]));
+        assert!(out[super::super::RELEASE_LEN..].iter().all(|v| *v == 0.));
+        assert!(matches!(bank.input.active, active::Event::Sync));
+    }
+
+    #[test]
+    fn aftertouch_127_doubles_speed() {
+        // wav words read over hold, with touch target slewed to first
+        let advance = |touch: f32| {
+            let mut bank = BankHandler::<1>::new();
+            bank.kit.inner[0].onsets[0] = Some(assigned(44100, &[8192; 32768]));
+            let mut kits = [Kit::<1>::new()];
+            bank.cmd(false, 0., &mut kits, super::super::BankCmd::AssignTouch(super::super::Touch::Speed, touch)).unwrap();
+            let mut out = [0.; 64];
+            for _ in 0..100 {
+                bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut out, 1).unwrap();
+            }
+            bank.force_event(0., super::super::Event::Hold { index: 0, vel: 1. }).unwrap();
+            let active::Event::Hold(onset, _) = &mut bank.input.active else { panic!("hold not active") };
+            let from = onset.pos().unwrap();
+            for _ in 0..64 {
+                bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut out, 1).unwrap();
+            }
+            let active::Event::Hold(onset, _) = &mut bank.input.active else { panic!("hold not active") };
+            (onset.pos().unwrap() - from) as f32
+        };
+        // aftertouch 127 sent by input as full touch
+        assert!((advance(1.) / advance(0.) - 2.).abs() < 0.01);
+    }
+
+    #[test]
+    fn swing_lags_off_steps_only() {
+        // read position jumped to on each clock of steps 1 to 4
+        let positions = |swing: f32| {
+            let mut bank = BankHandler::<1>::new();
+            let mut onset = assigned(44100, &[0; 16 * 256]);
+            onset.wav.steps = Some(16);
+            bank.kit.inner[0].onsets[0] = Some(onset);
+            bank.force_event(0., super::super::Event::Hold { index: 0, vel: 1. }).unwrap();
+            (1..5).map(|clock| {
+                bank.clock(clock as f32, swing).unwrap();
+                let active::Event::Hold(onset, _) = &mut bank.input.active else { panic!("hold not active") };
+                onset.pos().unwrap()
+            }).collect::<Vec<_>>()
+        };
+        // 512 bytes a step
+        assert_eq!(positions(0.5), [512, 1024, 1536, 2048]);
+        // off steps read half a step behind, heard later
+        assert_eq!(positions(0.75), [256, 1024, 1280, 2048]);
+    }
+
+    #[test]
+    fn nudge_forward_then_back_restores_start() {
+        let mut bank = BankHandler::<1>::new();
+        let mut onset = assigned(44100, &[0; 256]);
+        onset.start = 200;
+        bank.kit.inner[0].onsets[0] = Some(onset);
+        let mut kits = [Kit::<1>::new()];
+        let mut nudge = |delta| {
+            bank.cmd(false, 0., &mut kits, super::super::BankCmd::NudgeOnset(0, false, delta)).unwrap();
+            bank.kit.inner[0].onsets[0].as_ref().unwrap().start
+        };
+        // 16 bit words
+        assert_eq!(nudge(10), 220);
+        assert_eq!(nudge(-10), 200);
+        // clamped within pcm
+        assert_eq!(nudge(-1000), 0);
+        assert_eq!(nudge(1000), 510);
+        nudge(-155);
+        // heard from next trigger
+        bank.force_event(0., super::super::Event::Hold { index: 0, vel: 1. }).unwrap();
+        let active::Event::Hold(onset, _) = &mut bank.input.active else { panic!("hold not active") };
+        assert_eq!(onset.pos().unwrap(), 200);
+        // unassigned alt left alone
+        bank.cmd(false, 0., &mut kits, super::super::BankCmd::NudgeOnset(0, true, 10)).unwrap();
+        assert!(bank.kit.inner[0].onsets[1].is_none());
+    }
+
+    #[test]
+    fn chromatic_pad_12_plays_octave_up() {
+        use super::super::{PadMode, Scale};
+        assert_eq!(PadMode::Chromatic(0).pitch(0, Scale::Chromatic), 1.);
+        assert_eq!(PadMode::Chromatic(0).pitch(12, Scale::Chromatic), 2.);
+        assert_eq!(PadMode::Chromatic(12).pitch(0, Scale::Chromatic), 0.5);
+        assert_eq!(PadMode::Kit.pitch(12, Scale::Chromatic), 1.);
+        // an octave as many pads on as scale has tones
+        assert_eq!(PadMode::Chromatic(0).pitch(7, Scale::Major), 2.);
+        assert_eq!(PadMode::Chromatic(0).pitch(5, Scale::Pentatonic), 2.);
+        // wav words read over hold of pad from root's onset
+        let advance = |index: u8| {
+            let mut bank = BankHandler::<8>::new();
+            bank.kit.inner[0].onsets[0] = Some(assigned(44100, &[8192; 32768]));
+            let mut kits = core::array::from_fn(|_| Kit::<8>::new());
+            bank.cmd(false, 0., &mut kits, super::super::BankCmd::AssignPadMode(PadMode::Chromatic(0))).unwrap();
+            bank.cmd(false, 0., &mut kits, super::super::BankCmd::AssignScale(Scale::Major)).unwrap();
+            bank.force_event(0., super::super::Event::Hold { index, vel: 1. }).unwrap();
+            let active::Event::Hold(onset, _) = &mut bank.input.active else { panic!("hold not active") };
+            let from = onset.pos().unwrap();
+            let mut out = [0.; 64];
+            for _ in 0..64 {
+                bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut out, 1).unwrap();
+            }
+            let active::Event::Hold(onset, _) = &mut bank.input.active else { panic!("hold not active") };
+            (onset.pos().unwrap() - from) as f32
+        };
+        assert!((advance(7) / advance(0) - 2.).abs() < 0.01);
+    }
+
+    #[test]
+    fn reverse_toggles_without_discontinuity() {
+        let mut bank = BankHandler::<1>::new();
+        bank.kit.inner[0].onsets[0] = Some(assigned(44100, &sine()));
+        bank.force_event(0., super::super::Event::Hold { index: 0, vel: 1. }).unwrap();
+        let mut out = Vec::new();
+        // toggled mid grain under sustained hold, each way, short of reading back past pcm start
+        for i in 0..60 {
+            if i == 20 || i == 30 {
+                bank.assign_reverse(0., i == 20);
+            }
+            let mut buffer = [0.; 50];
+            bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut buffer, 1).unwrap();
+            out.extend(buffer);
+        }
+        let max = out[super::super::ATTACK_LEN..].windows(2).map(|v| (v[1] - v[0]).abs()).fold(0., f32::max);
+        assert!(max < 0.05, "jumps {max}");
+        // turned each time: 1000 words forward, 500 back, then 1500 forward
+        assert!(!bank.read_reverse);
+        let active::Event::Hold(onset, ..) = &mut bank.input.active else {
+            panic!("hold ended");
+        };
+        assert!((onset.pos().unwrap() as i64 / 2 - 2000).abs() < 64);
+    }
+
+    #[test]
+    fn loop_wraps_without_discontinuity() {
+        let mut bank = BankHandler::<1>::new();
+        bank.kit.inner[0].onsets[0] = Some(assigned(44100, &sine()));
+        // 1/32 loop of 5512 words ends mid-cycle, so wrap jumps phase
+        bank.force_event(0., super::super::Event::Loop { index: 0, len: super::super::Fraction::new(1, 32), vel: 1. }).unwrap();
+        let active::Event::Loop(onset, ..) = &mut bank.input.active else {
+            panic!("no loop");
+        };
+        onset.env = 1.;
+        let mut out = Vec::new();
+        for _ in 0..200 {
+            let mut buffer = [0.; 64];
+            bank.read_active(super::super::Interp::Linear, super::super::GRAIN_LEN, super::super::LOOP_FADE, 44100, 120., &mut buffer, 1).unwrap();
+            out.extend(buffer);
+        }
+        // several wraps in, no step far beyond sine's own slope
+        assert!(out.len() > 2 * 5512);
+        let max = out.windows(2).map(|v| (v[1] - v[0]).abs()).fold(0., f32::max);
+        assert!(max < 0.05, "jumps {max}");
+    }
+
+    #[test]
+    fn sixteenth_loop_half_of_eighth() {
+        let eighth = loop_period(super::super::Fraction::new(1, 8));
+        let sixteenth = loop_period(super::super::Fraction::new(1, 16));
+        assert!(sixteenth.abs_diff(eighth / 2) <= 64, "{sixteenth} not half of {eighth}");
+        // same grid as loop division chosen from LOOP_DIVS
+        assert_eq!(loop_period(super::super::Fraction::new(2, 16)), eighth);
+    }
+
+    #[test]
+    fn loop_period_follows_output_rate() {
+        // output frames between loop wraps of 44.1khz file at given output rate
+        let period = |sample_rate: u32| {
+            let (mut tx, rx) = super::super::cmd_queue::<1>();
+            let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+            let mut handler = AudioHandler::<1>::new(rx, tui_tx, None, sample_rate);
+            tx.send(super::super::Cmd::AssignTempo(120.)).unwrap();
+            tx.send(super::super::Cmd::Bank(Bank::A, super::super::BankCmd::AssignOnset(0, false, Box::new(assigned(44100, &vec![0; 44100 * 2]))))).unwrap();
+            let event = super::super::Event::Loop { index: 0, len: super::super::Fraction::new(1, 8), vel: 1. };
+            tx.send(super::super::Cmd::Bank(Bank::A, super::super::BankCmd::ForceEvent(event))).unwrap();
+            let (mut pos, mut wraps) = (0, Vec::new());
+            for i in 0..2000usize {
+                handler.tick(&mut [0f32; 128], 2);
+                let active::Event::Loop(onset, ..) = &mut handler.bank_a.input.active else {
+                    panic!("loop ended");
+                };
+                let next = onset.pos().unwrap();
+                if next < pos {
+                    wraps.push(i * 64);
+                }
+                pos = next;
+            }
+            wraps[2] - wraps[1]
+        };
+        // half a second either way
+        assert!(period(44100).abs_diff(22050) <= 64);
+        assert!(period(48000).abs_diff(24000) <= 64);
+    }
+
+    #[test]
+    fn grains_match_reference_across_pcm_end() {
+        let words = (0..64).map(|i| ((i * i * 37) % 2000 - 1000) as i16 * 16).collect::<Vec<_>>();
+        let start = 56;
+        for interp in [super::super::Interp::Linear, super::super::Interp::Hermite] {
+            let mut onset = onset(44100, &words);
+            onset.wav.seek(start as i64 * 2).unwrap();
+            onset.env = 1.;
+            // grains of 16 words at half speed from 8 words before pcm end, first wrapping to its start
+            let mut out = vec![0.; 64];
+            BankHandler::<1>::read_grain(&mut onset, interp, 32, super::super::LOOP_FADE, 44100, 1., 0.5, 0., false, false, 120., &mut out, 1).unwrap();
+            // words from start on, looping, led by interp's guards
+            let (pre, post) = interp.guards();
+            let looped = (0..32 + pre + post + 1)
+                .map(|k| words[(start + k - pre) % words.len()] as f32 / i16::MAX as f32)
+                .collect::<Vec<_>>();
+            let reference = super::super::tests::resample(interp, &looped, 0.5, out.len());
+            for (i, (v, r)) in out.iter().zip(&reference).enumerate() {
+                assert!((v - r).abs() < 1e-4, "frame {i}: {v} != {r}");
+            }
+        }
+    }
+
+    #[test]
+    fn grains_stride_frames_for_any_channel_count() {
+        let words = sine();
+        // hard left at full width, mono folding to half
+        for (channels, gains) in [(1, &[0.5][..]), (2, &[1., 0.]), (4, &[1., 0., 0., 0.])] {
+            let mut onset = onset(44100, &words);
+            onset.env = 1.;
+            onset.pan = -0.5;
+            let mut buffer = vec![0.; 300 * channels];
+            BankHandler::<1>::read_grain(&mut onset, super::super::Interp::Linear, 100, super::super::LOOP_FADE, 44100, 1., 1., 1., false, false, 120., &mut buffer, channels).unwrap();
+            for (i, frame) in buffer.chunks_exact(channels).enumerate() {
+                let word = words[i] as f32 / i16::MAX as f32;
+                for (c, (v, gain)) in frame.iter().zip(gains).enumerate() {
+                    assert!((v - word * gain).abs() < 1e-3, "{channels} channels, frame {i} channel {c}: {v} != {}", word * gain);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reversed_grains_continue_across_buffers() {
+        for grain_len in [100, 700] {
+            let (out, source) = play(grain_len, 256, 12, 1., true);
+            // first grain plays down from its last word
+            let top = 4000 + grain_len - 1;
+            for (i, v) in out.iter().enumerate() {
+                assert!((v - source[top - i]).abs() < 1e-3, "grain {grain_len} frame {i}: {v} != {}", source[top - i]);
+            }
+        }
     }
 }
@@ -0,0 +1,126 @@
+use super::{Event, Phrase};
+use midly::{
+    num::{u15, u24, u28, u4, u7},
+    Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
+};
+use std::path::Path;
+
+/// midi ticks per phrase step
+const STEP_TICKS: u32 = (super::PPQ / super::STEP_DIV) as u32;
+
+/// phrase events as (on tick, off tick, pad index, velocity), each ending by next event
+fn notes(phrase: &Phrase) -> Vec<(u32, u32, u8, u8)> {
+    let end = phrase.len as u32 * STEP_TICKS;
+    let mut notes = Vec::new();
+    for (i, stamped) in phrase.events.iter().enumerate() {
+        let on = stamped.step as u32 * STEP_TICKS;
+        let next = phrase.events.get(i + 1).map_or(end, |v| v.step as u32 * STEP_TICKS);
+        let (index, vel, off) = match &stamped.event {
+            Event::Sync => continue,
+            Event::Hold { index, vel } => (*index, *vel, next),
+            Event::Loop { index, len, vel } => {
+                // loop len in steps
+                let len = (f32::from(*len) * super::LOOP_DIV as f32 * STEP_TICKS as f32).round() as u32;
+                (*index, *vel, next.min(on + len.max(1)))
+            }
+        };
+        let vel = (vel * 127.).round().clamp(1., 127.) as u8;
+        notes.push((on, off.max(on + 1), index, vel));
+    }
+    notes
+}
+
+/// write phrase as single track smf at tempo, pad index offset from base note
+pub fn write(phrase: &Phrase, tempo: f32, path: impl AsRef<Path>) -> color_eyre::Result<()> {
+    // (tick, note on, key, vel), offs sorted before ons of same tick
+    let mut messages = Vec::new();
+    for (on, off, index, vel) in notes(phrase) {
+        let key = (super::EXPORT_NOTE + index).min(127);
+        messages.push((on, true, key, vel));
+        messages.push((off, false, key, 0));
+    }
+    messages.sort_by_key(|&(tick, on, ..)| (tick, on));
+
+    let mut track = vec![TrackEvent {
+        delta: u28::new(0),
+        kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new((60_000_000. / tempo) as u32))),
+    }];
+    let mut last = 0;
+    for (tick, on, key, vel) in messages {
+        let message = if on {
+            MidiMessage::NoteOn { key: u7::new(key), vel: u7::new(vel) }
+        } else {
+            MidiMessage::NoteOff { key: u7::new(key), vel: u7::new(vel) }
+        };
+        track.push(TrackEvent {
+            delta: u28::new(tick - last),
+            kind: TrackEventKind::Midi { channel: u4::new(0), message },
+        });
+        last = tick;
+    }
+    let end = (phrase.len as u32 * STEP_TICKS).saturating_sub(last);
+    track.push(TrackEvent { delta: u28::new(end), kind: TrackEventKind::Meta(MetaMessage::EndOfTrack) });
+
+    let smf = Smf {
+        header: Header::new(Format::SingleTrack, Timing::Metrical(u15::new(super::PPQ as u16))),
+        tracks: vec![track],
+    };
+    smf.save(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phrase() -> Phrase {
+        Phrase::new([
+            (0, Event::Hold { index: 0, vel: 1. }),
+            // one step of loop, cut short of next event
+            (2, Event::Loop { index: 1, len: super::super::Fraction::new(1, 8), vel: 0.5 }),
+            (5, Event::Sync),
+            (6, Event::Hold { index: 2, vel: 1. }),
+        ], 8)
+    }
+
+    #[test]
+    fn notes_land_on_step_ticks() {
+        let step = STEP_TICKS;
+        assert_eq!(notes(&phrase()), [
+            (0, 2 * step, 0, 127),
+            (2 * step, 3 * step, 1, 64),
+            (6 * step, 8 * step, 2, 127),
+        ]);
+    }
+
+    #[test]
+    fn written_file_keeps_tick_positions() {
+        let path = std::env::temp_dir().join(format!("smf-ticks-{}.mid", std::process::id()));
+        write(&phrase(), 120., &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let smf = Smf::parse(&bytes).unwrap();
+        assert_eq!(smf.header.timing, Timing::Metrical(u15::new(super::super::PPQ as u16)));
+        let mut tick = 0;
+        let mut messages = Vec::new();
+        for event in &smf.tracks[0] {
+            tick += event.delta.as_int();
+            match event.kind {
+                TrackEventKind::Midi { message: MidiMessage::NoteOn { key, .. }, .. } => messages.push((tick, true, key.as_int())),
+                TrackEventKind::Midi { message: MidiMessage::NoteOff { key, .. }, .. } => messages.push((tick, false, key.as_int())),
+                _ => (),
+            }
+        }
+        let (step, note) = (STEP_TICKS, super::super::EXPORT_NOTE);
+        assert_eq!(messages, [
+            (0, true, note),
+            (2 * step, false, note),
+            (2 * step, true, note + 1),
+            (3 * step, false, note + 1),
+            (6 * step, true, note + 2),
+            (8 * step, false, note + 2),
+        ]);
+        // track runs whole phrase
+        assert_eq!(tick, 8 * step);
+    }
+}
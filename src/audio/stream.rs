@@ -0,0 +1,385 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    sync::{atomic::{AtomicBool, AtomicU64, Ordering}, mpsc::Sender, Arc, Mutex, OnceLock, Weak},
+    thread::Thread,
+};
+
+/// pcm bytes buffered around read position
+struct Window {
+    /// pcm offset of first buffered byte
+    start: u64,
+    data: VecDeque<u8>,
+}
+
+/// window of one reader, allocated and freed by io thread
+struct Slot {
+    window: Mutex<Window>,
+    /// pcm offset last read or sought by audio thread
+    want: AtomicU64,
+    used: AtomicBool,
+}
+
+struct Shared {
+    /// pcm bytes read on open around each onset start, sorted by offset and never overlapping
+    heads: Box<[(u64, Box<[u8]>)]>,
+    slots: [Slot; super::STREAM_VOICES],
+    format: super::active::SampleFormat,
+    rate: u32,
+    /// pcm offset and length in bytes
+    offset: u64,
+    len: u64,
+    /// buffered bytes per window once full, word-aligned
+    cap: u64,
+    align: u64,
+}
+
+/// wav opened off audio thread for streaming; clones share its file, heads and windows
+#[derive(Clone)]
+pub struct Handle {
+    shared: Arc<Shared>,
+}
+
+impl Handle {
+    /// parse header and read pcm around each onset start, then hand file to io thread
+    pub fn open(path: impl AsRef<Path>, starts: &[u64]) -> Result<Self, std::io::Error> {
+        let mut file = File::open(path)?;
+        let super::active::Header { format, rate, offset, len } = super::active::Header::parse(&mut file)?;
+        let align = format.align();
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+        let mut starts: Vec<u64> = starts.iter().map(|v| (*v).min(len) & align).collect();
+        starts.sort_unstable();
+        for start in starts {
+            let lo = start.saturating_sub(super::STREAM_HEAD) & align;
+            let hi = (start + super::STREAM_HEAD).min(len);
+            match ranges.last_mut() {
+                Some(last) if lo <= last.1 => last.1 = last.1.max(hi),
+                _ => ranges.push((lo, hi)),
+            }
+        }
+        let heads = ranges
+            .into_iter()
+            .map(|(lo, hi)| Ok((lo, read_at(&mut file, offset, len, lo, hi - lo)?.into())))
+            .collect::<Result<_, std::io::Error>>()?;
+        let shared = Arc::new(Shared {
+            heads,
+            slots: core::array::from_fn(|_| Slot {
+                window: Mutex::new(Window { start: 0, data: VecDeque::new() }),
+                want: AtomicU64::new(0),
+                used: AtomicBool::new(false),
+            }),
+            format,
+            rate,
+            offset,
+            len,
+            cap: super::STREAM_LEN.min(len) & align,
+            align,
+        });
+        io().0.send((Arc::downgrade(&shared), file)).ok();
+        Ok(Self { shared })
+    }
+
+    pub fn header(&self) -> super::active::Header {
+        let Shared { format, rate, offset, len, .. } = *self.shared;
+        super::active::Header { format, rate, offset, len }
+    }
+
+    /// reader from start of pcm, taking a free window if any; never allocates
+    pub fn stream(&self) -> Stream {
+        let slot = self.shared.slots.iter().position(|v| {
+            v.used.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+        });
+        let stream = Stream { shared: self.shared.clone(), slot, pos: 0 };
+        stream.want();
+        stream
+    }
+}
+
+/// wav pcm read from heads, or from window read ahead by io thread; missing bytes read as silence
+pub struct Stream {
+    shared: Arc<Shared>,
+    /// none if all windows taken, reading heads only
+    slot: Option<usize>,
+    /// pcm offset of read position
+    pos: u64,
+}
+
+impl Stream {
+    fn want(&self) {
+        if let Some(slot) = self.slot {
+            self.shared.slots[slot].want.store(self.pos, Ordering::Relaxed);
+            io().1.unpark();
+        }
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot {
+            // window freed by io thread
+            self.shared.slots[slot].used.store(false, Ordering::Release);
+            io().1.unpark();
+        }
+    }
+}
+
+impl Read for Stream {
+    /// never blocks; bytes neither in heads nor buffered, or held by io thread, are zeroed
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = (self.shared.len.saturating_sub(self.pos) as usize).min(buf.len());
+        let buf = &mut buf[..n];
+        let window = self.slot.and_then(|v| self.shared.slots[v].window.try_lock().ok());
+        let mut copied = 0;
+        while copied < n {
+            let pos = self.pos + copied as u64;
+            let dst = &mut buf[copied..];
+            // last head starting at or before pos
+            let head = self.shared.heads.partition_point(|v| v.0 <= pos).checked_sub(1).map(|v| &self.shared.heads[v]);
+            if let Some((start, data)) = head.filter(|(start, data)| pos < start + data.len() as u64) {
+                let src = &data[(pos - start) as usize..];
+                let len = src.len().min(dst.len());
+                dst[..len].copy_from_slice(&src[..len]);
+                copied += len;
+                continue;
+            }
+            let Some(window) = window.as_ref() else {
+                break;
+            };
+            let skip = (pos as i64 - window.start as i64).rem_euclid(self.shared.len as i64) as usize;
+            if skip >= window.data.len() {
+                break;
+            }
+            // up to next head, so heads stay preferred
+            let next = self.shared.heads.iter().find(|v| v.0 > pos).map_or(u64::MAX, |v| v.0);
+            let len = (window.data.len() - skip).min(dst.len()).min((next - pos).min(usize::MAX as u64) as usize);
+            for (dst, src) in dst.iter_mut().zip(window.data.range(skip..skip + len)) {
+                *dst = *src;
+            }
+            copied += len;
+        }
+        buf[copied..].fill(0);
+        self.pos += n as u64;
+        self.want();
+        Ok(n)
+    }
+}
+
+impl Seek for Stream {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let offset = self.shared.offset as i64;
+        let abs = match pos {
+            SeekFrom::Start(v) => v as i64,
+            SeekFrom::End(v) => offset + self.shared.len as i64 + v,
+            SeekFrom::Current(v) => offset + self.pos as i64 + v,
+        };
+        let pos = (abs - offset).clamp(0, self.shared.len as i64) as u64;
+        if pos != self.pos {
+            self.pos = pos;
+            self.want();
+        }
+        Ok(self.shared.offset + self.pos)
+    }
+}
+
+type Io = (Sender<(Weak<Shared>, File)>, Thread);
+
+/// io thread and its registration queue, spawned on first stream
+fn io() -> &'static Io {
+    static IO: OnceLock<Io> = OnceLock::new();
+    IO.get_or_init(|| {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let mut streams: Vec<(Weak<Shared>, File)> = Vec::new();
+            loop {
+                streams.extend(rx.try_iter());
+                // drop streams whose wavs are gone, and those failing reads
+                let mut busy = false;
+                streams.retain_mut(|(shared, file)| match shared.upgrade() {
+                    Some(shared) => shared.slots.iter().try_fold(false, |busy, slot| {
+                        Ok::<_, std::io::Error>(fill(&shared, slot, file)? || busy)
+                    })
+                    .map(|v| busy |= v)
+                    .is_ok(),
+                    None => false,
+                });
+                if !busy {
+                    std::thread::park_timeout(super::STREAM_POLL);
+                }
+            }
+        });
+        (tx, handle.thread().clone())
+    })
+}
+
+/// `len` pcm bytes from offset `pos`, wrapping at end of pcm
+fn read_at(file: &mut File, offset: u64, pcm_len: u64, pos: u64, len: u64) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len as usize];
+    let mut done = 0;
+    while done < buf.len() {
+        let pos = (pos + done as u64) % pcm_len;
+        let n = ((pcm_len - pos) as usize).min(buf.len() - done);
+        file.seek(SeekFrom::Start(offset + pos))?;
+        file.read_exact(&mut buf[done..done + n])?;
+        done += n;
+    }
+    Ok(buf)
+}
+
+/// move window of used slot toward wanted offset and read one chunk; free window of unused slot.
+/// true if more to read
+fn fill(shared: &Shared, slot: &Slot, file: &mut File) -> std::io::Result<bool> {
+    let read = |file: &mut File, pos: u64, len: u64| read_at(file, shared.offset, shared.len, pos, len);
+    if !slot.used.load(Ordering::Acquire) {
+        let mut window = slot.window.lock().unwrap();
+        if window.data.capacity() > 0 {
+            let data = std::mem::take(&mut window.data);
+            drop(window);
+            drop(data);
+        }
+        return Ok(false);
+    }
+    if shared.cap == 0 {
+        return Ok(false);
+    }
+    let len = shared.len as i64;
+    let want = slot.want.load(Ordering::Relaxed) & shared.align;
+    let (start, filled, capacity) = {
+        let window = slot.window.lock().unwrap();
+        (window.start, window.data.len() as u64, window.data.capacity() as u64)
+    };
+    if capacity < shared.cap {
+        // allocated here rather than on audio thread
+        let mut data = VecDeque::with_capacity(shared.cap as usize);
+        let mut window = slot.window.lock().unwrap();
+        data.extend(window.data.drain(..));
+        window.data = data;
+    }
+    // signed distance of wanted offset past window start, around the wrap
+    let mut ahead = (want as i64 - start as i64).rem_euclid(len);
+    if ahead > len / 2 {
+        ahead -= len;
+    }
+    // whole file buffered once cap reaches len, so never moved
+    let partial = shared.cap < shared.len;
+    if filled == 0 || (partial && (ahead < -(shared.cap as i64 / 4) || ahead > filled as i64)) {
+        // fresh, or sought away from window
+        let mut window = slot.window.lock().unwrap();
+        window.data.clear();
+        window.start = want;
+    } else if partial && ahead < 0 {
+        // played backward; read behind before taking lock
+        let back = (super::STREAM_CHUNK as i64).max(-ahead) as u64 & shared.align;
+        let target = (start as i64 - back as i64).rem_euclid(len) as u64;
+        let front = read(file, target, back)?;
+        let mut window = slot.window.lock().unwrap();
+        let keep = shared.cap.saturating_sub(back).min(filled) as usize;
+        window.data.truncate(keep);
+        for v in front.into_iter().rev() {
+            window.data.push_front(v);
+        }
+        window.start = target;
+        return Ok(true);
+    } else if partial && ahead as u64 > shared.cap / 2 {
+        // keep a quarter of window behind reader
+        let drain = (ahead as u64 - shared.cap / 4) & shared.align;
+        let mut window = slot.window.lock().unwrap();
+        window.data.drain(..drain as usize);
+        window.start = (start + drain) % shared.len;
+    }
+    let (start, filled) = {
+        let window = slot.window.lock().unwrap();
+        (window.start, window.data.len() as u64)
+    };
+    if filled >= shared.cap {
+        return Ok(false);
+    }
+    let chunk = read(file, start + filled, (shared.cap - filled).min(super::STREAM_CHUNK))?;
+    slot.window.lock().unwrap().data.extend(chunk);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16-bit wav whose every byte is its pcm offset mod 251
+    fn wav(name: &str, len: usize) -> std::path::PathBuf {
+        let pcm: Vec<u8> = (0..len).map(|v| (v % 251) as u8).collect();
+        let path = std::env::temp_dir().join(format!("{}-{}.wav", name, std::process::id()));
+        std::fs::write(&path, super::super::bounce::image(44100, &pcm).unwrap()).unwrap();
+        path
+    }
+
+    fn expect(pos: u64, len: usize, pcm_len: u64) -> Vec<u8> {
+        (0..len as u64).map(|v| ((pos + v) % pcm_len % 251) as u8).collect()
+    }
+
+    /// wait for io thread to fill window around reader
+    fn settle(stream: &mut Stream, pos: u64, len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; len];
+        for _ in 0..500 {
+            stream.seek(SeekFrom::Start(stream.shared.offset + pos)).unwrap();
+            stream.read_exact(&mut buf).unwrap();
+            if buf.iter().any(|v| *v != 0) && buf == expect(pos, len, stream.shared.len) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        buf
+    }
+
+    #[test]
+    fn onset_heads_read_before_io() {
+        let len = 4 * super::super::STREAM_LEN as usize;
+        let path = wav("stream-heads", len);
+        let start = 3 * super::super::STREAM_LEN;
+        let handle = Handle::open(&path, &[start]).unwrap();
+        let offset = handle.header().offset;
+        // every window taken, so only heads are read
+        let streams: Vec<_> = (0..super::super::STREAM_VOICES).map(|_| handle.stream()).collect();
+        let mut stream = handle.stream();
+        assert!(stream.slot.is_none());
+        let mut buf = vec![0u8; 4096];
+        stream.seek(SeekFrom::Start(offset + start)).unwrap();
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, expect(start, buf.len(), len as u64));
+        // before start too, for nudges and reverse
+        stream.seek(SeekFrom::Start(offset + start - 4096)).unwrap();
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, expect(start - 4096, buf.len(), len as u64));
+        // away from heads is silent
+        stream.seek(SeekFrom::Start(offset + start / 2)).unwrap();
+        stream.read_exact(&mut buf).unwrap();
+        assert!(buf.iter().all(|v| *v == 0));
+        // freed window taken by next reader
+        drop(streams);
+        assert!(handle.stream().slot.is_some());
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn window_follows_seek_and_wrap() {
+        let len = 3 * super::super::STREAM_LEN as usize;
+        let path = wav("stream-window", len);
+        // no heads, so only window is read
+        let handle = Handle::open(&path, &[]).unwrap();
+        let mut stream = handle.stream();
+        assert!(stream.slot.is_some());
+        // sought past head, then filled by io thread
+        let pos = 2 * super::super::STREAM_LEN;
+        assert_eq!(settle(&mut stream, pos, 4096), expect(pos, 4096, len as u64));
+        // read across end of pcm continues from its start
+        let end = len as u64 - 1024;
+        let got = settle(&mut stream, end, 1024);
+        assert_eq!(got, expect(end, 1024, len as u64));
+        let mut buf = vec![0u8; 1024];
+        assert_eq!(stream.read(&mut buf).unwrap(), 0);
+        // window read on past end into start, rather than moved
+        assert_eq!(settle(&mut stream, 0, 1024), expect(0, 1024, len as u64));
+        let window = stream.shared.slots[stream.slot.unwrap()].window.lock().unwrap();
+        assert!(window.start > len as u64 / 2 && window.start + window.data.len() as u64 > len as u64);
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -1,48 +1,21 @@
-use crate::{audio, tui};
+use crate::{audio, pll, tui};
 use audio::PAD_COUNT;
 
 use color_eyre::Result;
 use midly::{live::LiveEvent, MidiMessage};
-use std::{path::{Path, PathBuf}, sync::mpsc::Sender};
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::mpsc::Sender};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub enum Bank {
     A,
     B,
 }
 
-/**
-    chords:
-        Reverse\*: reverse bank playback
-        Hold\*: toggle hold
-        Kit\* + Pad\*: load pad's kit
-        Shift\* + Reverse\*: init record
-            bake phrase \*
-            first Pad\*: assign phrase to pad
-            more Pad\*s: phrase len
-            release Reverse\*: take phrase, assign to first held pad, if any
-        Shift\* + Hold\*: init build pool
-            Pad\*s: push pads' phrase to pool
-            release Hold\*: clear pool if unchanged
-        Shift\* + Kit\* + Pad\*: save bank to pad's kit
-
-        Global + HoldB: open onset fs
-            KitB: decrement
-            ShiftB: increment
-            HoldB: into wav/dir
-            in wav:
-                KitB: decrement
-                ShiftB: increment
-                Pad\*: assign to first Pad\* onset
-                Reverse\* + Pad\*: assign to second Pad\* onset
-            release Global: exit fs
-        Global + KitB: open scene fs
-            KitB: decrement
-            ShiftB: increment
-            HoldB: load scene / into dir
-            release Global: exit fs
-        Global + ReverseB: save active scene to new .sd
-*/
+/// chord vocabulary, shown in tui help
+pub const CHORDS: &str = include_str!("chords.txt");
+
+#[doc = concat!("```text\n", include_str!("chords.txt"), "```")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 enum KeyCode {
     BankAOffset = 48,
     ShiftA = 56,
@@ -57,25 +30,274 @@ enum KeyCode {
     ShiftB = 63,
     ReverseB = 64,
     BankBOffset = 65,
+
+    Tap = 47,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 enum CtrlCode {
     GainA = 23,
     SpeedA = 105,
     DriftA = 106,
+    PhraseDriftA = 107,
+    ProbA = 108,
     BiasA = 29,
     WidthA = 26,
-    
+    PadGainA = 30,
+    CutoffA = 74,
+    ResonanceA = 71,
+    FeedbackA = 12,
+    WetA = 13,
+    CrushDepthA = 16,
+    CrushRateA = 17,
+    TremoloDepthA = 85,
+    TremoloPeriodA = 86,
+    TremoloShapeA = 87,
+    PanDepthA = 91,
+    PanRateA = 92,
+
     GainB = 83,
     SpeedB = 102,
     DriftB = 103,
+    PhraseDriftB = 104,
+    ProbB = 109,
     BiasB = 28,
     WidthB = 24,
+    PadGainB = 31,
+    CutoffB = 77,
+    ResonanceB = 76,
+    FeedbackB = 14,
+    WetB = 15,
+    CrushDepthB = 18,
+    CrushRateB = 19,
+    TremoloDepthB = 88,
+    TremoloPeriodB = 89,
+    TremoloShapeB = 90,
+    PanDepthB = 93,
+    PanRateB = 94,
+
+    GrainLen = 20,
+    Swing = 21,
+    DuckDepth = 95,
+    LoopDivA = 110,
+    LoopDivB = 111,
+    HumanizeA = 112,
+    HumanizeB = 113,
+    PreRollA = 114,
+    PreRollB = 115,
+    DuckRelease = 96,
+    ClickLevel = 97,
+    ClickBar = 98,
+    NudgeA = 22,
+    NudgeB = 25,
+    DelayTimeA = 116,
+    DelayTimeB = 117,
+}
+
+impl KeyCode {
+    const ALL: [Self; 12] = [
+        Self::BankAOffset, Self::ShiftA, Self::ReverseA, Self::KitA, Self::HoldA,
+        Self::Global,
+        Self::KitB, Self::HoldB, Self::ShiftB, Self::ReverseB, Self::BankBOffset,
+        Self::Tap,
+    ];
+
+    /// consecutive notes mapped from note
+    fn span(&self) -> u8 {
+        match self {
+            Self::BankAOffset | Self::BankBOffset => PAD_COUNT as u8,
+            _ => 1,
+        }
+    }
+}
+
+impl CtrlCode {
+    /// ctrls refined by 14-bit lsb, with default lsb cc; spare ccs for speeds, whose msbs lie above LSB_OFFSET
+    const FINE: [(Self, u8); 4] = [
+        (Self::SpeedA, 118),
+        (Self::SpeedB, 119),
+        (Self::WidthA, Self::WidthA as u8 + audio::LSB_OFFSET),
+        (Self::WidthB, Self::WidthB as u8 + audio::LSB_OFFSET),
+    ];
+    const ALL: [Self; 54] = [
+        Self::GainA, Self::SpeedA, Self::DriftA, Self::PhraseDriftA, Self::ProbA, Self::BiasA, Self::WidthA, Self::PadGainA,
+        Self::CutoffA, Self::ResonanceA, Self::FeedbackA, Self::WetA, Self::CrushDepthA, Self::CrushRateA,
+        Self::TremoloDepthA, Self::TremoloPeriodA, Self::TremoloShapeA, Self::PanDepthA, Self::PanRateA,
+        Self::GainB, Self::SpeedB, Self::DriftB, Self::PhraseDriftB, Self::ProbB, Self::BiasB, Self::WidthB, Self::PadGainB,
+        Self::CutoffB, Self::ResonanceB, Self::FeedbackB, Self::WetB, Self::CrushDepthB, Self::CrushRateB,
+        Self::TremoloDepthB, Self::TremoloPeriodB, Self::TremoloShapeB, Self::PanDepthB, Self::PanRateB,
+        Self::GrainLen, Self::Swing, Self::DuckDepth, Self::DuckRelease, Self::NudgeA, Self::NudgeB,
+        Self::LoopDivA, Self::LoopDivB, Self::ClickLevel, Self::ClickBar,
+        Self::HumanizeA, Self::HumanizeB, Self::PreRollA, Self::PreRollB,
+        Self::DelayTimeA, Self::DelayTimeB,
+    ];
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct ControlsFile {
+    keys: HashMap<KeyCode, u8>,
+    ctrls: HashMap<CtrlCode, u8>,
+    /// lsb cc per fine ctrl
+    lsbs: HashMap<CtrlCode, u8>,
+    /// channel aftertouch target
+    touch: audio::Touch,
+}
+
+/// note and cc number lookup
+struct Controls {
+    path: Box<Path>,
+    file: ControlsFile,
+    /// key code and offset into span per note
+    keys: [Option<(KeyCode, u8)>; 128],
+    ctrls: [Option<CtrlCode>; 128],
+    /// fine ctrl refined per lsb cc
+    lsbs: [Option<CtrlCode>; 128],
+}
+
+impl Controls {
+    /// load remapped codes, if any, falling back to defaults
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file: ControlsFile = match std::fs::read_to_string(&path) {
+            Ok(v) => serde_json::from_str(&v)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ControlsFile::default(),
+            Err(e) => Err(e)?,
+        };
+        let mut controls = Self {
+            path: path.as_ref().into(),
+            file,
+            keys: [None; 128],
+            ctrls: [None; 128],
+            lsbs: [None; 128],
+        };
+        controls.remap();
+        Ok(controls)
+    }
+
+    /// rebuild lookup from file
+    fn remap(&mut self) {
+        self.keys = [None; 128];
+        self.ctrls = [None; 128];
+        self.lsbs = [None; 128];
+        // map defaults first so remapped codes take precedence
+        for remapped in [false, true] {
+            for code in KeyCode::ALL.into_iter().filter(|v| self.file.keys.contains_key(v) == remapped) {
+                let note = self.file.keys.get(&code).copied().unwrap_or(code as u8);
+                for i in 0..code.span() {
+                    if let Some(v) = self.keys.get_mut(note as usize + i as usize) {
+                        *v = Some((code, i));
+                    }
+                }
+            }
+            for code in CtrlCode::ALL.into_iter().filter(|v| self.file.ctrls.contains_key(v) == remapped) {
+                let number = self.file.ctrls.get(&code).copied().unwrap_or(code as u8);
+                if let Some(v) = self.ctrls.get_mut(number as usize) {
+                    *v = Some(code);
+                }
+            }
+            for (code, lsb) in CtrlCode::FINE.into_iter().filter(|v| self.file.lsbs.contains_key(&v.0) == remapped) {
+                let number = self.file.lsbs.get(&code).copied().unwrap_or(lsb);
+                if let Some(v) = self.lsbs.get_mut(number as usize) {
+                    *v = Some(code);
+                }
+            }
+        }
+    }
+
+    /// bind target to note or cc number, persisting to file
+    fn bind(&mut self, target: LearnTarget, number: u8) -> Result<()> {
+        match target {
+            LearnTarget::Key(code) => self.file.keys.insert(code, number),
+            LearnTarget::Ctrl(code) => self.file.ctrls.insert(code, number),
+        };
+        self.remap();
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.file)?)?;
+        Ok(())
+    }
+
+    fn key(&self, note: u8) -> Option<(KeyCode, u8)> {
+        self.keys[note as usize]
+    }
+
+    fn ctrl(&self, number: u8) -> Option<CtrlCode> {
+        self.ctrls[number as usize]
+    }
+
+    /// fine ctrl whose lsb is cc `number`, unless `number` is mapped itself
+    fn fine(&self, number: u8) -> Option<CtrlCode> {
+        self.lsbs[number as usize].filter(|_| self.ctrl(number).is_none())
+    }
+
+    /// lsb cc of fine ctrl, unless mapped to another ctrl
+    fn lsb(&self, code: CtrlCode) -> Option<u8> {
+        let number = self.lsbs.iter().position(|v| *v == Some(code))? as u8;
+        self.fine(number).map(|_| number)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum LearnTarget {
+    Key(KeyCode),
+    Ctrl(CtrlCode),
+}
+
+impl LearnTarget {
+    const FIRST: Self = Self::Key(KeyCode::BankAOffset);
+
+    /// next target in learn order; Global cancels learn, so isn't learned
+    fn next(self) -> Option<Self> {
+        let mut targets = KeyCode::ALL
+            .into_iter()
+            .filter(|v| *v != KeyCode::Global)
+            .map(Self::Key)
+            .chain(CtrlCode::ALL.into_iter().map(Self::Ctrl));
+        targets.by_ref().find(|v| *v == self)?;
+        targets.next()
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Self::Key(v) => format!("{:?}", v),
+            Self::Ctrl(v) => format!("{:?}", v),
+        }
+    }
+}
+
+/// pad velocity to gain
+#[derive(Copy, Clone, Default)]
+enum VelCurve {
+    #[default]
+    Linear,
+    Square,
+    Fixed,
+}
+
+impl VelCurve {
+    fn gain(&self, vel: u8) -> f32 {
+        let vel = vel as f32 / 127.;
+        match self {
+            Self::Linear => vel,
+            Self::Square => vel * vel,
+            Self::Fixed => 1.,
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Self::Linear => Self::Square,
+            Self::Square => Self::Fixed,
+            Self::Fixed => Self::Linear,
+        }
+    }
 }
 
 enum GlobalState {
     Yield,
     Prime,
+    Learn {
+        target: LearnTarget,
+        since: std::time::Instant,
+    },
     LoadScene {
         paths: Vec<Box<Path>>,
         file_index: usize,
@@ -87,10 +309,17 @@ enum GlobalState {
     AssignOnset {
         paths: Vec<Box<Path>>,
         file_index: usize,
+        wav: audio::Wav,
         rd: audio::Rd,
         onset_index: usize,
         alt: bool,
-    }
+    },
+    /// kit file picked in scene fs, awaiting slot
+    StoreKit {
+        paths: Vec<Box<Path>>,
+        file_index: usize,
+        kit: Box<audio::pads::Kit<PAD_COUNT>>,
+    },
 }
 
 enum BankState {
@@ -105,8 +334,30 @@ struct BankHandler {
     bank: Bank,
     hold: bool,
     reverse: bool,
+    /// play phrases last event to first
+    phrase_reverse: bool,
+    pool_order: audio::PoolOrder,
+    pad_mode: audio::PadMode,
+    /// loop length denominator
+    loop_div: u8,
+    /// bars counted in before record captures, 0 capturing at once
+    preroll: u8,
+    /// record counting in or capturing since count-in; next init bakes
+    armed: bool,
+    scale: audio::Scale,
+    /// retriggering held onsets until chord up
+    stutter: bool,
+    /// grain position held in place
+    frozen: bool,
+    /// song sections defined, and whether playing
+    sections: usize,
+    song: bool,
+    /// pads pushed to pool since last section
+    pool_pushed: bool,
     downs: Vec<u8>,
     shift: bool,
+    /// velocity gain of last pad down
+    vel: f32,
     state: BankState,
 }
 
@@ -134,17 +385,32 @@ impl BankHandler {
             bank,
             hold: false,
             reverse: false,
+            phrase_reverse: false,
+            pool_order: audio::PoolOrder::default(),
+            pad_mode: audio::PadMode::default(),
+            loop_div: audio::LOOP_DIV,
+            preroll: 0,
+            armed: false,
+            scale: audio::Scale::default(),
+            stutter: false,
+            frozen: false,
+            sections: 0,
+            song: false,
+            pool_pushed: false,
             downs: Vec::new(),
             shift: false,
+            vel: 1.,
             state: BankState::LoadOnset,
         }
     }
 
-    fn handle_reverse_up<const N: usize>(&mut self, pads_tx: &mut Sender<audio::Cmd<N>>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+    fn handle_reverse_up<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
         match self.state {
+            BankState::BuildPool { .. } | BankState::AssignKit => self.reverse = false,
             BankState::LoadOnset => {
                 self.reverse = false;
                 pads_tx.send(audio_bank_cmd!(self.bank, AssignReverse, false))?;
+                tui_tx.send(tui_bank_cmd!(self.bank, Reverse, false))?;
             }
             BankState::BakeRecord => {
                 // exit record
@@ -157,12 +423,28 @@ impl BankHandler {
         Ok(())
     }
 
-    fn handle_reverse_down<const N: usize>(&mut self, pads_tx: &mut Sender<audio::Cmd<N>>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
-        if let BankState::LoadOnset = self.state {
-            if self.shift {
+    fn handle_reverse_down<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+        if let BankState::LoadKit = self.state {
+            // toggle phrase direction
+            self.phrase_reverse = !self.phrase_reverse;
+            pads_tx.send(audio_bank_cmd!(self.bank, AssignPhraseReverse, self.phrase_reverse))?;
+            let bank = match self.bank { Bank::A => "a", Bank::B => "b" };
+            let direction = if self.phrase_reverse { "reversed" } else { "forward" };
+            tui_tx.send(tui::Cmd::Log(format!("bank {} phrases {}", bank, direction)))?;
+        } else if let BankState::BuildPool { .. } | BankState::AssignKit = self.state {
+            // pads clear phrases or copy kits while held
+            self.reverse = true;
+        } else if let BankState::LoadOnset = self.state {
+            if self.shift && self.preroll > 0 && !self.armed {
+                // count in, then capture until init again
+                self.armed = true;
+                pads_tx.send(audio_bank_cmd!(self.bank, ArmRecord, self.preroll))?;
+            } else if self.shift {
                 // init record
+                self.armed = false;
                 self.state = BankState::BakeRecord;
                 self.hold = false;
+                tui_tx.send(tui_bank_cmd!(self.bank, Hold, false))?;
                 if self.downs.is_empty() {
                     pads_tx.send(audio_bank_cmd!(self.bank, PushEvent, audio::Event::Sync))?;
                 }
@@ -171,12 +453,63 @@ impl BankHandler {
             } else {
                 self.reverse = true;
                 pads_tx.send(audio_bank_cmd!(self.bank, AssignReverse, true))?;
+                tui_tx.send(tui_bank_cmd!(self.bank, Reverse, true))?;
             }
         }
         Ok(())
     }
 
-    fn handle_hold_up<const N: usize>(&mut self, pads_tx: &mut Sender<audio::Cmd<N>>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+    fn handle_shift_down<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+        if let BankState::BuildPool { .. } = self.state {
+            let bank = match self.bank { Bank::A => "a", Bank::B => "b" };
+            if self.reverse {
+                // start song, or stop and clear it
+                if self.song || self.sections == 0 {
+                    self.song = false;
+                    self.sections = 0;
+                    tui_tx.send(tui::Cmd::Log(format!("bank {} song cleared", bank)))?;
+                } else {
+                    self.song = true;
+                }
+                pads_tx.send(audio_bank_cmd!(self.bank, ToggleSong))?;
+            } else if self.pool_pushed || self.sections == 0 {
+                // append pool as section
+                self.sections += 1;
+                self.pool_pushed = false;
+                pads_tx.send(audio_bank_cmd!(self.bank, PushSection))?;
+                tui_tx.send(tui::Cmd::Log(format!("bank {} song section {}", bank, self.sections)))?;
+            } else {
+                // unchanged pool repeats last section
+                pads_tx.send(audio_bank_cmd!(self.bank, RepeatSection))?;
+                tui_tx.send(tui::Cmd::Log(format!("bank {} song section {} repeated", bank, self.sections)))?;
+            }
+        } else if let BankState::LoadKit = self.state {
+            // toggle grain freeze
+            self.frozen = !self.frozen;
+            pads_tx.send(audio_bank_cmd!(self.bank, AssignFrozen, self.frozen))?;
+            let bank = match self.bank { Bank::A => "a", Bank::B => "b" };
+            let state = if self.frozen { "frozen" } else { "thawed" };
+            tui_tx.send(tui::Cmd::Log(format!("bank {} {}", bank, state)))?;
+        } else if let (BankState::LoadOnset, true, audio::PadMode::Chromatic(_)) = (&self.state, self.reverse, self.pad_mode) {
+            // cycle chromatic scale
+            self.scale = self.scale.next();
+            pads_tx.send(audio_bank_cmd!(self.bank, AssignScale, self.scale))?;
+            let bank = match self.bank { Bank::A => "a", Bank::B => "b" };
+            tui_tx.send(tui::Cmd::Log(format!("bank {} scale {}", bank, self.scale.name())))?;
+        }
+        Ok(())
+    }
+
+    fn release_stutter<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>) -> Result<()> {
+        if self.stutter {
+            self.stutter = false;
+            pads_tx.send(audio_bank_cmd!(self.bank, AssignStutter, None))?;
+        }
+        Ok(())
+    }
+
+    fn handle_hold_up<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+        self.release_stutter(pads_tx)?;
         if let BankState::BuildPool { cleared } = self.state {
             // exit build pool
             if !cleared {
@@ -189,14 +522,19 @@ impl BankHandler {
         Ok(())
     }
 
-    fn handle_hold_down<const N: usize>(&mut self, pads_tx: &mut Sender<audio::Cmd<N>>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
-        if let BankState::LoadOnset = self.state {
+    fn handle_hold_down<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+        if let BankState::LoadKit = self.state {
+            // stutter while held
+            self.stutter = true;
+            pads_tx.send(audio_bank_cmd!(self.bank, AssignStutter, Some(audio::STUTTER)))?;
+        } else if let BankState::LoadOnset = self.state {
             if self.shift {
                 // init build pool
                 self.state = BankState::BuildPool { cleared: false };
                 tui_tx.send(tui_bank_cmd!(self.bank, BuildPool))?;
             } else {
                 self.hold = !self.hold;
+                tui_tx.send(tui_bank_cmd!(self.bank, Hold, self.hold))?;
                 if !self.hold && self.downs.is_empty() {
                     pads_tx.send(audio_bank_cmd!(self.bank, PushEvent, audio::Event::Sync))?;
                 }
@@ -205,7 +543,8 @@ impl BankHandler {
         Ok(())
     }
 
-    fn handle_kit_up(&mut self, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+    fn handle_kit_up<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+        self.release_stutter(pads_tx)?;
         match self.state {
             BankState::LoadKit => {
                 // exit load kit
@@ -222,9 +561,28 @@ impl BankHandler {
         Ok(())
     }
 
-    fn handle_kit_down(&mut self, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
-        if let BankState::LoadOnset = self.state {
-            if self.shift {
+    fn handle_kit_down<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+        if let BankState::BuildPool { .. } = self.state {
+            // cycle pool order
+            self.pool_order = self.pool_order.next();
+            pads_tx.send(audio_bank_cmd!(self.bank, AssignPoolOrder, self.pool_order))?;
+            let bank = match self.bank { Bank::A => "a", Bank::B => "b" };
+            tui_tx.send(tui::Cmd::Log(format!("bank {} pool {}", bank, self.pool_order.name())))?;
+        } else if let BankState::LoadOnset = self.state {
+            if self.reverse {
+                // toggle chromatic pads, rooted on first held pad
+                self.pad_mode = match self.pad_mode {
+                    audio::PadMode::Kit => audio::PadMode::Chromatic(self.downs.first().copied().unwrap_or(0)),
+                    audio::PadMode::Chromatic(_) => audio::PadMode::Kit,
+                };
+                pads_tx.send(audio_bank_cmd!(self.bank, AssignPadMode, self.pad_mode))?;
+                let bank = match self.bank { Bank::A => "a", Bank::B => "b" };
+                let mode = match self.pad_mode {
+                    audio::PadMode::Kit => "kit".to_string(),
+                    audio::PadMode::Chromatic(root) => format!("chromatic from pad {}", root),
+                };
+                tui_tx.send(tui::Cmd::Log(format!("bank {} pads {}", bank, mode)))?;
+            } else if self.shift {
                 // init assign kit
                 self.state = BankState::AssignKit;
                 tui_tx.send(tui_bank_cmd!(self.bank, AssignKit, None))?;
@@ -237,7 +595,17 @@ impl BankHandler {
         Ok(())
     }
 
-    fn handle_pad_up<const N: usize>(&mut self, pads_tx: &mut Sender<audio::Cmd<N>>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+    fn handle_program<const N: usize>(&mut self, index: u8, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+        pads_tx.send(audio_bank_cmd!(self.bank, LoadKit, index))?;
+        tui_tx.send(tui_bank_cmd!(self.bank, LoadKit, Some(index)))?;
+        if let BankState::LoadOnset = self.state {
+            // return from load kit view
+            tui_tx.send(tui_bank_cmd!(self.bank, LoadOnset))?;
+        }
+        Ok(())
+    }
+
+    fn handle_pad_up<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
         match self.state {
             BankState::LoadOnset => if !self.hold {
                 self.handle_pad_input(pads_tx)?;
@@ -260,16 +628,21 @@ impl BankHandler {
         Ok(())
     }
 
-    fn handle_pad_down<const N: usize>(&mut self, pads_tx: &mut Sender<audio::Cmd<N>>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
+    fn handle_pad_down<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>, tui_tx: &mut Sender<tui::Cmd>) -> Result<()> {
         match self.state {
             BankState::LoadOnset => self.handle_pad_input(pads_tx)?,
-            BankState::LoadKit => {
-                pads_tx.send(audio_bank_cmd!(self.bank, LoadKit, self.downs[0]))?;
-                tui_tx.send(tui_bank_cmd!(self.bank, LoadKit, Some(self.downs[0])))?;
+            BankState::LoadKit => if let Some(&index) = self.downs.first() {
+                pads_tx.send(audio_bank_cmd!(self.bank, LoadKit, index))?;
+                tui_tx.send(tui_bank_cmd!(self.bank, LoadKit, Some(index)))?;
             }
-            BankState::AssignKit => {
-                pads_tx.send(audio_bank_cmd!(self.bank, AssignKit, self.downs[0]))?;
-                tui_tx.send(tui_bank_cmd!(self.bank, AssignKit, Some(self.downs[0])))?;
+            BankState::AssignKit if self.reverse => if let Some(&index) = self.downs.first() {
+                // copy pad's kit into other bank
+                pads_tx.send(audio::Cmd::CopyKit(self.bank, index))?;
+                tui_tx.send(tui::Cmd::CopyKit(self.bank, index))?;
+            }
+            BankState::AssignKit => if let Some(&index) = self.downs.first() {
+                pads_tx.send(audio_bank_cmd!(self.bank, AssignKit, index))?;
+                tui_tx.send(tui_bank_cmd!(self.bank, AssignKit, Some(index)))?;
             }
             BankState::BakeRecord => {
                 let len = if self.downs.len() > 1 {
@@ -284,19 +657,28 @@ impl BankHandler {
                 pads_tx.send(audio_bank_cmd!(self.bank, BakeRecord, len))?;
                 tui_tx.send(tui_bank_cmd!(self.bank, BakeRecord, self.downs.first().copied(), len))?;
             }
+            BankState::BuildPool { .. } if self.reverse => if let Some(&index) = self.downs.last() {
+                // clear pad's phrase, keeping pool on exit
+                self.state = BankState::BuildPool { cleared: true };
+                pads_tx.send(audio_bank_cmd!(self.bank, ClearPhrase, index))?;
+                tui_tx.send(tui_bank_cmd!(self.bank, ClearPhrase, index))?;
+            }
             BankState::BuildPool { cleared } => {
                 if !cleared {
                     self.state = BankState::BuildPool { cleared: true };
                     pads_tx.send(audio_bank_cmd!(self.bank, ClearPool))?;
                     tui_tx.send(tui_bank_cmd!(self.bank, ClearPool))?;
                 }
-                pads_tx.send(audio_bank_cmd!(self.bank, PushPool, self.downs[0]))?;
+                if let Some(&index) = self.downs.first() {
+                    self.pool_pushed = true;
+                    pads_tx.send(audio_bank_cmd!(self.bank, PushPool, index))?;
+                }
             }
         }
         Ok(())
     }
 
-    fn handle_pad_input<const N: usize>(&mut self, pads_tx: &mut Sender<audio::Cmd<N>>) -> Result<()> {
+    fn handle_pad_input<const N: usize>(&mut self, pads_tx: &mut audio::CmdTx<N>) -> Result<()> {
         if let Some(&index) = self.downs.first() {
             if self.downs.len() > 1 {
                 // init loop start
@@ -304,11 +686,11 @@ impl BankHandler {
                     v.checked_sub(index + 1).unwrap_or(v + PAD_COUNT as u8 - 1 - index)
                 })
                 .fold(0u8, |acc, v| acc | (1 << v));
-                let len = audio::Fraction::new(numerator, audio::LOOP_DIV);
-                pads_tx.send(audio_bank_cmd!(self.bank, PushEvent, audio::Event::Loop { index, len }))?;
+                let len = audio::Fraction::new(numerator, self.loop_div);
+                pads_tx.send(audio_bank_cmd!(self.bank, PushEvent, audio::Event::Loop { index, len, vel: self.vel }))?;
             } else {
                 // init loop stop | jump
-                pads_tx.send(audio_bank_cmd!(self.bank, PushEvent, audio::Event::Hold { index }))?;
+                pads_tx.send(audio_bank_cmd!(self.bank, PushEvent, audio::Event::Hold { index, vel: self.vel }))?;
             }
         } else {
             // init sync
@@ -318,25 +700,107 @@ impl BankHandler {
     }
 }
 
+/// window of FILE_COUNT stems centered on index
+macro_rules! to_fs_at {
+    ($paths:expr,$index:expr) => {
+        {
+            let mut strings = [const { String::new() }; tui::FILE_COUNT];
+            if !$paths.is_empty() {
+                for i in 0..tui::FILE_COUNT {
+                    let index = ($index as isize + i as isize - tui::FILE_COUNT as isize / 2).rem_euclid($paths.len() as isize) as usize;
+                    strings[i] = $paths[index]
+                        .file_stem()
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .to_string();
+                }
+            }
+            tui::Listing { names: strings, index: $index, count: $paths.len() }
+        }
+    }
+}
+
 pub struct InputHandler {
+    controls: Controls,
     clock: u8,
+    /// incoming midi clocks per quarter, a multiple of STEP_DIV
+    ppq: u8,
     last_step: Option<std::time::Instant>,
+    /// smooths midi clock steps, if on
+    pll: Option<pll::Pll>,
+    /// decimated waveform per wav, if readable
+    peaks: HashMap<Box<Path>, Option<std::sync::Arc<[f32]>>>,
+    /// last msb per fine ctrl, and whether an lsb has followed one, so msbs await lsbs
+    msbs: HashMap<CtrlCode, (u8, bool)>,
+    /// modification time per wav loaded or assigned, for rescan
+    wavs: HashMap<Box<Path>, Option<std::time::SystemTime>>,
+    /// preloaded scenes for instant recall, if loadable
+    slots: Vec<Option<audio::pads::Scene<PAD_COUNT>>>,
+    /// slot last recalled, until a scene is loaded from file
+    slot: Option<u8>,
+    /// recent inter-step intervals in seconds
+    iois: std::collections::VecDeque<f32>,
+    last_tap: Option<std::time::Instant>,
+    /// recent inter-tap intervals in seconds
+    taps: std::collections::VecDeque<f32>,
+    interp: audio::Interp,
+    vel_curve: VelCurve,
+    limit: bool,
+    master: bool,
+    /// whether ableton link rather than midi clocks steps; none without link listener
+    link: Option<bool>,
+    /// metronome on
+    click: bool,
+    /// tap held, for tap + global
+    tap: bool,
+    /// save scenes as .sdb bincode rather than .sd json
+    scene_binary: bool,
+    /// prompt for scene names in tui rather than saving at once
+    naming: bool,
+    /// path and writer of take being bounced, if any
+    bounce: Option<(String, audio::bounce::Bounce)>,
 
     state: GlobalState,
+    /// last touched bank
+    focus: Bank,
     bank_a: BankHandler,
     bank_b: BankHandler,
 
-    pads_tx: Sender<audio::Cmd<PAD_COUNT>>,
+    pads_tx: audio::CmdTx<PAD_COUNT>,
     tui_tx: Sender<tui::Cmd>,
 }
 
 impl InputHandler {
-    pub fn new(tui_tx: Sender<tui::Cmd>, pads_tx: Sender<audio::Cmd<PAD_COUNT>>) -> Result<Self> {
+    pub fn new(tui_tx: Sender<tui::Cmd>, mut pads_tx: audio::CmdTx<PAD_COUNT>) -> Result<Self> {
+        pads_tx.assign_tui(tui_tx.clone());
         Ok(Self {
+            controls: Controls::load("controls.json")?,
             clock: 0,
+            ppq: audio::PPQ,
             last_step: None,
+            pll: None,
+            peaks: HashMap::new(),
+            msbs: HashMap::new(),
+            wavs: HashMap::new(),
+            slots: Vec::new(),
+            slot: None,
+            iois: std::collections::VecDeque::with_capacity(audio::TEMPO_WINDOW),
+            last_tap: None,
+            taps: std::collections::VecDeque::with_capacity(audio::TAP_COUNT),
+            interp: audio::Interp::default(),
+            vel_curve: VelCurve::default(),
+            limit: false,
+            master: false,
+            link: None,
+            click: false,
+            tap: false,
+            scene_binary: false,
+            naming: false,
+            bounce: None,
 
             state: GlobalState::Yield,
+            focus: Bank::A,
             bank_a: BankHandler::new(Bank::A),
             bank_b: BankHandler::new(Bank::B),
 
@@ -346,31 +810,21 @@ impl InputHandler {
     }
 
     pub fn push(&mut self, message: &[u8]) -> Result<()> {
-        macro_rules! to_fs_at {
-            ($paths:expr,$index:expr) => {
-                {
-                    let mut strings = [const { String::new() }; tui::FILE_COUNT];
-                    if !$paths.is_empty() {
-                        for i in 0..tui::FILE_COUNT {
-                            let index = ($index as isize + i as isize - tui::FILE_COUNT as isize / 2).rem_euclid($paths.len() as isize) as usize;
-                            strings[i] = $paths[index]
-                                .file_stem()
-                                .unwrap()
-                                .to_str()
-                                .unwrap()
-                                .to_string();
-                        }
-                    }
-                    strings
-                }
+        let event = LiveEvent::parse(message)?;
+        if let GlobalState::Learn { since, .. } = self.state {
+            if since.elapsed() < tui::LEARN_TIMEOUT {
+                return self.learn(event);
             }
+            // timed out; handle as usual
+            self.state = GlobalState::Yield;
+            self.tui_tx.send(tui::Cmd::Yield)?;
         }
-        match LiveEvent::parse(message)? {
+        match event {
             LiveEvent::Midi { message, .. } => {
                 match message {
-                    MidiMessage::NoteOff{ key, .. } => match key.as_int() {
-                        v if (KeyCode::BankAOffset as u8..KeyCode::BankAOffset as u8 + PAD_COUNT as u8).contains(&v) => {
-                            let index = PAD_COUNT as u8 - 1 - (v - KeyCode::BankAOffset as u8);
+                    MidiMessage::NoteOff{ key, .. } => match self.controls.key(key.as_int()) {
+                        Some((KeyCode::BankAOffset, v)) => {
+                            let index = PAD_COUNT as u8 - 1 - v;
                             self.bank_a.downs.retain(|&v| v != index);
                             self.tui_tx.send(tui_bank_cmd!(Bank::A, Pad, index, false))?;
                             match self.state {
@@ -383,40 +837,42 @@ impl InputHandler {
                                 _ => (),
                             }
                         }
-                        v if v == KeyCode::ShiftA as u8 => self.bank_a.shift = false,
-                        v if v == KeyCode::ReverseA as u8 => if let GlobalState::Yield = self.state {
+                        Some((KeyCode::ShiftA, _)) => self.bank_a.shift = false,
+                        Some((KeyCode::ReverseA, _)) => if let GlobalState::Yield = self.state {
                             self.bank_a.handle_reverse_up(&mut self.pads_tx, &mut self.tui_tx)?;
                         }
-                        v if v == KeyCode::KitA as u8 => if let GlobalState::Yield = self.state {
-                            self.bank_a.handle_kit_up(&mut self.tui_tx)?;
+                        Some((KeyCode::KitA, _)) => if let GlobalState::Yield = self.state {
+                            self.bank_a.handle_kit_up(&mut self.pads_tx, &mut self.tui_tx)?;
                         }
-                        v if v == KeyCode::HoldA as u8 => if let GlobalState::Yield = self.state {
+                        Some((KeyCode::HoldA, _)) => if let GlobalState::Yield = self.state {
                             self.bank_a.handle_hold_up(&mut self.pads_tx, &mut self.tui_tx)?;
                         }
-                        v if v == KeyCode::Global as u8 => {
+                        Some((KeyCode::Global, _)) => {
                             self.state = GlobalState::Yield;
                             self.tui_tx.send(tui::Cmd::Yield)?;
                         }
-                        v if v == KeyCode::KitB as u8 => if let GlobalState::Yield = self.state {
-                            self.bank_b.handle_kit_up(&mut self.tui_tx)?;
+                        Some((KeyCode::Tap, _)) => self.tap = false,
+                        Some((KeyCode::KitB, _)) => if let GlobalState::Yield = self.state {
+                            self.bank_b.handle_kit_up(&mut self.pads_tx, &mut self.tui_tx)?;
                         }
-                        v if v == KeyCode::HoldB as u8 => if let GlobalState::Yield = self.state {
+                        Some((KeyCode::HoldB, _)) => if let GlobalState::Yield = self.state {
                             self.bank_b.handle_hold_up(&mut self.pads_tx, &mut self.tui_tx)?;
                         }
-                        v if v == KeyCode::ShiftB as u8 => self.bank_b.shift = false,
-                        v if v == KeyCode::ReverseB as u8 => match &mut self.state {
+                        Some((KeyCode::ShiftB, _)) => self.bank_b.shift = false,
+                        Some((KeyCode::ReverseB, _)) => match &mut self.state {
                             GlobalState::Yield => {
                                 self.bank_b.handle_reverse_up(&mut self.pads_tx, &mut self.tui_tx)?;
                             }
-                            GlobalState::AssignOnset { paths, file_index, rd, onset_index, alt } => {
+                            GlobalState::AssignOnset { paths, file_index, wav, rd, onset_index, alt } => {
                                 *alt = false;
                                 let name = paths[*file_index].file_stem().unwrap().to_str().unwrap().to_string();
-                                self.tui_tx.send(tui::Cmd::AssignOnset { name, index: *onset_index, count: rd.onsets.len(), alt: *alt })?;
+                                let pos = rd.onsets[*onset_index] as f32 / wav.len as f32;
+                                self.tui_tx.send(tui::Cmd::AssignOnset { name, index: *onset_index, count: rd.onsets.len(), alt: *alt, pos })?;
                             }
                             _ => (),
                         }
-                        v if (KeyCode::BankBOffset as u8..KeyCode::BankBOffset as u8 + PAD_COUNT as u8).contains(&v) => {
-                            let index = v - KeyCode::BankBOffset as u8;
+                        Some((KeyCode::BankBOffset, v)) => {
+                            let index = v;
                             self.bank_b.downs.retain(|&v| v != index);
                             self.tui_tx.send(tui_bank_cmd!(Bank::B, Pad, index, false))?;
                             match self.state {
@@ -431,50 +887,101 @@ impl InputHandler {
                         }
                         _ => (),
                     }
-                    MidiMessage::NoteOn { key, .. } => match key.as_int() {
-                        v if (KeyCode::BankAOffset as u8..KeyCode::BankAOffset as u8 + PAD_COUNT as u8).contains(&v) => {
-                            let index = PAD_COUNT as u8 - 1 - (v - KeyCode::BankAOffset as u8);
+                    MidiMessage::NoteOn { key, vel } => match self.controls.key(key.as_int()) {
+                        Some((KeyCode::BankAOffset, v)) => {
+                            let index = PAD_COUNT as u8 - 1 - v;
                             self.bank_a.downs.push(index);
+                            self.bank_a.vel = self.vel_curve.gain(vel.as_int());
+                            self.focus = Bank::A;
                             self.tui_tx.send(tui_bank_cmd!(Bank::A, Pad, index, true))?;
                             match &self.state {
+                                GlobalState::Yield if self.tap => self.recall(index)?,
                                 GlobalState::Yield => {
                                     self.bank_a.handle_pad_down(&mut self.pads_tx, &mut self.tui_tx)?;
                                 }
-                                GlobalState::AssignOnset { paths, file_index, rd, onset_index, alt } => {
+                                GlobalState::StoreKit { kit, .. } => {
+                                    let kit = kit.clone();
+                                    self.store_kit(Bank::A, index, kit)?;
+                                }
+                                GlobalState::Prime => self.swap_banks()?,
+                                GlobalState::AssignOnset { wav, rd, onset_index, alt, .. } => {
                                     // assign onset to pad
-                                    let len = std::fs::metadata(&paths[*file_index])?.len() - 44;
                                     let start = rd.onsets[*onset_index];
-                                    let wav = audio::Wav {
-                                        tempo: rd.tempo,
-                                        steps: rd.steps,
-                                        path: paths[*file_index].clone(),
-                                        len,
-                                    };
-                                    let onset = audio::Onset { wav, start };
+                                    let onset = audio::Onset { wav: wav.clone(), start };
                                     self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignOnset, index, *alt, Box::new(onset)))?;
                                 }
                                 _ => (),
                             }
                         }
-                        v if v == KeyCode::ShiftA as u8 => self.bank_a.shift = true,
-                        v if v == KeyCode::ReverseA as u8 => if let GlobalState::Yield = self.state {
-                            self.bank_a.handle_reverse_down(&mut self.pads_tx, &mut self.tui_tx)?;
+                        Some((KeyCode::ShiftA, _)) => {
+                            self.bank_a.shift = true;
+                            match self.state {
+                                GlobalState::Yield => self.bank_a.handle_shift_down(&mut self.pads_tx, &mut self.tui_tx)?,
+                                GlobalState::Prime => {
+                                    // toggle grain interpolation
+                                    self.interp = match self.interp {
+                                        audio::Interp::Linear => audio::Interp::Hermite,
+                                        audio::Interp::Hermite => audio::Interp::Linear,
+                                    };
+                                    self.pads_tx.send(audio::Cmd::AssignInterp(self.interp))?;
+                                }
+                                _ => (),
+                            }
+                        }
+                        Some((KeyCode::ReverseA, _)) => match self.state {
+                            GlobalState::Yield if self.tap => self.save_kit(Bank::A)?,
+                            GlobalState::Yield => self.bank_a.handle_reverse_down(&mut self.pads_tx, &mut self.tui_tx)?,
+                            GlobalState::Prime => {
+                                // toggle output soft-clip
+                                self.limit = !self.limit;
+                                self.pads_tx.send(audio::Cmd::AssignLimit(self.limit))?;
+                            }
+                            _ => (),
+                        }
+                        Some((KeyCode::KitA, _)) => match self.state {
+                            GlobalState::Yield if self.tap => self.assign_link(self.link.map(|v| !v))?,
+                            GlobalState::Yield => self.bank_a.handle_kit_down(&mut self.pads_tx, &mut self.tui_tx)?,
+                            GlobalState::Prime => {
+                                // toggle internal clock; one clock source at a time
+                                self.master = !self.master;
+                                self.pads_tx.send(audio::Cmd::AssignMaster(self.master))?;
+                                if self.master && self.link == Some(true) {
+                                    self.assign_link(Some(false))?;
+                                }
+                            }
+                            _ => (),
+                        }
+                        Some((KeyCode::HoldA, _)) => match self.state {
+                            GlobalState::Yield if self.tap => self.panic()?,
+                            GlobalState::Yield => self.bank_a.handle_hold_down(&mut self.pads_tx, &mut self.tui_tx)?,
+                            GlobalState::Prime => {
+                                // enter learn
+                                let target = LearnTarget::FIRST;
+                                self.tui_tx.send(tui::Cmd::Learn(target.name()))?;
+                                self.state = GlobalState::Learn { target, since: std::time::Instant::now() };
+                            }
+                            _ => (),
                         }
-                        v if v == KeyCode::KitA as u8 => if let GlobalState::Yield = self.state {
-                            self.bank_a.handle_kit_down(&mut self.tui_tx)?;
+                        Some((KeyCode::Global, _)) => if self.tap {
+                            // toggle metronome
+                            self.click = !self.click;
+                            self.pads_tx.send(audio::Cmd::AssignClick(self.click))?;
+                        } else {
+                            self.state = GlobalState::Prime;
                         }
-                        v if v == KeyCode::HoldA as u8 => if let GlobalState::Yield = self.state {
-                            self.bank_a.handle_hold_down(&mut self.pads_tx, &mut self.tui_tx)?;
+                        Some((KeyCode::Tap, _)) => {
+                            self.tap = true;
+                            match self.state {
+                                GlobalState::Prime => self.toggle_bounce()?,
+                                _ => self.handle_tap()?,
+                            }
                         }
-                        v if v == KeyCode::Global as u8 => self.state = GlobalState::Prime,
-                        v if v == KeyCode::KitB as u8 => match &mut self.state {
-                            GlobalState::Yield => self.bank_b.handle_kit_down(&mut self.tui_tx)?,
+                        Some((KeyCode::KitB, _)) => match &mut self.state {
+                            GlobalState::Yield => self.bank_b.handle_kit_down(&mut self.pads_tx, &mut self.tui_tx)?,
+                            GlobalState::Learn { .. } | GlobalState::StoreKit { .. } => (),
                             GlobalState::Prime => {
                                 // open scene dir
-                                let mut paths = std::fs::read_dir("scenes")?
-                                    .flat_map(|v| Some(v.ok()?.path().into_boxed_path()))
-                                    .filter(|v| v.extension().unwrap() == "sd" || v.is_dir())
-                                    .collect::<Vec<_>>();
+                                let mut paths = list(audio::scenes_dir(), &["sd", "sdb", "kit"])?;
                                 paths.sort();
                                 self.tui_tx.send(tui::Cmd::LoadScene(to_fs_at!(paths, 0)))?;
                                 self.state = GlobalState::LoadScene {
@@ -483,30 +990,31 @@ impl InputHandler {
                                 };
                             }
                             GlobalState::LoadScene { paths, file_index } => {
-                                // decrement file index
-                                *file_index = (*file_index as isize - 1).rem_euclid(paths.len() as isize) as usize;
+                                // decrement file index, by page if shifted
+                                let step = if self.bank_b.shift { tui::FILE_COUNT } else { 1 };
+                                *file_index = (*file_index as isize - step as isize).rem_euclid(paths.len().max(1) as isize) as usize;
                                 self.tui_tx.send(tui::Cmd::LoadScene(to_fs_at!(paths, *file_index)))?;
                             }
                             GlobalState::LoadWav { paths, file_index } => {
-                                // decrement file index
-                                *file_index = (*file_index as isize - 1).rem_euclid(paths.len() as isize) as usize;
+                                // decrement file index, by page if shifted
+                                let step = if self.bank_b.shift { tui::FILE_COUNT } else { 1 };
+                                *file_index = (*file_index as isize - step as isize).rem_euclid(paths.len().max(1) as isize) as usize;
                                 self.tui_tx.send(tui::Cmd::LoadWav(to_fs_at!(paths, *file_index)))?;
                             }
-                            GlobalState::AssignOnset { paths, file_index, rd, onset_index, alt } => {
+                            GlobalState::AssignOnset { paths, file_index, wav, rd, onset_index, alt } => {
                                 // decrement onset index
                                 *onset_index = (*onset_index as isize - 1).rem_euclid(rd.onsets.len() as isize) as usize;
                                 let name = paths[*file_index].file_stem().unwrap().to_str().unwrap().to_string();
-                                self.tui_tx.send(tui::Cmd::AssignOnset { name, index: *onset_index, count: rd.onsets.len(), alt: *alt })?;
+                                let pos = rd.onsets[*onset_index] as f32 / wav.len as f32;
+                                self.tui_tx.send(tui::Cmd::AssignOnset { name, index: *onset_index, count: rd.onsets.len(), alt: *alt, pos })?;
                             }
                         }
-                        v if v == KeyCode::HoldB as u8 => match &mut self.state {
+                        Some((KeyCode::HoldB, _)) => match &mut self.state {
                             GlobalState::Yield => self.bank_b.handle_hold_down(&mut self.pads_tx, &mut self.tui_tx)?,
+                            GlobalState::Learn { .. } => (),
                             GlobalState::Prime => {
                                 // open onset dir
-                                let mut paths = std::fs::read_dir("onsets")?
-                                    .flat_map(|v| Some(v.ok()?.path().into_boxed_path()))
-                                    .filter(|v| v.extension().unwrap() == "wav" || v.is_dir())
-                                    .collect::<Vec<_>>();
+                                let mut paths = list(audio::onsets_dir(), &["wav", "flac", "ogg"])?;
                                 paths.sort();
                                 self.tui_tx.send(tui::Cmd::LoadWav(to_fs_at!(paths, 0)))?;
                                 self.state = GlobalState::LoadWav {
@@ -529,19 +1037,45 @@ impl InputHandler {
                                             // in subdirectory; include ".."
                                             vec![path.parent().unwrap().into()]
                                         };
-                                        paths.extend(std::fs::read_dir(path)?
-                                            .flat_map(|v| Some(v.ok()?.path().into_boxed_path()))
-                                            .filter(|v| v.extension().unwrap() == "sd" || v.is_dir())
-                                        );
+                                        paths.extend(list(path, &["sd", "sdb", "kit"])?);
                                         paths.sort();
                                         self.tui_tx.send(tui::Cmd::LoadScene(to_fs_at!(paths, 0)))?;
                                         self.state = GlobalState::LoadScene { paths, file_index: 0 };
+                                    } else if path.extension().is_some_and(|v| v == "kit") {
+                                        // pick slot for kit
+                                        let mut kit = match audio::pads::Kit::<PAD_COUNT>::load(path) {
+                                            Ok(v) => v,
+                                            Err(e) => {
+                                                self.tui_tx.send(tui::Cmd::Error(load_failure(path, &e)))?;
+                                                return Ok(());
+                                            }
+                                        };
+                                        let missing = kit.validate();
+                                        if !missing.is_empty() {
+                                            let names = missing.iter().map(|v| v.display().to_string()).collect::<Vec<_>>();
+                                            self.tui_tx.send(tui::Cmd::Error(format!("dropped missing: {}", names.join(", "))))?;
+                                        }
+                                        kit.preload();
+                                        let name = path.file_stem().unwrap().to_string_lossy();
+                                        self.tui_tx.send(tui::Cmd::Log(format!("store kit `{}` into pad's slot", name)))?;
+                                        self.state = GlobalState::StoreKit { paths: paths.clone(), file_index: *file_index, kit: Box::new(kit) };
                                     } else {
                                         // load scene
-                                        let sd_string = std::fs::read_to_string(&paths[*file_index])?;
-                                        let scene: audio::pads::Scene<PAD_COUNT> = serde_json::from_str(&sd_string)?;
-                                        self.tui_tx.send(tui::Cmd::AssignScene(Box::new(tui::Scene::from_audio(&scene))))?;
-                                        self.pads_tx.send(audio::Cmd::LoadScene(Box::new(scene)))?;
+                                        let path = &paths[*file_index];
+                                        let mut scene = match audio::pads::Scene::<PAD_COUNT>::load(path) {
+                                            Ok(v) => v,
+                                            Err(e) => {
+                                                self.tui_tx.send(tui::Cmd::Error(load_failure(path, &e)))?;
+                                                return Ok(());
+                                            }
+                                        };
+                                        self.scene_binary = path.extension().is_some_and(|v| v == "sdb");
+                                        let missing = scene.validate();
+                                        if !missing.is_empty() {
+                                            let names = missing.iter().map(|v| v.display().to_string()).collect::<Vec<_>>();
+                                            self.tui_tx.send(tui::Cmd::Error(format!("dropped missing: {}", names.join(", "))))?;
+                                        }
+                                        self.load_scene(scene)?;
                                     }
                                 }
                             }
@@ -560,22 +1094,45 @@ impl InputHandler {
                                             // in subdirectory; include ".."
                                             vec![path.parent().unwrap().into()]
                                         };
-                                        paths.extend(std::fs::read_dir(path)?
-                                            .flat_map(|v| Some(v.ok()?.path().into_boxed_path()))
-                                            .filter(|v| v.extension().unwrap() == "wav" || v.is_dir())
-                                        );
+                                        paths.extend(list(path, &["wav", "flac", "ogg"])?);
                                         paths.sort();
                                         self.tui_tx.send(tui::Cmd::LoadWav(to_fs_at!(paths, 0)))?;
                                         self.state = GlobalState::LoadWav { paths, file_index: 0 };
                                     } else {
                                         // enter onset selection
-                                        let rd_string = std::fs::read_to_string(path.with_extension("rd"))?;
-                                        let rd: audio::Rd = serde_json::from_str(&rd_string)?;
+                                        let rd = match audio::Rd::load(path) {
+                                            Ok(v) => v,
+                                            Err(e) => {
+                                                self.tui_tx.send(tui::Cmd::Error(load_failure(&path.with_extension("rd"), &e)))?;
+                                                return Ok(());
+                                            }
+                                        };
+                                        if rd.onsets.is_empty() {
+                                            self.tui_tx.send(tui::Cmd::Error(format!("no onsets in `{}`", path.with_extension("rd").display())))?;
+                                            return Ok(());
+                                        }
+                                        let mut wav = audio::Wav {
+                                            tempo: rd.tempo,
+                                            steps: rd.steps,
+                                            path: path.clone(),
+                                            len: audio::active::Header::open(path)?.len,
+                                            data: None,
+                                            stream: None,
+                                        };
+                                        wav.preload(&[])?;
+                                        self.wavs.insert(path.clone(), modified(path));
+                                        let peaks = self.peaks
+                                            .entry(path.clone())
+                                            .or_insert_with(|| audio::active::peaks(path, tui::PEAK_BINS).ok().map(Into::into))
+                                            .clone();
+                                        self.tui_tx.send(tui::Cmd::AssignPeaks(peaks))?;
                                         let name = path.file_stem().unwrap().to_str().unwrap().to_string();
-                                        self.tui_tx.send(tui::Cmd::AssignOnset { name, index: 0, count: rd.onsets.len(), alt: false })?;
+                                        let pos = rd.onsets.first().map_or(0., |v| *v as f32 / wav.len as f32);
+                                        self.tui_tx.send(tui::Cmd::AssignOnset { name, index: 0, count: rd.onsets.len(), alt: false, pos })?;
                                         self.state = GlobalState::AssignOnset {
                                             paths: paths.clone(),
                                             file_index: *file_index,
+                                            wav,
                                             rd,
                                             onset_index: 0,
                                             alt: false,
@@ -588,57 +1145,79 @@ impl InputHandler {
                                 self.tui_tx.send(tui::Cmd::LoadWav(to_fs_at!(paths, *file_index)))?;
                                 self.state = GlobalState::LoadWav { paths: paths.clone(), file_index: 0 };
                             }
+                            GlobalState::StoreKit { paths, file_index, .. } => {
+                                // drop kit, return to dir
+                                self.tui_tx.send(tui::Cmd::LoadScene(to_fs_at!(paths, *file_index)))?;
+                                self.state = GlobalState::LoadScene { paths: paths.clone(), file_index: *file_index };
+                            }
                         }
-                        v if v == KeyCode::ShiftB as u8 => self.bank_b.shift = true,
-                        v if v == KeyCode::ReverseB as u8 => match &mut self.state {
+                        Some((KeyCode::ShiftB, _)) => {
+                            self.bank_b.shift = true;
+                            match self.state {
+                                GlobalState::Yield => self.bank_b.handle_shift_down(&mut self.pads_tx, &mut self.tui_tx)?,
+                                GlobalState::Prime => {
+                                    // cycle velocity curve
+                                    self.vel_curve = self.vel_curve.next();
+                                }
+                                _ => (),
+                            }
+                        }
+                        Some((KeyCode::ReverseB, _)) => match &mut self.state {
+                            GlobalState::Yield if self.tap => self.save_kit(Bank::B)?,
                             GlobalState::Yield => self.bank_b.handle_reverse_down(&mut self.pads_tx, &mut self.tui_tx)?,
+                            GlobalState::Learn { .. } | GlobalState::StoreKit { .. } => (),
                             GlobalState::Prime => {
-                                // save active scene for both banks
-                                let mut index = 0;
-                                let mut file = std::fs::File::create_new(format!("scenes/scene{}.sd", index));
-                                while file.is_err() {
-                                    index += 1;
-                                    file = std::fs::File::create_new(format!("scenes/scene{}.sd", index));
+                                // save active scene for both banks, named in tui if attached
+                                let name = (0..)
+                                    .map(|v| format!("scene{}", v))
+                                    .find(|v| !self.scene_path(v).exists())
+                                    .unwrap();
+                                if self.naming {
+                                    self.tui_tx.send(tui::Cmd::NameScene(name))?;
+                                } else {
+                                    self.save_scene(&name, false)?;
                                 }
-                                self.pads_tx.send(audio::Cmd::SaveScene(file?))?;
-                                self.tui_tx.send(tui::Cmd::SaveScene(format!("scenes/scene{}.sd", index)))?;
                             }
                             GlobalState::LoadScene { paths, file_index } => {
-                                // increment file index
-                                *file_index = (*file_index as isize + 1).rem_euclid(paths.len() as isize) as usize;
+                                // increment file index, by page if shifted
+                                let step = if self.bank_b.shift { tui::FILE_COUNT } else { 1 };
+                                *file_index = (*file_index as isize + step as isize).rem_euclid(paths.len().max(1) as isize) as usize;
                                 self.tui_tx.send(tui::Cmd::LoadScene(to_fs_at!(paths, *file_index)))?;
                             }
                             GlobalState::LoadWav { paths, file_index } => {
-                                // increment file index
-                                *file_index = (*file_index as isize + 1).rem_euclid(paths.len() as isize) as usize;
+                                // increment file index, by page if shifted
+                                let step = if self.bank_b.shift { tui::FILE_COUNT } else { 1 };
+                                *file_index = (*file_index as isize + step as isize).rem_euclid(paths.len().max(1) as isize) as usize;
                                 self.tui_tx.send(tui::Cmd::LoadWav(to_fs_at!(paths, *file_index)))?;
                             }
-                            GlobalState::AssignOnset { paths, file_index, rd, onset_index, alt } => {
+                            GlobalState::AssignOnset { paths, file_index, wav, rd, onset_index, alt } => {
                                 // increment onset index
                                 *onset_index = (*onset_index as isize + 1).rem_euclid(rd.onsets.len() as isize) as usize;
                                 let name = paths[*file_index].file_stem().unwrap().to_str().unwrap().to_string();
-                                self.tui_tx.send(tui::Cmd::AssignOnset { name, index: *onset_index, count: rd.onsets.len(), alt: *alt })?;
+                                let pos = rd.onsets[*onset_index] as f32 / wav.len as f32;
+                                self.tui_tx.send(tui::Cmd::AssignOnset { name, index: *onset_index, count: rd.onsets.len(), alt: *alt, pos })?;
                             }
                         }
-                        v if (KeyCode::BankBOffset as u8..KeyCode::BankBOffset as u8 + PAD_COUNT as u8).contains(&v) => {
-                            let index = v - KeyCode::BankBOffset as u8;
+                        Some((KeyCode::BankBOffset, v)) => {
+                            let index = v;
                             self.bank_b.downs.push(index);
+                            self.bank_b.vel = self.vel_curve.gain(vel.as_int());
+                            self.focus = Bank::B;
                             self.tui_tx.send(tui_bank_cmd!(Bank::B, Pad, index, true))?;
                             match &self.state {
+                                GlobalState::Yield if self.tap => self.recall(PAD_COUNT as u8 + index)?,
                                 GlobalState::Yield => {
                                     self.bank_b.handle_pad_down(&mut self.pads_tx, &mut self.tui_tx)?;
                                 }
-                                GlobalState::AssignOnset { paths, file_index, rd, onset_index, alt } => {
+                                GlobalState::StoreKit { kit, .. } => {
+                                    let kit = kit.clone();
+                                    self.store_kit(Bank::B, index, kit)?;
+                                }
+                                GlobalState::Prime => self.swap_banks()?,
+                                GlobalState::AssignOnset { wav, rd, onset_index, alt, .. } => {
                                     // assign onset to pad
-                                    let len = std::fs::metadata(&paths[*file_index])?.len() - 44;
                                     let start = rd.onsets[*onset_index];
-                                    let wav = audio::Wav {
-                                        tempo: rd.tempo,
-                                        steps: rd.steps,
-                                        path: paths[*file_index].clone(),
-                                        len,
-                                    };
-                                    let onset = audio::Onset { wav, start };
+                                    let onset = audio::Onset { wav: wav.clone(), start };
                                     self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignOnset, index, *alt, Box::new(onset)))?;
                                 }
                                 _ => (),
@@ -646,43 +1225,226 @@ impl InputHandler {
                         }
                         _ => (),
                     }
-                    MidiMessage::Controller { controller, value } => match controller.as_int() {
-                        v if v == CtrlCode::GainA as u8 => {
+                    MidiMessage::Controller { controller, .. } if controller == audio::ALL_NOTES_OFF => self.panic()?,
+                    MidiMessage::Controller { controller, value } if self.controls.fine(controller.as_int()).is_some() => {
+                        // refine latched msb; lsb without msb ignored
+                        let code = self.controls.fine(controller.as_int()).unwrap();
+                        if let Some((coarse, paired)) = self.msbs.get_mut(&code) {
+                            *paired = true;
+                            let v = combine(*coarse, value.as_int()) as f32 / 16383.;
+                            self.assign_fine(code, v)?;
+                        }
+                    }
+                    MidiMessage::Controller { controller, value } => match self.controls.ctrl(controller.as_int()) {
+                        Some(CtrlCode::GainA) => {
                             self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignGain, value.as_int() as f32 / 127. * 2.))?;
                         }
-                        v if v == CtrlCode::SpeedA as u8 => {
-                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignSpeed, value.as_int() as f32 / 127. * 2.))?;
+                        Some(code @ (CtrlCode::SpeedA | CtrlCode::SpeedB | CtrlCode::WidthA | CtrlCode::WidthB)) => {
+                            // 7-bit until an lsb follows, then latched for lsb
+                            let paired = self.msbs.get(&code).is_some_and(|v| v.1);
+                            self.msbs.insert(code, (value.as_int(), paired));
+                            if !paired {
+                                self.assign_fine(code, value.as_int() as f32 / 127.)?;
+                            }
                         }
-                        v if v == CtrlCode::DriftA as u8 => {
+                        Some(CtrlCode::DriftA) => {
                             self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignDrift, value.as_int() as f32 / 127.))?;
                             self.tui_tx.send(tui_bank_cmd!(Bank::A, AssignDrift, value.as_int()))?;
                         }
-                        v if v == CtrlCode::BiasA as u8 => {
+                        Some(CtrlCode::PhraseDriftA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignPhraseDrift, value.as_int() as f32 / 127.))?;
+                            self.tui_tx.send(tui_bank_cmd!(Bank::A, AssignPhraseDrift, value.as_int()))?;
+                        }
+                        Some(CtrlCode::ProbA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignProb, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::BiasA) => {
                             self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignBias, value.as_int() as f32 / 127.))?;
                             self.tui_tx.send(tui_bank_cmd!(Bank::A, AssignBias, value.as_int()))?;
                         }
-                        v if v == CtrlCode::WidthA as u8 => {
-                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignWidth, value.as_int() as f32 / 127.))?;
+                        Some(CtrlCode::PadGainA) => if let Some(&index) = self.bank_a.downs.first() {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignPadGain, index, value.as_int() as f32 / 127. * 2.))?;
                         }
-                        v if v == CtrlCode::GainB as u8 => {
-                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignGain, value.as_int() as f32 / 127. * 2.))?;
+                        Some(CtrlCode::CutoffA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignCutoff, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::ResonanceA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignResonance, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::FeedbackA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignFeedback, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::WetA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignWet, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::DelayTimeA) => {
+                            let beats = audio::DELAY_BEATS[value.as_int() as usize * audio::DELAY_BEATS.len() / 128];
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignDelayBeats, beats))?;
+                        }
+                        Some(CtrlCode::CrushDepthA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignCrushDepth, 16 - (value.as_int() as u16 * 15 / 127) as u8))?;
+                        }
+                        Some(CtrlCode::CrushRateA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignCrushRate, 1 + (value.as_int() as u16 * 31 / 127) as u8))?;
+                        }
+                        Some(CtrlCode::TremoloDepthA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignTremoloDepth, value.as_int() as f32 / 127.))?;
                         }
-                        v if v == CtrlCode::SpeedB as u8 => {
-                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignSpeed, value.as_int() as f32 / 127. * 2.))?;
+                        Some(CtrlCode::TremoloPeriodA) => {
+                            let period = audio::TREMOLO_PERIODS[value.as_int() as usize * audio::TREMOLO_PERIODS.len() / 128];
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignTremoloPeriod, period))?;
                         }
-                        v if v == CtrlCode::DriftB as u8 => {
+                        Some(CtrlCode::TremoloShapeA) => {
+                            let shape = match value.as_int() / 43 {
+                                0 => audio::fx::Shape::Sine,
+                                1 => audio::fx::Shape::Triangle,
+                                _ => audio::fx::Shape::Square,
+                            };
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignTremoloShape, shape))?;
+                        }
+                        Some(CtrlCode::PanDepthA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignPanDepth, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::PanRateA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignPanRate, pan_rate(value.as_int())))?;
+                        }
+                        Some(CtrlCode::GainB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignGain, value.as_int() as f32 / 127. * 2.))?;
+                        }
+                        Some(CtrlCode::DriftB) => {
                             self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignDrift, value.as_int() as f32 / 127.))?;
                             self.tui_tx.send(tui_bank_cmd!(Bank::B, AssignDrift, value.as_int()))?;
                         }
-                        v if v == CtrlCode::BiasB as u8 => {
+                        Some(CtrlCode::PhraseDriftB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignPhraseDrift, value.as_int() as f32 / 127.))?;
+                            self.tui_tx.send(tui_bank_cmd!(Bank::B, AssignPhraseDrift, value.as_int()))?;
+                        }
+                        Some(CtrlCode::ProbB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignProb, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::BiasB) => {
                             self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignBias, value.as_int() as f32 / 127.))?;
                             self.tui_tx.send(tui_bank_cmd!(Bank::B, AssignBias, value.as_int()))?;
                         }
-                        v if v == CtrlCode::WidthB as u8 => {
-                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignWidth, value.as_int() as f32 / 127.))?;
+                        Some(CtrlCode::PadGainB) => if let Some(&index) = self.bank_b.downs.first() {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignPadGain, index, value.as_int() as f32 / 127. * 2.))?;
+                        }
+                        Some(CtrlCode::CutoffB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignCutoff, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::ResonanceB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignResonance, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::FeedbackB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignFeedback, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::WetB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignWet, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::DelayTimeB) => {
+                            let beats = audio::DELAY_BEATS[value.as_int() as usize * audio::DELAY_BEATS.len() / 128];
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignDelayBeats, beats))?;
+                        }
+                        Some(CtrlCode::CrushDepthB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignCrushDepth, 16 - (value.as_int() as u16 * 15 / 127) as u8))?;
+                        }
+                        Some(CtrlCode::CrushRateB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignCrushRate, 1 + (value.as_int() as u16 * 31 / 127) as u8))?;
+                        }
+                        Some(CtrlCode::TremoloDepthB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignTremoloDepth, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::TremoloPeriodB) => {
+                            let period = audio::TREMOLO_PERIODS[value.as_int() as usize * audio::TREMOLO_PERIODS.len() / 128];
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignTremoloPeriod, period))?;
+                        }
+                        Some(CtrlCode::TremoloShapeB) => {
+                            let shape = match value.as_int() / 43 {
+                                0 => audio::fx::Shape::Sine,
+                                1 => audio::fx::Shape::Triangle,
+                                _ => audio::fx::Shape::Square,
+                            };
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignTremoloShape, shape))?;
+                        }
+                        Some(CtrlCode::PanDepthB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignPanDepth, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::PanRateB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignPanRate, pan_rate(value.as_int())))?;
+                        }
+                        Some(CtrlCode::NudgeA) => if let Some(&index) = self.bank_a.downs.first().filter(|_| self.bank_a.reverse) {
+                            // relative encoder, centered on 64
+                            let delta = (value.as_int() as i64 - 64) * audio::NUDGE_WORDS;
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, NudgeOnset, index, self.bank_a.shift, delta))?;
+                        }
+                        Some(CtrlCode::NudgeB) => if let Some(&index) = self.bank_b.downs.first().filter(|_| self.bank_b.reverse) {
+                            // relative encoder, centered on 64
+                            let delta = (value.as_int() as i64 - 64) * audio::NUDGE_WORDS;
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, NudgeOnset, index, self.bank_b.shift, delta))?;
+                        }
+                        Some(CtrlCode::LoopDivA) => {
+                            self.bank_a.loop_div = audio::LOOP_DIVS[value.as_int() as usize * audio::LOOP_DIVS.len() / 128];
+                        }
+                        Some(CtrlCode::LoopDivB) => {
+                            self.bank_b.loop_div = audio::LOOP_DIVS[value.as_int() as usize * audio::LOOP_DIVS.len() / 128];
+                        }
+                        Some(CtrlCode::PreRollA) => {
+                            self.bank_a.preroll = (value.as_int() as usize * (audio::PREROLL_MAX as usize + 1) / 128) as u8;
+                        }
+                        Some(CtrlCode::PreRollB) => {
+                            self.bank_b.preroll = (value.as_int() as usize * (audio::PREROLL_MAX as usize + 1) / 128) as u8;
+                        }
+                        Some(CtrlCode::HumanizeA) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::A, AssignHumanize, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::HumanizeB) => {
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignHumanize, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::DuckDepth) => {
+                            // bank a ducks bank b
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignDuckDepth, value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::DuckRelease) => {
+                            let release = audio::DUCK_RELEASE_MAX * value.as_int() as f32 / 127.;
+                            self.pads_tx.send(audio_bank_cmd!(Bank::B, AssignDuckRelease, release))?;
+                        }
+                        Some(CtrlCode::ClickLevel) => {
+                            self.pads_tx.send(audio::Cmd::AssignClickLevel(value.as_int() as f32 / 127.))?;
+                        }
+                        Some(CtrlCode::ClickBar) => {
+                            // 1 to CLICK_BAR_MAX steps
+                            let bar = 1 + (audio::CLICK_BAR_MAX - 1) * value.as_int() as u16 / 127;
+                            self.pads_tx.send(audio::Cmd::AssignClickBar(bar))?;
+                        }
+                        Some(CtrlCode::Swing) => {
+                            // affect both banks; straight to SWING_MAX
+                            let swing = 0.5 + (audio::SWING_MAX - 0.5) * value.as_int() as f32 / 127.;
+                            self.pads_tx.send(audio::Cmd::AssignSwing(swing))?;
+                        }
+                        Some(CtrlCode::GrainLen) => {
+                            // affect both banks; exponential between bounds
+                            let ratio = audio::GRAIN_LEN_MAX as f32 / audio::GRAIN_LEN_MIN as f32;
+                            let len = audio::GRAIN_LEN_MIN as f32 * ratio.powf(value.as_int() as f32 / 127.);
+                            self.pads_tx.send(audio::Cmd::AssignGrainLen(len as usize))?;
                         }
                         _ => (),
                     }
+                    MidiMessage::ProgramChange { program } if matches!(self.state, GlobalState::Prime) => {
+                        self.recall(program.as_int())?;
+                    }
+                    MidiMessage::ProgramChange { program } => {
+                        // load kit into last touched bank
+                        let index = program.as_int().min(PAD_COUNT as u8 - 1);
+                        match self.focus {
+                            Bank::A => self.bank_a.handle_program(index, &mut self.pads_tx, &mut self.tui_tx)?,
+                            Bank::B => self.bank_b.handle_program(index, &mut self.pads_tx, &mut self.tui_tx)?,
+                        }
+                    }
+                    MidiMessage::ChannelAftertouch { vel } => {
+                        // modulate last touched bank
+                        let touch = self.controls.file.touch;
+                        self.pads_tx.send(audio_bank_cmd!(self.focus, AssignTouch, touch, vel.as_int() as f32 / 127.))?;
+                    }
                     MidiMessage::PitchBend { bend } => {
                         // affect both banks
                         self.pads_tx.send(audio::Cmd::OffsetSpeed(bend.as_f32() + 1.))?;
@@ -690,24 +1452,74 @@ impl InputHandler {
                     _ => (),
                 }
             }
+            // ableton link is clock source
+            LiveEvent::Realtime(_) | LiveEvent::Common(midly::live::SystemCommon::SongPosition(_)) if self.link == Some(true) => (),
             LiveEvent::Realtime(midly::live::SystemRealtime::TimingClock) => {
                 // affect both banks
                 if self.clock == 0 {
                     let now = std::time::Instant::now();
-                    if let Some(delta) = self.last_step {
-                        let ioi = now.duration_since(delta);
-                        let tempo = 60. / ioi.as_secs_f32() / audio::STEP_DIV as f32;
-                        self.pads_tx.send(audio::Cmd::AssignTempo(tempo))?;
+                    let ioi = self.last_step.map(|v| now.duration_since(v).as_secs_f32());
+                    // steps faster than TEMPO_MAX are duplicated clocks
+                    let min = 60. / audio::TEMPO_MAX / audio::STEP_DIV as f32;
+                    match ioi {
+                        Some(ioi) if ioi < min => {
+                            self.tui_tx.send(tui::Cmd::Log(format!("ignored clock step after {:.1}ms", ioi * 1000.)))?;
+                        }
+                        Some(ioi) => {
+                            if let Some(tempo) = window_tempo(&mut self.iois, ioi) {
+                                self.pads_tx.send(audio::Cmd::AssignTempo(tempo))?;
+                                self.tui_tx.send(tui::Cmd::Tempo(tempo))?;
+                            }
+                        }
+                        None => (),
                     }
                     self.last_step = Some(now);
-                    self.pads_tx.send(audio::Cmd::Clock)?;
-                    self.tui_tx.send(tui::Cmd::Clock)?;
+                    if let Some(pll) = self.pll.as_mut() {
+                        // pll_tick steps on smoothed schedule
+                        pll.reference(pll.now());
+                    } else {
+                        self.pads_tx.send(audio::Cmd::Clock)?;
+                        self.tui_tx.send(tui::Cmd::Clock)?;
+                    }
+                }
+                self.clock = (self.clock + 1) % (self.ppq / audio::STEP_DIV);
+            }
+            LiveEvent::Realtime(midly::live::SystemRealtime::Start) => {
+                // affect both banks; restart from first step
+                self.last_step = None;
+                self.iois.clear();
+                if let Some(pll) = self.pll.as_mut() {
+                    pll.reset();
+                }
+                self.clock = 0;
+                self.pads_tx.send(audio::Cmd::Stop)?;
+                self.tui_tx.send(tui::Cmd::Stop)?;
+            }
+            LiveEvent::Realtime(midly::live::SystemRealtime::Continue) => {
+                // resume from current step; don't derive tempo across pause
+                self.last_step = None;
+                self.iois.clear();
+                if let Some(pll) = self.pll.as_mut() {
+                    pll.reset();
                 }
-                self.clock = (self.clock + 1) % (audio::PPQ / audio::STEP_DIV);
+            }
+            LiveEvent::Common(midly::live::SystemCommon::SongPosition(position)) => {
+                // affect both banks; position counts sixteenths, one per step
+                self.last_step = None;
+                self.iois.clear();
+                if let Some(pll) = self.pll.as_mut() {
+                    pll.reset();
+                }
+                self.clock = 0;
+                self.pads_tx.send(audio::Cmd::Seek(position.as_int()))?;
             }
             LiveEvent::Realtime(midly::live::SystemRealtime::Stop) => {
                 // affect both banks
                 self.last_step = None;
+                self.iois.clear();
+                if let Some(pll) = self.pll.as_mut() {
+                    pll.reset();
+                }
                 self.clock = 0;
                 self.pads_tx.send(audio::Cmd::Stop)?;
                 self.tui_tx.send(tui::Cmd::Stop)?;
@@ -716,4 +1528,1030 @@ impl InputHandler {
         }
         Ok(())
     }
+
+    /// apply speed or width ctrl at 0..=1, tui showing nearest 7-bit value
+    fn assign_fine(&mut self, code: CtrlCode, v: f32) -> Result<()> {
+        let (bank, speed) = match code {
+            CtrlCode::SpeedA => (Bank::A, true),
+            CtrlCode::SpeedB => (Bank::B, true),
+            CtrlCode::WidthA => (Bank::A, false),
+            CtrlCode::WidthB => (Bank::B, false),
+            _ => return Ok(()),
+        };
+        let coarse = (v * 127.).round() as u8;
+        if speed {
+            self.pads_tx.send(audio_bank_cmd!(bank, AssignSpeed, v * 2.))?;
+            self.tui_tx.send(tui_bank_cmd!(bank, AssignSpeed, coarse))?;
+        } else {
+            self.pads_tx.send(audio_bank_cmd!(bank, AssignWidth, v))?;
+            self.tui_tx.send(tui_bank_cmd!(bank, AssignWidth, coarse))?;
+        }
+        Ok(())
+    }
+
+    /// release stuck pads: clear held pads, stutter and hold in both banks, silencing playback
+    pub fn panic(&mut self) -> Result<()> {
+        for bank in [&mut self.bank_a, &mut self.bank_b] {
+            for index in bank.downs.drain(..) {
+                self.tui_tx.send(tui_bank_cmd!(bank.bank, Pad, index, false))?;
+            }
+            bank.release_stutter(&mut self.pads_tx)?;
+            if bank.hold {
+                bank.hold = false;
+                self.tui_tx.send(tui_bank_cmd!(bank.bank, Hold, false))?;
+            }
+        }
+        self.pads_tx.send(audio::Cmd::Panic)?;
+        self.tui_tx.send(tui::Cmd::Log("all voices off".to_string()))?;
+        Ok(())
+    }
+
+    /// pick clock source: link if true, midi if false; none without link listener
+    pub fn assign_link(&mut self, link: Option<bool>) -> Result<()> {
+        let Some(on) = link else {
+            self.tui_tx.send(tui::Cmd::Error("link off; set link = true in config.toml".to_string()))?;
+            return Ok(());
+        };
+        self.link = link;
+        // restart step and tempo derivation from new source
+        self.last_step = None;
+        self.iois.clear();
+        self.clock = 0;
+        if let Some(pll) = self.pll.as_mut() {
+            pll.reset();
+        }
+        if on && self.master {
+            self.master = false;
+            self.pads_tx.send(audio::Cmd::AssignMaster(false))?;
+        }
+        self.tui_tx.send(tui::Cmd::Log(format!("clock from {}", if on { "ableton link" } else { "midi" })))?;
+        Ok(())
+    }
+
+    /// advance both banks `steps` steps at link session tempo, first restarting from first step
+    /// if `start`; false if link not clock source
+    pub fn link_clock(&mut self, tempo: f32, start: bool, steps: u32) -> Result<bool> {
+        if self.link != Some(true) {
+            return Ok(false);
+        }
+        if start {
+            self.pads_tx.send(audio::Cmd::Stop)?;
+            self.tui_tx.send(tui::Cmd::Stop)?;
+        }
+        if let Some(tempo) = audio::clamp_tempo(tempo) {
+            self.pads_tx.send(audio::Cmd::AssignTempo(tempo))?;
+            self.tui_tx.send(tui::Cmd::Tempo(tempo))?;
+        }
+        for _ in 0..steps {
+            self.pads_tx.send(audio::Cmd::Clock)?;
+            self.tui_tx.send(tui::Cmd::Clock)?;
+        }
+        Ok(true)
+    }
+
+    /// step on phase-locked schedule of midi clock rather than each clock step
+    pub fn assign_pll(&mut self, on: bool) {
+        self.pll = on.then(pll::Pll::new);
+    }
+
+    /// send steps due on pll schedule
+    pub fn pll_tick(&mut self) -> Result<()> {
+        let Some(pll) = self.pll.as_mut() else {
+            return Ok(());
+        };
+        for _ in 0..pll.tick(pll.now()) {
+            self.pads_tx.send(audio::Cmd::Clock)?;
+            self.tui_tx.send(tui::Cmd::Clock)?;
+        }
+        Ok(())
+    }
+
+    /// expect `ppq` midi clocks per quarter, advancing a step every ppq / STEP_DIV
+    pub fn assign_ppq(&mut self, ppq: u8) -> Result<()> {
+        if ppq == 0 || !ppq.is_multiple_of(audio::STEP_DIV) {
+            self.tui_tx.send(tui::Cmd::Error(format!("ignoring ppq {}; not a multiple of {}", ppq, audio::STEP_DIV)))?;
+            return Ok(());
+        }
+        self.ppq = ppq;
+        self.clock = 0;
+        Ok(())
+    }
+
+    /// apply startup speed and width to both banks, and internal clock tempo
+    pub fn assign_defaults(&mut self, speed: Option<u8>, width: Option<u8>, tempo: Option<f32>) -> Result<()> {
+        for bank in [Bank::A, Bank::B] {
+            if let Some(v) = speed.map(|v| v.min(127)) {
+                self.pads_tx.send(audio_bank_cmd!(bank, AssignSpeed, v as f32 / 127. * 2.))?;
+                self.tui_tx.send(tui_bank_cmd!(bank, AssignSpeed, v))?;
+            }
+            if let Some(v) = width.map(|v| v.min(127)) {
+                self.pads_tx.send(audio_bank_cmd!(bank, AssignWidth, v as f32 / 127.))?;
+                self.tui_tx.send(tui_bank_cmd!(bank, AssignWidth, v))?;
+            }
+        }
+        if let Some(v) = tempo {
+            let Some(clamped) = audio::clamp_tempo(v) else {
+                self.tui_tx.send(tui::Cmd::Error(format!("ignoring tempo {}", v)))?;
+                return Ok(());
+            };
+            if clamped != v {
+                self.tui_tx.send(tui::Cmd::Error(format!("clamped tempo {} to {}", v, clamped)))?;
+            }
+            self.pads_tx.send(audio::Cmd::AssignTempo(clamped))?;
+            self.tui_tx.send(tui::Cmd::Tempo(clamped))?;
+        }
+        Ok(())
+    }
+
+    /// save active scene as json to file, signalling done once written
+    pub fn autosave(&mut self, file: std::fs::File, done: Sender<()>) -> Result<()> {
+        self.pads_tx.send(audio::Cmd::Autosave(file, done))
+    }
+
+    /// play note mapped to key code, as if from controller
+    fn push_code(&mut self, code: KeyCode, offset: u8, down: bool) -> Result<()> {
+        let Some(note) = self.controls.keys.iter().position(|v| *v == Some((code, offset))) else {
+            return Ok(());
+        };
+        if down {
+            self.push(&[0x90, note as u8, 127])
+        } else {
+            self.push(&[0x80, note as u8, 0])
+        }
+    }
+
+    /// install phrase programmed in tui step grid to bank's pad
+    pub fn assign_phrase(&mut self, bank: Bank, index: u8, phrase: audio::Phrase) -> Result<()> {
+        self.pads_tx.send(audio_bank_cmd!(bank, AssignPhrase, index, Box::new(phrase)))?;
+        self.tui_tx.send(tui_bank_cmd!(bank, AssignPhrase, index))?;
+        Ok(())
+    }
+
+    /// press or release key by name, case and underscores ignored, as if its mapped note; false if unknown
+    pub fn push_key(&mut self, name: &str, down: bool) -> Result<bool> {
+        let Some(code) = KeyCode::ALL.into_iter().find(|v| same_name(v, name)) else {
+            return Ok(false);
+        };
+        self.push_code(code, 0, down)?;
+        Ok(true)
+    }
+
+    /// move ctrl by name, case and underscores ignored, to value in [0, 1] as if its mapped cc; false if unknown
+    pub fn push_ctrl(&mut self, name: &str, value: f32) -> Result<bool> {
+        let Some(code) = CtrlCode::ALL.into_iter().find(|v| same_name(v, name)) else {
+            return Ok(false);
+        };
+        if let Some(number) = self.controls.ctrls.iter().position(|v| *v == Some(code)) {
+            let number = number as u8;
+            if let Some(lsb) = self.controls.lsb(code) {
+                // fine ctrls as msb then lsb
+                let fine = (value.clamp(0., 1.) * 16383.).round() as u16;
+                self.push(&[0xb0, number, (fine >> 7) as u8])?;
+                self.push(&[0xb0, lsb, (fine & 0x7f) as u8])?;
+            } else {
+                self.push(&[0xb0, number, (value.clamp(0., 1.) * 127.).round() as u8])?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// press or release pad from tui
+    pub fn push_pad(&mut self, bank: Bank, index: u8, down: bool) -> Result<()> {
+        match bank {
+            Bank::A => self.push_code(KeyCode::BankAOffset, PAD_COUNT as u8 - 1 - index, down),
+            Bank::B => self.push_code(KeyCode::BankBOffset, index, down),
+        }
+    }
+
+    /// step fs toward row from tui, entering middle row
+    pub fn push_file(&mut self, row: usize) -> Result<()> {
+        if !matches!(self.state, GlobalState::LoadScene { .. } | GlobalState::LoadWav { .. }) {
+            return Ok(());
+        }
+        let delta = row as isize - tui::FILE_COUNT as isize / 2;
+        let code = match delta.signum() {
+            -1 => KeyCode::KitB,
+            1 => KeyCode::ReverseB,
+            _ => KeyCode::HoldB,
+        };
+        for _ in 0..delta.unsigned_abs().max(1) {
+            self.push_code(code, 0, true)?;
+            self.push_code(code, 0, false)?;
+        }
+        Ok(())
+    }
+
+    /// track kits' wavs for rescan
+    fn track<'a>(&mut self, kits: impl IntoIterator<Item = &'a audio::pads::Kit<PAD_COUNT>>) {
+        let paths = kits.into_iter()
+            .flat_map(|v| v.inner.iter())
+            .flat_map(|v| v.onsets.iter().flatten())
+            .map(|v| &v.wav.path);
+        for path in paths {
+            self.wavs.entry(path.clone()).or_insert_with(|| modified(path));
+        }
+    }
+
+    /// preload scene, tracking its wavs for rescan, then load it
+    pub fn load_scene(&mut self, mut scene: audio::pads::Scene<PAD_COUNT>) -> Result<()> {
+        scene.preload();
+        self.track(scene.kit_a.iter().chain(scene.kit_b.iter()));
+        self.slot = None;
+        self.tui_tx.send(tui::Cmd::AssignSlot(None))?;
+        self.tui_tx.send(tui::Cmd::AssignScene(Box::new(tui::Scene::from_audio(&scene))))?;
+        self.pads_tx.send(audio::Cmd::LoadScene(Box::new(scene)))?;
+        Ok(())
+    }
+
+    /// preload scenes into slots, up to SCENE_SLOTS
+    pub fn assign_slots(&mut self, slots: Vec<Option<audio::pads::Scene<PAD_COUNT>>>) {
+        let mut slots = slots.into_iter().take(audio::SCENE_SLOTS).collect::<Vec<_>>();
+        for scene in slots.iter_mut().flatten() {
+            scene.preload();
+            self.track(scene.kit_a.iter().chain(scene.kit_b.iter()));
+        }
+        self.slots = slots;
+    }
+
+    pub fn assign_naming(&mut self, naming: bool) {
+        self.naming = naming;
+    }
+
+    /// scene file of name in scenes dir, in format of last loaded scene
+    fn scene_path(&self, name: &str) -> PathBuf {
+        let ext = if self.scene_binary { "sdb" } else { "sd" };
+        audio::scenes_dir().join(format!("{}.{}", name, ext))
+    }
+
+    /// save active scene under name verbatim; false without saving if taken and not overwriting
+    pub fn save_scene(&mut self, name: &str, overwrite: bool) -> Result<bool> {
+        std::fs::create_dir_all(audio::scenes_dir())?;
+        let path = self.scene_path(name);
+        let file = if overwrite {
+            std::fs::File::create(&path)
+        } else {
+            std::fs::File::create_new(&path)
+        };
+        let file = match file {
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(false),
+            v => v?,
+        };
+        self.pads_tx.send(audio::Cmd::SaveScene(file, self.scene_binary))?;
+        self.tui_tx.send(tui::Cmd::SaveScene(path.display().to_string()))?;
+        Ok(true)
+    }
+
+    /// save bank's loaded kit to new .kit in scenes dir
+    fn save_kit(&mut self, bank: Bank) -> Result<()> {
+        std::fs::create_dir_all(audio::scenes_dir())?;
+        let mut index = 0;
+        let mut file = std::fs::File::create_new(format!("{}/kit{}.kit", audio::scenes_dir().display(), index));
+        while file.is_err() {
+            index += 1;
+            file = std::fs::File::create_new(format!("{}/kit{}.kit", audio::scenes_dir().display(), index));
+        }
+        self.pads_tx.send(audio::Cmd::SaveKit(bank, file?))?;
+        self.tui_tx.send(tui::Cmd::Log(format!("saved kit to `{}/kit{}.kit`!", audio::scenes_dir().display(), index)))?;
+        Ok(())
+    }
+
+    /// store preloaded kit into bank's slot, leaving loaded kit
+    fn store_kit(&mut self, bank: Bank, index: u8, kit: Box<audio::pads::Kit<PAD_COUNT>>) -> Result<()> {
+        self.track([&*kit]);
+        self.tui_tx.send(tui::Cmd::StoreKit(bank, index, tui::Scene::kit_from_audio(&kit)))?;
+        self.pads_tx.send(audio::Cmd::StoreKit(bank, index, kit))?;
+        Ok(())
+    }
+
+    /// load slot's preloaded scene; bank parameters and actives carry over
+    fn recall(&mut self, index: u8) -> Result<()> {
+        let Some(scene) = self.slots.get(index as usize).and_then(Clone::clone) else {
+            self.tui_tx.send(tui::Cmd::Log(format!("no scene in slot {}", index + 1)))?;
+            return Ok(());
+        };
+        self.slot = Some(index);
+        self.tui_tx.send(tui::Cmd::AssignSlot(self.slot))?;
+        self.tui_tx.send(tui::Cmd::AssignScene(Box::new(tui::Scene::from_audio(&scene))))?;
+        self.pads_tx.send(audio::Cmd::LoadScene(Box::new(scene)))?;
+        Ok(())
+    }
+
+    /// reload wavs modified on disk since loaded, with their .rd tempo and steps
+    pub fn rescan(&mut self) -> Result<()> {
+        let mut reloaded = Vec::new();
+        for (path, time) in self.wavs.iter_mut() {
+            let now = modified(path);
+            if now == *time {
+                continue;
+            }
+            *time = now;
+            self.peaks.remove(path);
+            let reload = || -> Result<audio::Wav> {
+                let rd = audio::Rd::load(path).map_err(|e| color_eyre::Report::msg(load_failure(&path.with_extension("rd"), &e)))?;
+                let mut wav = audio::Wav {
+                    tempo: rd.tempo,
+                    steps: rd.steps,
+                    path: path.clone(),
+                    len: audio::active::Header::open(path)?.len,
+                    data: None,
+                    stream: None,
+                };
+                wav.preload(&[])?;
+                Ok(wav)
+            };
+            match reload() {
+                Ok(wav) => {
+                    for kit in self.slots.iter_mut().flatten().flat_map(|v| v.kit_a.iter_mut().chain(v.kit_b.iter_mut())) {
+                        kit.reload(&wav);
+                    }
+                    self.pads_tx.send(audio::Cmd::ReloadWav(Box::new(wav)))?;
+                    reloaded.push(path.display().to_string());
+                }
+                Err(e) => self.tui_tx.send(tui::Cmd::Error(format!("{}: {}", path.display(), e)))?,
+            }
+        }
+        if !reloaded.is_empty() {
+            self.tui_tx.send(tui::Cmd::Log(format!("reloaded: {}", reloaded.join(", "))))?;
+        }
+        Ok(())
+    }
+
+    /// jump browser to first file stem starting with query, else containing it
+    pub fn search(&mut self, query: &str) -> Result<()> {
+        let query = query.to_lowercase();
+        let find = |paths: &[Box<Path>]| {
+            let stems = paths.iter()
+                .map(|v| v.file_stem().map(|v| v.to_string_lossy().to_lowercase()).unwrap_or_default())
+                .collect::<Vec<_>>();
+            stems.iter().position(|v| v.starts_with(&query))
+                .or_else(|| stems.iter().position(|v| v.contains(&query)))
+        };
+        match &mut self.state {
+            GlobalState::LoadScene { paths, file_index } => if let Some(index) = find(paths) {
+                *file_index = index;
+                self.tui_tx.send(tui::Cmd::LoadScene(to_fs_at!(paths, *file_index)))?;
+            }
+            GlobalState::LoadWav { paths, file_index } => if let Some(index) = find(paths) {
+                *file_index = index;
+                self.tui_tx.send(tui::Cmd::LoadWav(to_fs_at!(paths, *file_index)))?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// exchange bank states, leaving held keys and stored kit slots in place
+    fn swap_banks(&mut self) -> Result<()> {
+        let (a, b) = (&mut self.bank_a, &mut self.bank_b);
+        std::mem::swap(&mut a.hold, &mut b.hold);
+        std::mem::swap(&mut a.phrase_reverse, &mut b.phrase_reverse);
+        std::mem::swap(&mut a.pool_order, &mut b.pool_order);
+        std::mem::swap(&mut a.pad_mode, &mut b.pad_mode);
+        std::mem::swap(&mut a.scale, &mut b.scale);
+        std::mem::swap(&mut a.stutter, &mut b.stutter);
+        std::mem::swap(&mut a.frozen, &mut b.frozen);
+        std::mem::swap(&mut a.sections, &mut b.sections);
+        std::mem::swap(&mut a.song, &mut b.song);
+        std::mem::swap(&mut a.pool_pushed, &mut b.pool_pushed);
+        std::mem::swap(&mut a.armed, &mut b.armed);
+        self.pads_tx.send(audio::Cmd::SwapBanks)?;
+        self.tui_tx.send(tui::Cmd::SwapBanks)?;
+        Ok(())
+    }
+
+    /// start bouncing output to new take, or stop current take
+    fn toggle_bounce(&mut self) -> Result<()> {
+        if let Some((path, bounce)) = self.bounce.take() {
+            self.pads_tx.send(audio::Cmd::AssignBounce(None))?;
+            match bounce.finish() {
+                Ok(()) => self.tui_tx.send(tui::Cmd::Log(format!("stopped recording `{}`", path)))?,
+                Err(e) => self.tui_tx.send(tui::Cmd::Error(format!("failed to finish `{}`: {}", path, e)))?,
+            }
+        } else {
+            std::fs::create_dir_all("recordings")?;
+            let mut index = 0;
+            let mut file = std::fs::File::create_new(format!("recordings/take{}.wav", index));
+            while file.is_err() {
+                index += 1;
+                file = std::fs::File::create_new(format!("recordings/take{}.wav", index));
+            }
+            let path = format!("recordings/take{}.wav", index);
+            let (bounce, tap) = audio::bounce::Bounce::new(file?);
+            self.pads_tx.send(audio::Cmd::AssignBounce(Some(tap)))?;
+            self.tui_tx.send(tui::Cmd::Log(format!("recording to `{}`...", path)))?;
+            self.bounce = Some((path, bounce));
+        }
+        Ok(())
+    }
+
+    fn handle_tap(&mut self) -> Result<()> {
+        self.tap_at(std::time::Instant::now())
+    }
+
+    /// tap landing at `now`
+    fn tap_at(&mut self, now: std::time::Instant) -> Result<()> {
+        if !self.master {
+            return Ok(());
+        }
+        match self.last_tap.map(|v| now.duration_since(v)) {
+            Some(gap) if gap < audio::TAP_TIMEOUT => {
+                if self.taps.len() == audio::TAP_COUNT - 1 {
+                    self.taps.pop_front();
+                }
+                self.taps.push_back(gap.as_secs_f32());
+                if let Some(tempo) = audio::clamp_tempo(60. / median(&self.taps)).filter(|_| self.taps.len() == audio::TAP_COUNT - 1) {
+                    self.pads_tx.send(audio::Cmd::AssignTempo(tempo))?;
+                    self.tui_tx.send(tui::Cmd::Tempo(tempo))?;
+                }
+            }
+            // long gap; start over
+            _ => self.taps.clear(),
+        }
+        self.last_tap = Some(now);
+        Ok(())
+    }
+
+    /// bind next note or cc to learn target
+    fn learn(&mut self, event: LiveEvent) -> Result<()> {
+        let GlobalState::Learn { target, .. } = self.state else {
+            return Ok(());
+        };
+        let LiveEvent::Midi { message, .. } = event else {
+            return Ok(());
+        };
+        match (message, target) {
+            (MidiMessage::NoteOn { key, .. }, _) if self.controls.key(key.as_int()).is_some_and(|v| v.0 == KeyCode::Global) => {
+                // cancel learn
+                self.state = GlobalState::Yield;
+                self.tui_tx.send(tui::Cmd::Yield)?;
+                return Ok(());
+            }
+            (MidiMessage::NoteOn { key, .. }, LearnTarget::Key(..)) => self.controls.bind(target, key.as_int())?,
+            (MidiMessage::Controller { controller, .. }, LearnTarget::Ctrl(..)) => self.controls.bind(target, controller.as_int())?,
+            _ => return Ok(()),
+        }
+        if let Some(target) = target.next() {
+            self.tui_tx.send(tui::Cmd::Learn(target.name()))?;
+            self.state = GlobalState::Learn { target, since: std::time::Instant::now() };
+        } else {
+            self.state = GlobalState::Yield;
+            self.tui_tx.send(tui::Cmd::Yield)?;
+        }
+        Ok(())
+    }
+}
+
+/// 14-bit cc value, 0..=16383, from msb and lsb
+fn combine(msb: u8, lsb: u8) -> u16 {
+    u16::from(msb & 0x7f) << 7 | u16::from(lsb & 0x7f)
+}
+
+/// code's name matches query, ignoring case and underscores
+fn same_name(code: &impl std::fmt::Debug, query: &str) -> bool {
+    let query = query.chars().filter(|v| *v != '_').collect::<String>();
+    format!("{:?}", code).eq_ignore_ascii_case(&query)
+}
+
+/// failure loading file, telling missing from malformed
+fn load_failure(path: &Path, e: &color_eyre::Report) -> String {
+    match e.downcast_ref::<std::io::Error>() {
+        Some(v) if v.kind() == std::io::ErrorKind::NotFound => format!("missing `{}`", path.display()),
+        Some(v) => format!("unreadable `{}`: {}", path.display(), v),
+        None => format!("malformed `{}`: {}", path.display(), e),
+    }
+}
+
+/// modification time of file, if readable
+fn modified(path: impl AsRef<Path>) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|v| v.modified()).ok()
+}
+
+/// readable subdirectories and files of given extensions in dir, skipping hidden entries
+fn list(dir: impl AsRef<Path>, exts: &[&str]) -> Result<Vec<Box<Path>>, std::io::Error> {
+    // fresh checkouts lack browsed dirs
+    std::fs::create_dir_all(&dir)?;
+    Ok(std::fs::read_dir(dir)?
+        .flat_map(|v| {
+            let entry = v.ok()?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                return None;
+            }
+            let path = entry.path();
+            let keep = path.is_dir() || path
+                .extension()
+                .and_then(|v| v.to_str())
+                .is_some_and(|v| exts.contains(&v) && std::fs::File::open(&path).is_ok());
+            keep.then(|| path.into_boxed_path())
+        })
+        .collect())
+}
+
+/// lower half free-running, exponential between PAN_HZ_MIN and PAN_HZ_MAX;
+/// upper half synced to TREMOLO_PERIODS, longest first
+fn pan_rate(value: u8) -> audio::fx::Rate {
+    if value < 64 {
+        let t = value as f32 / 63.;
+        audio::fx::Rate::Free(audio::PAN_HZ_MIN * (audio::PAN_HZ_MAX / audio::PAN_HZ_MIN).powf(t))
+    } else {
+        let periods = audio::TREMOLO_PERIODS;
+        audio::fx::Rate::Synced(periods[periods.len() - 1 - (value as usize - 64) * periods.len() / 64])
+    }
+}
+
+/// push step interval into window, tempo of median once half full
+fn window_tempo(iois: &mut std::collections::VecDeque<f32>, ioi: f32) -> Option<f32> {
+    if iois.len() == audio::TEMPO_WINDOW {
+        iois.pop_front();
+    }
+    iois.push_back(ioi);
+    // median rejects jittered steps
+    let tempo = audio::clamp_tempo(60. / median(iois) / audio::STEP_DIV as f32);
+    tempo.filter(|_| iois.len() >= audio::TEMPO_WINDOW / 2)
+}
+
+fn median(values: &std::collections::VecDeque<f32>) -> f32 {
+    let mut values = values.iter().copied().collect::<Vec<_>>();
+    values.sort_by(f32::total_cmp);
+    values[values.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// handler with default controls, and receivers of what it sends
+    fn handler() -> (InputHandler, std::sync::mpsc::Receiver<tui::Cmd>, audio::CmdRx<PAD_COUNT>) {
+        let (tui_tx, tui_rx) = std::sync::mpsc::channel();
+        let (pads_tx, pads_rx) = audio::cmd_queue();
+        (InputHandler::new(tui_tx, pads_tx).unwrap(), tui_rx, pads_rx)
+    }
+
+    /// scene told apart by gain of first pad
+    fn scene(gain: f32) -> audio::pads::Scene<PAD_COUNT> {
+        let mut scene = audio::pads::Scene::new();
+        scene.kit_a[0].inner[0].gain = gain;
+        scene
+    }
+
+    #[test]
+    fn recall_loads_slot_scene() {
+        let (mut handler, tui_rx, mut pads_rx) = handler();
+        handler.assign_slots(vec![Some(scene(0.25)), Some(scene(0.5)), None]);
+        handler.recall(1).unwrap();
+        let Some(audio::Cmd::LoadScene(scene)) = pads_rx.try_recv() else {
+            panic!("recall sent no scene");
+        };
+        assert_eq!(scene.kit_a[0].inner[0].gain, 0.5);
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::AssignSlot(Some(1)))));
+        // empty slot leaves active scene
+        handler.recall(2).unwrap();
+        assert!(pads_rx.try_recv().is_none());
+        assert_eq!(handler.slot, Some(1));
+    }
+
+    /// tempos sent to audio
+    fn tempos(pads_rx: &mut audio::CmdRx<PAD_COUNT>) -> Vec<f32> {
+        std::iter::from_fn(|| pads_rx.try_recv())
+            .filter_map(|v| match v {
+                audio::Cmd::AssignTempo(v) => Some(v),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn zero_interval_clock_ignored() {
+        let (mut handler, tui_rx, mut pads_rx) = handler();
+        let step = (audio::PPQ / audio::STEP_DIV) as usize;
+        // every step doubled at once
+        for _ in 0..audio::TEMPO_WINDOW * 2 {
+            for _ in 0..step * 2 {
+                handler.push(&[0xf8]).unwrap();
+            }
+        }
+        assert!(tempos(&mut pads_rx).is_empty());
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::Log(v) if v.starts_with("ignored clock step"))));
+        // plausible steps after still derive tempo, within bounds
+        for _ in 0..audio::TEMPO_WINDOW {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            for _ in 0..step {
+                handler.push(&[0xf8]).unwrap();
+            }
+        }
+        let tempos = tempos(&mut pads_rx);
+        assert!(!tempos.is_empty());
+        assert!(tempos.iter().all(|v| (audio::TEMPO_MIN..=audio::TEMPO_MAX).contains(v)));
+        assert!(audio::clamp_tempo(f32::INFINITY).is_none() && audio::clamp_tempo(f32::NAN).is_none());
+        assert_eq!(window_tempo(&mut std::collections::VecDeque::from([0.; audio::TEMPO_WINDOW]), 0.), None);
+    }
+
+    #[test]
+    fn garbage_scene_logged_and_ignored() {
+        let (mut handler, tui_rx, mut pads_rx) = handler();
+        let dir = std::env::temp_dir().join(format!("garbage-scene-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("garbage.sd"), "{\"kit_a\": [{\"inner\": [").unwrap();
+        for (name, error) in [("garbage.sd", "malformed"), ("absent.sd", "missing")] {
+            handler.state = GlobalState::LoadScene { paths: vec![dir.join(name).into()], file_index: 0 };
+            // hold b confirms scene
+            handler.push(&[0x90, KeyCode::HoldB as u8, 100]).unwrap();
+            assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::Error(v) if v.starts_with(error))), "{name}");
+            assert!(!std::iter::from_fn(|| pads_rx.try_recv()).any(|v| matches!(v, audio::Cmd::LoadScene(_))));
+        }
+        std::fs::remove_dir_all(&dir).ok();
+        // handler carries on
+        handler.push(&[0xf8]).unwrap();
+        assert!(matches!(pads_rx.try_recv(), Some(audio::Cmd::Clock)));
+    }
+
+    #[test]
+    fn list_omits_extensionless_and_hidden_files() {
+        let dir = std::env::temp_dir().join(format!("listed-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("kit")).unwrap();
+        for name in ["kick.wav", "README", ".hidden", "notes.txt", "snare.WAV.bak"] {
+            std::fs::write(dir.join(name), []).unwrap();
+        }
+        let mut listed = list(&dir, &["wav"]).unwrap();
+        listed.sort();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(listed, [dir.join("kick.wav").into_boxed_path(), dir.join("kit").into_boxed_path()]);
+    }
+
+    #[test]
+    fn empty_rd_stays_in_dir() {
+        let dir = std::env::temp_dir().join(format!("empty-rd-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("empty.wav");
+        std::fs::write(&path, audio::bounce::image(44100, &[0; 64]).unwrap()).unwrap();
+        std::fs::write(path.with_extension("rd"), r#"{"tempo": null, "steps": null, "onsets": []}"#).unwrap();
+        let (mut handler, tui_rx, _pads_rx) = handler();
+        handler.state = GlobalState::LoadWav { paths: vec![path.clone().into()], file_index: 0 };
+        // selecting wav with no onsets logs rather than entering onset selection
+        handler.push(&[0x90, KeyCode::HoldB as u8, 100]).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert!(matches!(handler.state, GlobalState::LoadWav { .. }));
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::Error(e) if e.starts_with("no onsets"))));
+    }
+
+    #[test]
+    fn shifted_paging_wraps_around_listing() {
+        let (mut handler, _tui_rx, _pads_rx) = handler();
+        let paths = (0..12).map(|v| Path::new(&format!("{}.wav", v)).into()).collect::<Vec<_>>();
+        handler.state = GlobalState::LoadWav { paths, file_index: 0 };
+        let tap = |handler: &mut InputHandler, key: KeyCode| {
+            handler.push(&[0x90, key as u8, 100]).unwrap();
+            handler.push(&[0x80, key as u8, 0]).unwrap();
+            let GlobalState::LoadWav { file_index, .. } = handler.state else { panic!("left browser") };
+            file_index
+        };
+        // single steps wrap below zero
+        assert_eq!(tap(&mut handler, KeyCode::KitB), 11);
+        assert_eq!(tap(&mut handler, KeyCode::ReverseB), 0);
+        // pages of FILE_COUNT wrap past either end
+        handler.push(&[0x90, KeyCode::ShiftB as u8, 100]).unwrap();
+        assert_eq!(tap(&mut handler, KeyCode::KitB), 12 - tui::FILE_COUNT);
+        assert_eq!(tap(&mut handler, KeyCode::KitB), 12 - 2 * tui::FILE_COUNT);
+        assert_eq!(tap(&mut handler, KeyCode::KitB), (12 - 3 * tui::FILE_COUNT as isize).rem_euclid(12) as usize);
+        assert_eq!(tap(&mut handler, KeyCode::ReverseB), 12 - 2 * tui::FILE_COUNT);
+        handler.push(&[0x80, KeyCode::ShiftB as u8, 0]).unwrap();
+        assert_eq!(tap(&mut handler, KeyCode::ReverseB), 12 - 2 * tui::FILE_COUNT + 1);
+    }
+
+    #[test]
+    fn typing_kick_jumps_to_first_kick() {
+        let (mut handler, tui_rx, _pads_rx) = handler();
+        let paths = ["bass", "hat_kicked", "Kick1", "kick2", "snare"].map(|v| Path::new(&format!("{}.wav", v)).into()).to_vec();
+        handler.state = GlobalState::LoadWav { paths, file_index: 4 };
+        // typed a char at a time, as from tui
+        let mut query = String::new();
+        for c in "kick".chars() {
+            query.push(c);
+            handler.search(&query).unwrap();
+        }
+        assert!(matches!(handler.state, GlobalState::LoadWav { file_index: 2, .. }));
+        assert!(tui_rx.try_iter().last().is_some_and(|v| matches!(v, tui::Cmd::LoadWav(listing) if listing.index == 2)));
+        // no match leaves cursor
+        handler.search("clap").unwrap();
+        assert!(matches!(handler.state, GlobalState::LoadWav { file_index: 2, .. }));
+    }
+
+    #[test]
+    fn missing_dirs_browse_empty() {
+        // created on first listing, as on fresh checkout
+        for name in ["onsets", "scenes"] {
+            let dir = std::env::temp_dir().join(format!("fresh-{}-{}", name, std::process::id()));
+            std::fs::remove_dir_all(&dir).ok();
+            assert!(list(&dir, &["wav"]).unwrap().is_empty());
+            assert!(dir.is_dir());
+            std::fs::remove_dir_all(&dir).ok();
+        }
+        // empty listings stepped through in place, then left
+        let (mut handler, _tui_rx, _pads_rx) = handler();
+        for state in [GlobalState::LoadWav { paths: Vec::new(), file_index: 0 }, GlobalState::LoadScene { paths: Vec::new(), file_index: 0 }] {
+            handler.state = state;
+            for key in [KeyCode::KitB, KeyCode::ReverseB] {
+                handler.push(&[0x90, key as u8, 100]).unwrap();
+                handler.push(&[0x80, key as u8, 0]).unwrap();
+                assert!(matches!(handler.state, GlobalState::LoadWav { file_index: 0, .. } | GlobalState::LoadScene { file_index: 0, .. }));
+            }
+            handler.push(&[0x90, KeyCode::HoldB as u8, 100]).unwrap();
+            handler.push(&[0x80, KeyCode::HoldB as u8, 0]).unwrap();
+            assert!(matches!(handler.state, GlobalState::Yield));
+        }
+    }
+
+    #[test]
+    fn scene_saved_under_name_verbatim() {
+        let dir = std::env::temp_dir().join(format!("named-scenes-{}", std::process::id()));
+        audio::assign_dirs(None, Some(&dir));
+        assert_eq!(audio::scenes_dir(), dir);
+        let (mut handler, tui_rx, mut pads_rx) = handler();
+        let path = dir.join("live set v1.2.sd");
+        assert!(handler.save_scene("live set v1.2", false).unwrap());
+        assert!(path.exists());
+        assert!(matches!(pads_rx.try_recv(), Some(audio::Cmd::SaveScene(_, false))));
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::SaveScene(v) if v == path.display().to_string())));
+        // taken name kept unless overwriting
+        assert!(!handler.save_scene("live set v1.2", false).unwrap());
+        assert!(pads_rx.try_recv().is_none());
+        assert!(handler.save_scene("live set v1.2", true).unwrap());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn remapped_note_and_cc_reach_their_handlers() {
+        let (mut handler, _tui_rx, mut pads_rx) = handler();
+        let path = std::env::temp_dir().join(format!("controls-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"keys": {"BankAOffset": 100}, "ctrls": {"GainA": 20}}"#).unwrap();
+        handler.controls = Controls::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        // third note of bank a's span moved up to note 102, pads counting down from its top
+        handler.push(&[0x90, 102, 100]).unwrap();
+        assert_eq!(handler.bank_a.downs, [PAD_COUNT as u8 - 1 - 2]);
+        handler.push(&[0x80, 102, 0]).unwrap();
+        handler.push(&[0x90, KeyCode::BankAOffset as u8 + 2, 100]).unwrap();
+        assert!(handler.bank_a.downs.is_empty());
+        while pads_rx.try_recv().is_some() {}
+        // gain a moved to cc 20, default cc left unmapped
+        handler.push(&[0xb0, 20, 127]).unwrap();
+        assert!(matches!(pads_rx.try_recv(), Some(audio::Cmd::Bank(Bank::A, audio::BankCmd::AssignGain(2.)))));
+        handler.push(&[0xb0, CtrlCode::GainA as u8, 127]).unwrap();
+        assert!(pads_rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn velocity_64_plays_near_half_of_127() {
+        // peak of bank a's top pad hit at velocity through curve
+        let peak = |vel: u8, curve: VelCurve| {
+            let (mut handler, _tui_rx, pads_rx) = handler();
+            handler.vel_curve = curve;
+            let (tui_tx, _audio_tui_rx) = std::sync::mpsc::channel();
+            let mut audio = audio::pads::AudioHandler::new(pads_rx, tui_tx, None, 44100);
+            let pcm = [8192i16; 4096].iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>();
+            let wav = audio::Wav {
+                tempo: None,
+                steps: None,
+                path: Path::new("memory.wav").into(),
+                len: pcm.len() as u64,
+                data: Some(audio::bounce::image(44100, &pcm).unwrap().into()),
+                stream: None,
+            };
+            let onset = audio::Onset { wav, start: 0 };
+            handler.pads_tx.send(audio::Cmd::Bank(Bank::A, audio::BankCmd::AssignOnset(PAD_COUNT as u8 - 1, false, Box::new(onset)))).unwrap();
+            handler.pads_tx.send(audio::Cmd::AssignTempo(120.)).unwrap();
+            handler.push(&[0x90, KeyCode::BankAOffset as u8, vel]).unwrap();
+            let mut buffer = vec![0f32; 1024];
+            audio.tick(&mut buffer, 2);
+            buffer.iter().fold(0f32, |peak, v| peak.max(v.abs()))
+        };
+        let full = peak(127, VelCurve::Linear);
+        assert!(full > 0.);
+        assert!((peak(64, VelCurve::Linear) / full - 0.5).abs() < 0.01);
+        assert!((peak(64, VelCurve::Square) / full - 0.25).abs() < 0.01);
+        // fixed for clock-y material
+        assert_eq!(peak(64, VelCurve::Fixed), full);
+    }
+
+    #[test]
+    fn all_notes_off_releases_stuck_pads() {
+        let (mut handler, tui_rx, mut pads_rx) = handler();
+        // note on whose note off never came
+        handler.push(&[0x90, KeyCode::BankAOffset as u8, 100]).unwrap();
+        assert_eq!(handler.bank_a.downs, [PAD_COUNT as u8 - 1]);
+        while pads_rx.try_recv().is_some() {}
+        handler.push(&[0xb0, audio::ALL_NOTES_OFF, 0]).unwrap();
+        assert!(handler.bank_a.downs.is_empty() && handler.bank_b.downs.is_empty());
+        assert!(std::iter::from_fn(|| pads_rx.try_recv()).any(|v| matches!(v, audio::Cmd::Panic)));
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::Log(v) if v == "all voices off")));
+    }
+
+    #[test]
+    fn kit_handlers_ignore_empty_downs() {
+        let (mut handler, _tui_rx, mut pads_rx) = handler();
+        // pad's note off handled before its down
+        for (state, reverse) in [(BankState::LoadKit, false), (BankState::AssignKit, false), (BankState::AssignKit, true)] {
+            handler.bank_a.state = state;
+            handler.bank_a.reverse = reverse;
+            assert!(handler.bank_a.downs.is_empty());
+            handler.bank_a.handle_pad_down(&mut handler.pads_tx, &mut handler.tui_tx).unwrap();
+            assert!(pads_rx.try_recv().is_none());
+        }
+        // program change names its kit without downs
+        handler.bank_a.handle_program(3, &mut handler.pads_tx, &mut handler.tui_tx).unwrap();
+        assert!(matches!(pads_rx.try_recv(), Some(audio::Cmd::Bank(Bank::A, audio::BankCmd::LoadKit(3)))));
+    }
+
+    #[test]
+    fn program_change_loads_kit_into_last_touched_bank() {
+        let (mut handler, tui_rx, mut pads_rx) = handler();
+        // touch bank b
+        handler.push(&[0x90, KeyCode::BankBOffset as u8, 100]).unwrap();
+        handler.push(&[0x80, KeyCode::BankBOffset as u8, 0]).unwrap();
+        while pads_rx.try_recv().is_some() {}
+        tui_rx.try_iter().for_each(drop);
+        handler.push(&[0xc0, 3]).unwrap();
+        assert!(matches!(pads_rx.try_recv(), Some(audio::Cmd::Bank(Bank::B, audio::BankCmd::LoadKit(3)))));
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::Bank(Bank::B, tui::BankCmd::LoadKit(Some(3))))));
+        // program past last kit clamped
+        handler.push(&[0xc0, 100]).unwrap();
+        assert!(matches!(pads_rx.try_recv(), Some(audio::Cmd::Bank(Bank::B, audio::BankCmd::LoadKit(v))) if v == PAD_COUNT as u8 - 1));
+    }
+
+    #[test]
+    fn aftertouch_modulates_last_touched_bank() {
+        let (mut handler, _tui_rx, mut pads_rx) = handler();
+        handler.push(&[0xd0, 127]).unwrap();
+        assert!(matches!(pads_rx.try_recv(), Some(audio::Cmd::Bank(Bank::A, audio::BankCmd::AssignTouch(audio::Touch::Speed, 1.)))));
+        // released back to base
+        handler.push(&[0xd0, 0]).unwrap();
+        assert!(matches!(pads_rx.try_recv(), Some(audio::Cmd::Bank(Bank::A, audio::BankCmd::AssignTouch(audio::Touch::Speed, 0.)))));
+    }
+
+    #[test]
+    fn tap_tempo_locks_to_median_after_four_taps() {
+        let (mut handler, _tui_rx, mut pads_rx) = handler();
+        handler.master = true;
+        let start = std::time::Instant::now();
+        let tap = |handler: &mut InputHandler, secs: f32| handler.tap_at(start + std::time::Duration::from_secs_f32(secs)).unwrap();
+        // late third tap outvoted by others
+        for secs in [0., 0.5, 1.2] {
+            tap(&mut handler, secs);
+            assert!(pads_rx.try_recv().is_none());
+        }
+        tap(&mut handler, 1.7);
+        assert!((tempos(&mut pads_rx)[0] - 120.).abs() < 0.01);
+        // oldest gaps dropped for newest
+        tap(&mut handler, 2.1);
+        assert!((tempos(&mut pads_rx)[0] - 120.).abs() < 0.01);
+        tap(&mut handler, 2.5);
+        assert!((tempos(&mut pads_rx)[0] - 150.).abs() < 0.01);
+        // long gap starts over
+        tap(&mut handler, 10.);
+        for secs in [10.6, 11.2] {
+            tap(&mut handler, secs);
+            assert!(pads_rx.try_recv().is_none());
+        }
+        tap(&mut handler, 11.8);
+        assert!((tempos(&mut pads_rx)[0] - 100.).abs() < 0.01);
+    }
+
+    #[test]
+    fn ppq_48_steps_every_12_clocks() {
+        let (mut handler, tui_rx, mut pads_rx) = handler();
+        handler.assign_ppq(48).unwrap();
+        // clocks stepped on
+        let steps = (0..48)
+            .filter(|_| {
+                handler.push(&[0xf8]).unwrap();
+                std::iter::from_fn(|| pads_rx.try_recv()).any(|v| matches!(v, audio::Cmd::Clock))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(steps, [0, 12, 24, 36]);
+        assert_eq!(handler.clock, 0);
+        // step division kept for ppq not divisible by STEP_DIV
+        handler.assign_ppq(50).unwrap();
+        assert_eq!(handler.ppq, 48);
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::Error(v) if v.starts_with("ignoring ppq 50"))));
+    }
+
+    #[test]
+    fn start_then_24_clocks_lands_on_step_4() {
+        let (mut handler, tui_rx, mut pads_rx) = handler();
+        handler.push(&[0xf8]).unwrap();
+        while pads_rx.try_recv().is_some() {}
+        handler.push(&[0xfa]).unwrap();
+        assert!(matches!(pads_rx.try_recv(), Some(audio::Cmd::Stop)));
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::Stop)));
+        // one quarter at 24 ppq steps audio from 0 through 3
+        for _ in 0..24 {
+            handler.push(&[0xf8]).unwrap();
+        }
+        let clocks = std::iter::from_fn(|| pads_rx.try_recv()).filter(|v| matches!(v, audio::Cmd::Clock)).count();
+        assert_eq!(clocks, audio::STEP_DIV as usize);
+        assert_eq!(handler.clock, 0);
+    }
+
+    #[test]
+    fn song_position_back_during_record() {
+        let (mut handler, _tui_rx, pads_rx) = handler();
+        let (tui_tx, _audio_tui_rx) = std::sync::mpsc::channel();
+        let mut audio = audio::pads::AudioHandler::new(pads_rx, tui_tx, None, 44100);
+        let pad = KeyCode::BankAOffset as u8;
+        let mut step = |handler: &mut InputHandler| {
+            handler.push(&[0x90, pad, 100]).unwrap();
+            handler.push(&[0x80, pad, 0]).unwrap();
+            for _ in 0..audio::PPQ / audio::STEP_DIV {
+                handler.push(&[0xf8]).unwrap();
+            }
+            audio.tick(&mut [0f32; 64], 2);
+        };
+        // events captured from step 16 on
+        handler.push(&[0xf2, 16, 0]).unwrap();
+        for _ in 0..8 {
+            step(&mut handler);
+        }
+        // earlier step than any captured event
+        handler.push(&[0xf2, 2, 0]).unwrap();
+        assert_eq!(handler.clock, 0);
+        for _ in 0..8 {
+            step(&mut handler);
+        }
+    }
+
+    #[test]
+    fn combine_spans_14_bits() {
+        assert_eq!(combine(0, 0), 0);
+        assert_eq!(combine(0, 127), 127);
+        assert_eq!(combine(1, 0), 128);
+        assert_eq!(combine(64, 0), 8192);
+        assert_eq!(combine(127, 127), 16383);
+        // data bytes only
+        assert_eq!(combine(255, 255), 16383);
+    }
+
+    /// speeds sent to bank a
+    fn speeds(pads_rx: &mut audio::CmdRx<PAD_COUNT>) -> Vec<f32> {
+        std::iter::from_fn(|| pads_rx.try_recv())
+            .filter_map(|v| match v {
+                audio::Cmd::Bank(Bank::A, audio::BankCmd::AssignSpeed(v)) => Some(v),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn speed_msb_latched_once_lsb_follows() {
+        let (mut handler, _tui_rx, mut pads_rx) = handler();
+        let (msb, lsb) = (CtrlCode::SpeedA as u8, handler.controls.lsb(CtrlCode::SpeedA).unwrap());
+        // msb alone applies as 7-bit
+        handler.push(&[0xb0, msb, 64]).unwrap();
+        assert_eq!(speeds(&mut pads_rx), [64. / 127. * 2.]);
+        handler.push(&[0xb0, lsb, 64]).unwrap();
+        assert_eq!(speeds(&mut pads_rx), [8256. / 16383. * 2.]);
+        // msbs then wait for their lsb
+        handler.push(&[0xb0, msb, 65]).unwrap();
+        assert!(speeds(&mut pads_rx).is_empty());
+        handler.push(&[0xb0, lsb, 0]).unwrap();
+        assert_eq!(speeds(&mut pads_rx), [8320. / 16383. * 2.]);
+        // lsb remapped, old lsb cc unpaired
+        handler.controls.file.lsbs.insert(CtrlCode::SpeedA, 52);
+        handler.controls.remap();
+        assert_eq!(handler.controls.lsb(CtrlCode::SpeedA), Some(52));
+        handler.push(&[0xb0, lsb, 1]).unwrap();
+        assert!(speeds(&mut pads_rx).is_empty());
+        handler.push(&[0xb0, 52, 1]).unwrap();
+        assert_eq!(speeds(&mut pads_rx), [8321. / 16383. * 2.]);
+        // lsb cc mapped to another ctrl isn't paired
+        handler.controls.file.ctrls.insert(CtrlCode::GainA, 52);
+        handler.controls.remap();
+        assert_eq!(handler.controls.lsb(CtrlCode::SpeedA), None);
+    }
+
+    fn variance(values: &[f32]) -> f32 {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32
+    }
+
+    #[test]
+    fn window_tempo_reduces_jitter() {
+        // 120 bpm steps, every third pushed 20% late and the next pulled early
+        let step = 60. / 120. / audio::STEP_DIV as f32;
+        let iois = (0..64).map(|i| step * [1., 1.2, 0.8][i % 3]).collect::<Vec<_>>();
+        let raw = iois.iter().map(|v| 60. / v / audio::STEP_DIV as f32).collect::<Vec<_>>();
+        let mut window = std::collections::VecDeque::new();
+        let smoothed = iois.iter().filter_map(|&v| window_tempo(&mut window, v)).collect::<Vec<_>>();
+        assert!(variance(&smoothed) < variance(&raw) / 100.);
+        assert!(smoothed.iter().all(|v| (v - 120.).abs() < 1.));
+    }
+
+    #[test]
+    fn window_tempo_ramps_in() {
+        let mut window = std::collections::VecDeque::new();
+        let tempos = (0..audio::TEMPO_WINDOW).map(|_| window_tempo(&mut window, 0.125)).collect::<Vec<_>>();
+        // silent until half full, then steady
+        assert!(tempos[..audio::TEMPO_WINDOW / 2 - 1].iter().all(Option::is_none));
+        assert!(tempos[audio::TEMPO_WINDOW / 2 - 1..].iter().all(|v| *v == Some(120.)));
+        assert_eq!(window.len(), audio::TEMPO_WINDOW);
+    }
 }
@@ -0,0 +1,13 @@
+//! sampler engine, embeddable apart from the binary's tui and device setup: feed
+//! `Cmd`s to an `AudioHandler` and `tick` buffers directly. still links midir and
+//! cpal, whose sample traits and clock out connection it takes
+
+pub mod audio;
+pub mod input;
+pub mod link;
+pub mod osc;
+pub mod pll;
+pub mod tui;
+
+pub use audio::{pads::{AudioHandler, Scene}, BankCmd, Cmd, CmdRx, CmdTx, PAD_COUNT};
+pub use input::{Bank, InputHandler};
@@ -0,0 +1,466 @@
+use crate::{input::InputHandler, tui};
+use std::{
+    net::{Ipv4Addr, SocketAddrV4, UdpSocket},
+    sync::{mpsc::Sender, Arc, Mutex},
+    time::Instant,
+};
+use color_eyre::Result;
+
+/// ableton link discovery multicast group and port
+pub const GROUP: Ipv4Addr = Ipv4Addr::new(224, 76, 78, 75);
+pub const PORT: u16 = 20808;
+/// longest wait for peer message before checking step
+pub const POLL: std::time::Duration = std::time::Duration::from_millis(1);
+/// most steps clocked at once after a stall; more are skipped
+pub const CATCHUP: i64 = 4;
+/// beats per bar aligned with session phase; steps start on its lines
+pub const QUANTUM: i64 = 4;
+/// ghost time offsets taken per measurement, as link takes
+pub const MEASURE_POINTS: usize = 100;
+/// wait for pong before pinging again
+pub const PING_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(50);
+/// pings unanswered before measurement given up
+pub const PING_TRIES: u32 = 5;
+/// wait between measurements, keeping ghost time from drifting with host clocks
+pub const MEASURE_PERIOD: std::time::Duration = std::time::Duration::from_secs(30);
+/// wait before measuring again after peer left pings unanswered
+pub const MEASURE_RETRY: std::time::Duration = std::time::Duration::from_secs(1);
+
+const PROTOCOL: &[u8] = b"_asdp_v\x01";
+/// message type, ttl, group id and node id following protocol header
+const HEADER_LEN: usize = 12;
+const ALIVE: u8 = 1;
+const RESPONSE: u8 = 2;
+const TIMELINE: u32 = u32::from_be_bytes(*b"tmln");
+const SESSION: u32 = u32::from_be_bytes(*b"sess");
+const ENDPOINT: u32 = u32::from_be_bytes(*b"mep4");
+
+/// measurement protocol header, followed by message type
+const MEASURE_PROTOCOL: &[u8] = b"_link_v\x01";
+const PING: u8 = 1;
+const PONG: u8 = 2;
+const HOST_TIME: u32 = u32::from_be_bytes(*b"__ht");
+const GHOST_TIME: u32 = u32::from_be_bytes(*b"__gt");
+const PREV_GHOST_TIME: u32 = u32::from_be_bytes(*b"_pgt");
+
+/// link session beat grid, as broadcast by peers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timeline {
+    pub micros_per_beat: i64,
+    /// beat at time origin, in millionths of a beat
+    pub beat_origin: i64,
+    /// in microseconds of session ghost time
+    pub time_origin: i64,
+}
+
+impl Timeline {
+    pub fn tempo(&self) -> f32 {
+        60e6 / self.micros_per_beat as f32
+    }
+
+    pub fn beats_at(&self, micros: i64) -> f64 {
+        self.beat_origin as f64 / 1e6 + (micros - self.time_origin) as f64 / self.micros_per_beat as f64
+    }
+
+    /// step sounding at ghost time `micros`, STEP_DIV steps per beat
+    pub fn step_at(&self, micros: i64) -> i64 {
+        (self.beats_at(micros) * crate::audio::STEP_DIV as f64).floor() as i64
+    }
+}
+
+/// steps to clock on moving from step `last` to `next`; none if moved backward
+pub fn steps_between(last: Option<i64>, next: i64) -> u32 {
+    last.map(|v| (next - v).clamp(0, CATCHUP) as u32).unwrap_or(0)
+}
+
+/// peer's alive or response discovery message
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Peer {
+    pub session: [u8; 8],
+    pub timeline: Timeline,
+    /// where peer answers measurement pings
+    pub endpoint: Option<SocketAddrV4>,
+    /// seconds until peer presumed gone
+    pub ttl: u8,
+}
+
+/// key and value of each payload entry, until one overruns payload
+fn entries(mut payload: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    std::iter::from_fn(move || {
+        let key = u32::from_be_bytes(payload.get(..4)?.try_into().ok()?);
+        let len = u32::from_be_bytes(payload.get(4..8)?.try_into().ok()?) as usize;
+        let value = payload.get(8..8 + len)?;
+        payload = &payload[8 + len..];
+        Some((key, value))
+    })
+}
+
+fn micros(value: &[u8]) -> Option<i64> {
+    Some(i64::from_be_bytes(value.try_into().ok()?))
+}
+
+/// peer of alive or response discovery message, if any, with its session and timeline
+pub fn decode(bytes: &[u8]) -> Option<Peer> {
+    let bytes = bytes.strip_prefix(PROTOCOL)?;
+    if !matches!(bytes.first()?, &ALIVE | &RESPONSE) {
+        return None;
+    }
+    let ttl = *bytes.get(1)?;
+    let (mut session, mut timeline, mut endpoint) = (None, None, None);
+    for (key, value) in entries(bytes.get(HEADER_LEN..)?) {
+        match key {
+            TIMELINE if value.len() == 24 => {
+                let field = |i: usize| i64::from_be_bytes(value[i * 8..i * 8 + 8].try_into().unwrap());
+                timeline = Some(Timeline { micros_per_beat: field(0), beat_origin: field(1), time_origin: field(2) });
+            }
+            SESSION => session = value.try_into().ok(),
+            ENDPOINT if value.len() == 6 => {
+                let addr = Ipv4Addr::from(u32::from_be_bytes(value[..4].try_into().unwrap()));
+                endpoint = Some(SocketAddrV4::new(addr, u16::from_be_bytes([value[4], value[5]])));
+            }
+            _ => (),
+        }
+    }
+    Some(Peer {
+        session: session?,
+        timeline: timeline.filter(|v| v.micros_per_beat > 0)?,
+        endpoint,
+        ttl,
+    })
+}
+
+/// offset of session ghost time from host time, taken from ping round trips to one of its peers
+pub struct Measurement {
+    session: [u8; 8],
+    /// ghost minus host time per round trip, in microseconds
+    data: Vec<f64>,
+    /// pings sent since last pong
+    pub tries: u32,
+}
+
+/// what measurement sends or yields on pong
+pub enum Measured {
+    Ping(Vec<u8>),
+    /// ghost time less host time, in microseconds
+    Offset(i64),
+}
+
+impl Measurement {
+    pub fn new(session: [u8; 8]) -> Self {
+        Self { session, data: Vec::new(), tries: 0 }
+    }
+
+    /// ping sent at host time `now`, with ghost time of last pong if any
+    pub fn ping(&mut self, now: i64, ghost: Option<i64>) -> Vec<u8> {
+        self.tries += 1;
+        let mut bytes = MEASURE_PROTOCOL.to_vec();
+        bytes.push(PING);
+        for (key, time) in [(HOST_TIME, Some(now)), (PREV_GHOST_TIME, ghost)] {
+            if let Some(time) = time {
+                bytes.extend(key.to_be_bytes());
+                bytes.extend(8u32.to_be_bytes());
+                bytes.extend(time.to_be_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// take pong received at host time `now`; next ping, or offset once MEASURE_POINTS taken.
+    /// none if not a pong from session
+    pub fn pong(&mut self, bytes: &[u8], now: i64) -> Option<Measured> {
+        let bytes = bytes.strip_prefix(MEASURE_PROTOCOL)?.strip_prefix(&[PONG])?;
+        let (mut session, mut ghost, mut prev_ghost, mut host) = (None, None, None, None);
+        for (key, value) in entries(bytes) {
+            match key {
+                SESSION => session = <[u8; 8]>::try_from(value).ok(),
+                GHOST_TIME => ghost = micros(value),
+                PREV_GHOST_TIME => prev_ghost = micros(value),
+                HOST_TIME => host = micros(value),
+                _ => (),
+            }
+        }
+        if session != Some(self.session) {
+            return None;
+        }
+        self.tries = 0;
+        let ghost = ghost?;
+        if let Some(host) = host {
+            // ghost time taken halfway through round trip
+            self.data.push(ghost as f64 - (now + host) as f64 / 2.);
+            if let Some(prev_ghost) = prev_ghost {
+                self.data.push((ghost + prev_ghost) as f64 / 2. - host as f64);
+            }
+        }
+        if self.data.len() > MEASURE_POINTS {
+            self.data.sort_by(f64::total_cmp);
+            return Some(Measured::Offset(self.data[self.data.len() / 2].round() as i64));
+        }
+        Some(Measured::Ping(self.ping(now, Some(ghost))))
+    }
+}
+
+/// session followed, and its ghost time once measured
+struct Session {
+    peer: Peer,
+    seen: Instant,
+    /// ghost less host time, in microseconds
+    offset: Option<i64>,
+    /// when measurement last asked for, if any
+    measured: Option<Instant>,
+    /// whether measurement underway
+    measuring: bool,
+}
+
+/// join link discovery group, clocking steps from session timeline while link is clock source
+pub fn listen(input: Arc<Mutex<InputHandler>>, tui_tx: Sender<tui::Cmd>) -> Result<()> {
+    let socket = socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::DGRAM, Some(socket2::Protocol::UDP))?;
+    // peers on this host share the port
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, PORT).into())?;
+    socket.join_multicast_v4(&GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_read_timeout(Some(POLL))?;
+    let socket: UdpSocket = socket.into();
+    let pinger = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))?;
+    pinger.set_read_timeout(Some(PING_TIMEOUT))?;
+    input.lock().unwrap().assign_link(Some(true))?;
+    // host time; session ghost time is host time plus measured offset
+    let epoch = Instant::now();
+    let (measure_tx, measure_rx) = std::sync::mpsc::channel();
+    let (offset_tx, offset_rx) = std::sync::mpsc::channel();
+    // measured apart so round trips are timed as pongs arrive, without delaying steps
+    std::thread::spawn(move || {
+        for (session, endpoint) in measure_rx {
+            if offset_tx.send((session, measure(epoch, &pinger, session, endpoint))).is_err() {
+                break;
+            }
+        }
+    });
+    std::thread::spawn(move || {
+        let mut buf = vec![0u8; 512];
+        let mut session: Option<Session> = None;
+        let mut step = None;
+        loop {
+            if let Ok(len) = socket.recv(&mut buf) {
+                if let Some(peer) = decode(&buf[..len]) {
+                    match session.as_mut() {
+                        Some(v) if v.peer.session == peer.session => {
+                            // keep endpoint of peer already measured
+                            v.peer = Peer { endpoint: v.peer.endpoint.or(peer.endpoint), ..peer };
+                            v.seen = Instant::now();
+                        }
+                        Some(_) => (),
+                        None => {
+                            session = Some(Session { peer, seen: Instant::now(), offset: None, measured: None, measuring: false });
+                            step = None;
+                        }
+                    }
+                }
+            }
+            let Some(current) = session.as_mut() else {
+                continue;
+            };
+            for (id, result) in offset_rx.try_iter() {
+                if id != current.peer.session {
+                    continue;
+                }
+                current.measuring = false;
+                match result {
+                    Ok(offset) => {
+                        if current.offset.is_none() {
+                            tui_tx.send(tui::Cmd::Log(format!("link session joined at {:.1} bpm", current.peer.timeline.tempo()))).ok();
+                        }
+                        current.offset = Some(offset);
+                    }
+                    Err(e) => {
+                        tui_tx.send(tui::Cmd::Error(format!("link error: {}", e))).ok();
+                    }
+                }
+            }
+            if current.seen.elapsed().as_secs() > u64::from(current.peer.ttl) {
+                tui_tx.send(tui::Cmd::Log("link session gone".to_string())).ok();
+                session = None;
+                continue;
+            }
+            let wait = if current.offset.is_some() { MEASURE_PERIOD } else { MEASURE_RETRY };
+            if let Some(endpoint) = current.peer.endpoint.filter(|_| !current.measuring && current.measured.is_none_or(|v| v.elapsed() > wait)) {
+                current.measuring = measure_tx.send((current.peer.session, endpoint)).is_ok();
+                current.measured = Some(Instant::now());
+            }
+            let Some(offset) = current.offset else {
+                continue;
+            };
+            let timeline = current.peer.timeline;
+            let next = timeline.step_at(epoch.elapsed().as_micros() as i64 + offset);
+            let steps = match step {
+                // start on a bar line of session
+                None if next.rem_euclid(QUANTUM * crate::audio::STEP_DIV as i64) == 0 => 1,
+                None => continue,
+                Some(_) => steps_between(step, next),
+            };
+            let start = step.is_none();
+            step = Some(next);
+            if steps > 0 {
+                match input.lock().unwrap().link_clock(timeline.tempo(), start, steps) {
+                    // realign once link is clock source again
+                    Ok(false) => step = None,
+                    Ok(true) => (),
+                    Err(e) => {
+                        tui_tx.send(tui::Cmd::Error(format!("link error: {}", e))).ok();
+                    }
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// ghost less host time of session, from round trips to its peer at endpoint
+fn measure(epoch: Instant, pinger: &UdpSocket, session: [u8; 8], endpoint: SocketAddrV4) -> Result<i64, std::io::Error> {
+    let host = || epoch.elapsed().as_micros() as i64;
+    let mut measurement = Measurement::new(session);
+    let mut buf = vec![0u8; 512];
+    pinger.send_to(&measurement.ping(host(), None), endpoint)?;
+    loop {
+        match pinger.recv_from(&mut buf) {
+            Ok((len, _)) => match measurement.pong(&buf[..len], host()) {
+                Some(Measured::Ping(ping)) => {
+                    pinger.send_to(&ping, endpoint)?;
+                }
+                Some(Measured::Offset(offset)) => return Ok(offset),
+                None => (),
+            },
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                if measurement.tries >= PING_TRIES {
+                    let msg = format!("no answer from peer at {}", endpoint);
+                    return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, msg));
+                }
+                pinger.send_to(&measurement.ping(host(), None), endpoint)?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION_ID: [u8; 8] = *b"session1";
+
+    fn entry(key: u32, value: &[u8]) -> Vec<u8> {
+        let mut bytes = key.to_be_bytes().to_vec();
+        bytes.extend((value.len() as u32).to_be_bytes());
+        bytes.extend(value);
+        bytes
+    }
+
+    /// pong of peer in session at ghost time `ghost`, echoing ping's payload
+    fn pong(ping: &[u8], ghost: i64) -> Vec<u8> {
+        let mut bytes = MEASURE_PROTOCOL.to_vec();
+        bytes.push(PONG);
+        bytes.extend(entry(SESSION, &SESSION_ID));
+        bytes.extend(entry(GHOST_TIME, &ghost.to_be_bytes()));
+        bytes.extend(&ping[MEASURE_PROTOCOL.len() + 1..]);
+        bytes
+    }
+
+    #[test]
+    fn step_at_floors_steps_of_beats() {
+        // 120 bpm, beat 2.5 at 1s
+        let timeline = Timeline { micros_per_beat: 500_000, beat_origin: 2_500_000, time_origin: 1_000_000 };
+        assert_eq!(timeline.tempo(), 120.);
+        assert_eq!(timeline.step_at(1_000_000), 10);
+        assert_eq!(timeline.step_at(1_124_999), 10);
+        assert_eq!(timeline.step_at(1_125_000), 11);
+        assert_eq!(timeline.step_at(0), 2);
+        // beat -0.6
+        assert_eq!(timeline.step_at(-550_000), -3);
+    }
+
+    #[test]
+    fn steps_between_clamps_to_catchup() {
+        assert_eq!(steps_between(None, 5), 0);
+        assert_eq!(steps_between(Some(5), 5), 0);
+        assert_eq!(steps_between(Some(3), 5), 2);
+        assert_eq!(steps_between(Some(-2), 1), 3);
+        assert_eq!(steps_between(Some(5), 3), 0);
+        assert_eq!(steps_between(Some(0), 100), CATCHUP as u32);
+    }
+
+    #[test]
+    fn decodes_peer_of_alive_and_response() {
+        let timeline = Timeline { micros_per_beat: 500_000, beat_origin: 1, time_origin: 2 };
+        let mut payload = entry(u32::from_be_bytes(*b"stst"), &[0; 17]);
+        payload.extend(entry(TIMELINE, &[500_000i64, 1, 2].map(i64::to_be_bytes).concat()));
+        payload.extend(entry(SESSION, &SESSION_ID));
+        payload.extend(entry(ENDPOINT, &[192, 168, 1, 2, 0x4e, 0x20]));
+        let message = |kind: u8, payload: &[u8]| [PROTOCOL, &[kind, 5, 0, 0], b"node0001", payload].concat();
+        let peer = Peer { session: SESSION_ID, timeline, endpoint: Some("192.168.1.2:20000".parse().unwrap()), ttl: 5 };
+        assert_eq!(decode(&message(ALIVE, &payload)), Some(peer));
+        assert_eq!(decode(&message(RESPONSE, &payload)), Some(peer));
+        // byebye, and alive without session
+        assert_eq!(decode(&message(3, &payload)), None);
+        assert_eq!(decode(&message(ALIVE, &payload[..payload.len() - 14 - 16])), None);
+    }
+
+    #[test]
+    fn measurement_takes_median_offset() {
+        let offset = 1_234_567;
+        let mut measurement = Measurement::new(SESSION_ID);
+        let mut now = 1_000;
+        let mut ping = measurement.ping(now, None);
+        for i in 0.. {
+            // one-way latency varying around 200us, rarely far off
+            let (there, back) = (200 + i % 7 * 10, if i % 13 == 0 { 5_000 } else { 200 + i % 5 * 10 });
+            let ghost = now + there + offset;
+            now += there + back;
+            // pongs of other sessions ignored
+            let mut other = pong(&ping, 0);
+            other[MEASURE_PROTOCOL.len() + 9..][..8].copy_from_slice(b"session2");
+            assert!(measurement.pong(&other, now).is_none());
+            match measurement.pong(&pong(&ping, ghost), now).unwrap() {
+                Measured::Ping(v) => ping = v,
+                Measured::Offset(v) => {
+                    assert!((v - offset).abs() < 20, "{v}");
+                    assert!(i < MEASURE_POINTS as i64);
+                    return;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn measures_peer_over_udp() {
+        let epoch = Instant::now();
+        let offset = 5_000_000;
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let endpoint = match peer.local_addr().unwrap() {
+            std::net::SocketAddr::V4(v) => v,
+            v => panic!("{v}"),
+        };
+        // answers pings as a link peer, dropping the first
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let mut dropped = false;
+            while let Ok((len, from)) = peer.recv_from(&mut buf) {
+                if std::mem::replace(&mut dropped, true) {
+                    let ghost = epoch.elapsed().as_micros() as i64 + offset;
+                    peer.send_to(&pong(&buf[..len], ghost), from).ok();
+                }
+            }
+        });
+        let pinger = UdpSocket::bind("127.0.0.1:0").unwrap();
+        pinger.set_read_timeout(Some(PING_TIMEOUT)).unwrap();
+        let measured = measure(epoch, &pinger, SESSION_ID, endpoint).unwrap();
+        assert!((measured - offset).abs() < 1_000, "{measured}");
+        // no peer answering
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let endpoint = match silent.local_addr().unwrap() {
+            std::net::SocketAddr::V4(v) => v,
+            v => panic!("{v}"),
+        };
+        assert_eq!(measure(epoch, &pinger, SESSION_ID, endpoint).unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    }
+}
@@ -1,10 +1,7 @@
-mod audio;
-mod input;
-mod tui;
-
 use std::io::Write;
 
 use color_eyre::Result;
+use pc_computer_doctor::{audio, input, link, osc, pll, tui};
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     FromSample, SizedSample,
@@ -13,90 +10,272 @@ use cpal::{
 fn main() -> Result<()> {
     color_eyre::install()?;
 
+    // detect onsets of given wavs, writing .rd alongside each
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some((cmd, paths)) = args.split_first().filter(|(v, _)| *v == "detect") {
+        if paths.is_empty() {
+            return Err(color_eyre::Report::msg(format!("usage: {} wav...", cmd)));
+        }
+        for path in paths {
+            match audio::detect::write(path) {
+                Ok(rd) => println!("{}: {} onsets", path, rd.onsets.len()),
+                Err(e) => println!("{}: {}", path, e),
+            }
+        }
+        return Ok(());
+    }
+    // import audacity label starts as onsets of wav, writing .rd alongside
+    if let Some((cmd, args)) = args.split_first().filter(|(v, _)| *v == "labels") {
+        let [wav, labels] = args else {
+            return Err(color_eyre::Report::msg(format!("usage: {} wav labels", cmd)));
+        };
+        let rd = audio::labels::write(wav, labels)?;
+        println!("{}: {} onsets", wav, rd.onsets.len());
+        return Ok(());
+    }
+    // export phrases of given scene as .mid alongside, at optional bpm
+    if let Some((cmd, args)) = args.split_first().filter(|(v, _)| *v == "export") {
+        let Some((path, tempo)) = args.split_first() else {
+            return Err(color_eyre::Report::msg(format!("usage: {} scene [bpm]", cmd)));
+        };
+        let tempo = match tempo.first() {
+            Some(v) => v.parse::<f32>()?,
+            None => audio::DEFAULT_TEMPO,
+        };
+        let path = std::path::Path::new(path);
+        let scene = audio::pads::Scene::<{audio::PAD_COUNT}>::load(path)?;
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        for (bank, kit) in [("a", &scene.kit_a), ("b", &scene.kit_b)] {
+            for (k, kit) in kit.iter().enumerate() {
+                for (p, pad) in kit.inner.iter().enumerate() {
+                    if let Some(phrase) = pad.phrase.as_ref() {
+                        let out = path.with_file_name(format!("{}-{}{}-{}.mid", stem, bank, k, p));
+                        match audio::smf::write(phrase, tempo, &out) {
+                            Ok(()) => println!("wrote {}", out.display()),
+                            Err(e) => println!("{}: {}", out.display(), e),
+                        }
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    let config = Config::load(CONFIG_PATH);
+    audio::assign_dirs(config.onsets_dir.as_deref(), config.scenes_dir.as_deref());
+
     let (input_tui_tx, input_tui_rx) = std::sync::mpsc::channel::<tui::Cmd>();
-    let (input_pads_tx, input_pads_rx) = std::sync::mpsc::channel::<audio::Cmd<{audio::PAD_COUNT}>>();
+    // run without tui until sigint
+    let headless = args.iter().any(|v| v == "--headless");
+
+    let (input_pads_tx, input_pads_rx) = audio::cmd_queue::<{audio::PAD_COUNT}>();
 
     let hosts = cpal::available_hosts();
-    let id = match hosts.len() {
-        0 => return Err(color_eyre::Report::msg("no audio host found")),
-        1 => {
-            println!("selected only available audio host: {}", hosts[0].name(),);
-            hosts[0]
-        }
-        _ => {
-            println!("available audio hosts:");
-            for (i, h) in hosts.iter().enumerate() {
-                println!("{}: {}", i, h.name())
+    let id = if let Some(query) = flag(&args, "--host").or(config.host.as_deref()) {
+        let names = hosts.iter().map(|v| v.name().to_string()).collect::<Vec<_>>();
+        hosts[preselect("audio host", &names, query)?]
+    } else {
+        match hosts.len() {
+            0 => return Err(color_eyre::Report::msg("no audio host found")),
+            1 => {
+                println!("selected only available audio host: {}", hosts[0].name(),);
+                hosts[0]
+            }
+            _ => {
+                println!("available audio hosts:");
+                for (i, h) in hosts.iter().enumerate() {
+                    println!("{}: {}", i, h.name())
+                }
+                print!("select an audio host: ");
+                std::io::stdout().flush()?;
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input)?;
+                *hosts
+                    .get(input.trim().parse::<usize>()?)
+                    .ok_or(color_eyre::Report::msg("invalid audio host selected"))?
             }
-            print!("select an audio host: ");
-            std::io::stdout().flush()?;
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input)?;
-            *hosts
-                .get(input.trim().parse::<usize>()?)
-                .ok_or(color_eyre::Report::msg("invalid audio host selected"))?
         }
     };
     let host = cpal::host_from_id(id)?;
 
     let devices = host.output_devices().into_iter().flatten().collect::<Vec<_>>();
-    let device = match devices.len() {
-        0 => return Err(color_eyre::Report::msg("no audio device found")),
-        1 => {
-            println!(
-                "\nselected only available audio device: {}",
-                devices[0].name()?,
-            );
-            devices[0].clone()
-        }
-        _ => {
-            println!("\navailable audio devices:");
-            for (i, d) in devices.iter().enumerate() {
-                println!("{}: {}", i, d.name()?)
+    let device = if let Some(query) = flag(&args, "--device").or(config.device.as_deref()) {
+        let names = devices.iter().map(|v| v.name()).collect::<Result<Vec<_>, _>>()?;
+        devices[preselect("audio device", &names, query)?].clone()
+    } else {
+        match devices.len() {
+            0 => return Err(color_eyre::Report::msg("no audio device found")),
+            1 => {
+                println!(
+                    "\nselected only available audio device: {}",
+                    devices[0].name()?,
+                );
+                devices[0].clone()
+            }
+            _ => {
+                println!("\navailable audio devices:");
+                for (i, d) in devices.iter().enumerate() {
+                    println!("{}: {}", i, d.name()?)
+                }
+                print!("select an audio device: ");
+                std::io::stdout().flush()?;
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input)?;
+                devices
+                    .get(input.trim().parse::<usize>()?)
+                    .ok_or(color_eyre::Report::msg("invalid audio device selected"))?
+                    .clone()
             }
-            print!("select an audio device: ");
-            std::io::stdout().flush()?;
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input)?;
-            devices
-                .get(input.trim().parse::<usize>()?)
-                .ok_or(color_eyre::Report::msg("invalid audio device selected"))?
-                .clone()
         }
     };
 
     let midi_in = midir::MidiInput::new("angry-surgeon")?;
     let in_ports = midi_in.ports();
-    let in_port = match in_ports.len() {
-        0 => return Err(color_eyre::Report::msg("no midi input port found")),
-        1 => {
-            println!(
-                "\nselected only available input port: {}",
-                midi_in.port_name(&in_ports[0]).unwrap()
-            );
-            &in_ports[0]
+    let in_port = if let Some(query) = flag(&args, "--midi-in").or(config.midi_in.as_deref()) {
+        let names = in_ports.iter().map(|v| midi_in.port_name(v)).collect::<Result<Vec<_>, _>>()?;
+        &in_ports[preselect("input port", &names, query)?]
+    } else {
+        match in_ports.len() {
+            0 => return Err(color_eyre::Report::msg("no midi input port found")),
+            1 => {
+                println!(
+                    "\nselected only available input port: {}",
+                    midi_in.port_name(&in_ports[0]).unwrap()
+                );
+                &in_ports[0]
+            }
+            _ => {
+                println!("\navailable input ports:");
+                for (i, p) in in_ports.iter().enumerate() {
+                    println!("{}: {}", i, midi_in.port_name(p).unwrap());
+                }
+                print!("select an input port: ");
+                std::io::stdout().flush()?;
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input)?;
+                in_ports
+                    .get(input.trim().parse::<usize>()?)
+                    .ok_or(color_eyre::Report::msg("invalid input port selected"))?
+            }
         }
-        _ => {
-            println!("\navailable input ports:");
-            for (i, p) in in_ports.iter().enumerate() {
-                println!("{}: {}", i, midi_in.port_name(p).unwrap());
+    };
+    let midi_out = midir::MidiOutput::new("angry-surgeon")?;
+    let out_ports = midi_out.ports();
+    let out_port = if let Some(query) = flag(&args, "--midi-out").or(config.midi_out.as_deref()) {
+        let names = out_ports.iter().map(|v| midi_out.port_name(v)).collect::<Result<Vec<_>, _>>()?;
+        Some(&out_ports[preselect("output port", &names, query)?])
+    } else {
+        match out_ports.len() {
+            0 => {
+                println!("\nno midi output port found; not sending clock");
+                None
+            }
+            1 => {
+                println!(
+                    "\nselected only available output port: {}",
+                    midi_out.port_name(&out_ports[0]).unwrap()
+                );
+                Some(&out_ports[0])
             }
-            print!("select an input port: ");
+            _ => {
+                println!("\navailable output ports:");
+                for (i, p) in out_ports.iter().enumerate() {
+                    println!("{}: {}", i, midi_out.port_name(p).unwrap());
+                }
+                print!("select an output port: ");
+                std::io::stdout().flush()?;
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input)?;
+                Some(out_ports
+                    .get(input.trim().parse::<usize>()?)
+                    .ok_or(color_eyre::Report::msg("invalid output port selected"))?)
+            }
+        }
+    };
+    let clock_out = match out_port {
+        Some(port) => Some(midi_out
+            .connect(port, "angry-surgeon")
+            .map_err(|_| color_eyre::Report::msg("failed to connect midi output"))?),
+        None => None,
+    };
+
+    // offer to restore scene autosaved on last exit; restore unasked if headless
+    let mut restored = None;
+    if audio::autosave_path().exists() {
+        let restore = headless || {
+            print!("\nrestore autosaved scene? [y/N]: ");
             std::io::stdout().flush()?;
             let mut input = String::new();
             std::io::stdin().read_line(&mut input)?;
-            in_ports
-                .get(input.trim().parse::<usize>()?)
-                .ok_or(color_eyre::Report::msg("invalid input port selected"))?
+            input.trim().eq_ignore_ascii_case("y")
+        };
+        if restore {
+            // corrupt autosave starts empty rather than exiting
+            match audio::pads::Scene::<{audio::PAD_COUNT}>::load(audio::autosave_path()) {
+                Ok(mut scene) => {
+                    for path in scene.validate() {
+                        println!("dropped missing: {}", path.display());
+                    }
+                    restored = Some(scene);
+                }
+                Err(e) => println!("malformed {}: {}", audio::autosave_path().display(), e),
+            }
         }
-    };
-    let input_handler = input::InputHandler::new(input_tui_tx, input_pads_tx)?;
+    }
+
+    // preload scene slots; unloadable slots stay empty, keeping later slots' indices
+    let slots = config.slots.iter().take(audio::SCENE_SLOTS).map(|v| {
+        let path = audio::scenes_dir().join(v);
+        match audio::pads::Scene::<{audio::PAD_COUNT}>::load(&path) {
+            Ok(mut scene) => {
+                for path in scene.validate() {
+                    println!("dropped missing: {}", path.display());
+                }
+                Some(scene)
+            }
+            Err(e) => {
+                println!("empty slot {}: {}", path.display(), e);
+                None
+            }
+        }
+    }).collect::<Vec<_>>();
+
+    let audio_tui_tx = input_tui_tx.clone();
+    let stream_tui_tx = input_tui_tx.clone();
+    let midi_tui_tx = input_tui_tx.clone();
+    let osc_tui_tx = input_tui_tx.clone();
+    let link_tui_tx = input_tui_tx.clone();
+    let pll_tui_tx = input_tui_tx.clone();
+    // shared with tui for mouse input
+    let input_handler = std::sync::Arc::new(std::sync::Mutex::new(input::InputHandler::new(input_tui_tx, input_pads_tx)?));
+    input_handler.lock().unwrap().assign_defaults(config.speed, config.width, config.tempo)?;
+    if let Some(v) = flag(&args, "--ppq").map(|v| v.parse::<u8>()).transpose()?.or(config.ppq) {
+        input_handler.lock().unwrap().assign_ppq(v)?;
+    }
+    input_handler.lock().unwrap().assign_slots(slots);
+    if let Some(scene) = restored {
+        input_handler.lock().unwrap().load_scene(scene)?;
+    }
+    if let Some(addr) = config.osc.as_deref() {
+        osc::listen(addr, input_handler.clone(), osc_tui_tx)?;
+    }
+    if config.link {
+        link::listen(input_handler.clone(), link_tui_tx)?;
+    }
+    if config.pll {
+        input_handler.lock().unwrap().assign_pll(true);
+        pll::run(input_handler.clone(), pll_tui_tx);
+    }
+    let tui_input_handler = input_handler.clone();
+    let main_input_handler = input_handler.clone();
     let midi_in = midi_in
         .connect(
             in_port,
             "angry-surgeon",
-            move |_, message, input_handler: &mut input::InputHandler| {
-                input_handler.push(message).unwrap();
+            move |_, message, input_handler: &mut std::sync::Arc<std::sync::Mutex<input::InputHandler>>| {
+                if let Err(e) = input_handler.lock().unwrap().push(message) {
+                    midi_tui_tx.send(tui::Cmd::Error(format!("input error: {}", e))).ok();
+                }
             },
             input_handler,
         )
@@ -105,49 +284,292 @@ fn main() -> Result<()> {
     println!("\nplease make some noise <3");
     std::thread::sleep(std::time::Duration::from_millis(1000));
 
+    let exit = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let audio_exit = exit.clone();
+    let loop_fade = config.loop_fade;
     let audio_handle = std::thread::spawn(move || -> Result<()> {
-        let config = device.default_output_config().unwrap();
-        let handler = audio::pads::AudioHandler::<{audio::PAD_COUNT}>::new(input_pads_rx);
-
-        match config.sample_format() {
-            cpal::SampleFormat::I16 => play::<i16>(&device, &config.into(), handler)?,
-            cpal::SampleFormat::F32 => play::<f32>(&device, &config.into(), handler)?,
-            sample_format => panic!("unsupported sample format: {}", sample_format),
+        let sample_rate = device.default_output_config()?.sample_rate().0;
+        // outlives streams, keeping scene across device loss
+        let mut handler = audio::pads::AudioHandler::<{audio::PAD_COUNT}>::new(input_pads_rx, audio_tui_tx, clock_out, sample_rate);
+        if let Some(v) = loop_fade {
+            handler.assign_loop_fade(v);
+        }
+        let handler = std::sync::Arc::new(std::sync::Mutex::new(handler));
+        let name = device.name().ok();
+        let mut device = device;
+        loop {
+            if let Err(e) = play(&device, handler.clone(), stream_tui_tx.clone(), &audio_exit) {
+                stream_tui_tx.send(tui::Cmd::Error(format!("stream error: {}", e))).ok();
+            }
+            // rebuild on same device once back, else host default
+            loop {
+                if audio_exit.load(std::sync::atomic::Ordering::Relaxed) {
+                    return Ok(());
+                }
+                std::thread::sleep(audio::RECONNECT_INTERVAL);
+                let host = cpal::host_from_id(id)?;
+                let same = host.output_devices().into_iter().flatten().find(|v| v.name().ok() == name);
+                if let Some(v) = same.or_else(|| host.default_output_device()) {
+                    stream_tui_tx.send(tui::Cmd::Error(format!("rebuilding stream on {}", v.name()?))).ok();
+                    device = v;
+                    break;
+                }
+            }
         }
-        Ok(())
     });
 
-    let mut terminal = ratatui::init();
-    tui::TuiHandler::default().run(&mut terminal, input_tui_rx)?;
-    ratatui::restore();
+    if headless {
+        // log errors to stderr, dropping other tui commands as sent
+        std::thread::spawn(move || {
+            for cmd in input_tui_rx.iter() {
+                if let tui::Cmd::Error(e) = cmd {
+                    eprintln!("{}", e);
+                }
+            }
+        });
+        let exit = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGINT, exit.clone())?;
+        println!("running headless; interrupt to exit");
+        while !exit.load(std::sync::atomic::Ordering::Relaxed) {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+    } else {
+        let mut terminal = ratatui::init();
+        crossterm::execute!(std::io::stdout(), crossterm::event::EnableMouseCapture)?;
+        tui::TuiHandler::new(tui_input_handler).run(&mut terminal, input_tui_rx)?;
+        crossterm::execute!(std::io::stdout(), crossterm::event::DisableMouseCapture)?;
+        ratatui::restore();
+    }
+
+    // autosave active scene while audio thread still running, replacing last autosave only once written
+    let (done_tx, done_rx) = std::sync::mpsc::channel();
+    let temp = audio::autosave_path().with_extension("sd.tmp");
+    match std::fs::create_dir_all(audio::scenes_dir()).and_then(|_| std::fs::File::create(&temp)) {
+        Ok(file) => {
+            let sent = main_input_handler.lock().unwrap().autosave(file, done_tx).is_ok();
+            if !sent || done_rx.recv_timeout(std::time::Duration::from_millis(1000)).is_err() {
+                println!("failed to autosave scene");
+                std::fs::remove_file(&temp).ok();
+            } else if let Err(e) = std::fs::rename(&temp, audio::autosave_path()) {
+                println!("failed to autosave scene: {}", e);
+            }
+        }
+        Err(e) => println!("failed to autosave scene: {}", e),
+    }
 
     // pads thread completes once audio_tx held by input_handler dropped in _in_connection thread
     std::mem::drop(midi_in);
 
+    exit.store(true, std::sync::atomic::Ordering::Relaxed);
     audio_handle.thread().unpark();
     audio_handle.join().unwrap()?;
 
     Ok(())
 }
 
-fn play<T>(
+/// startup defaults read from cwd, overridden by flags
+const CONFIG_PATH: &str = "config.toml";
+
+#[derive(Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    host: Option<String>,
+    device: Option<String>,
+    midi_in: Option<String>,
+    midi_out: Option<String>,
+    onsets_dir: Option<std::path::PathBuf>,
+    scenes_dir: Option<std::path::PathBuf>,
+    /// scenes preloaded for recall, relative to scenes dir
+    slots: Vec<std::path::PathBuf>,
+    /// udp address to listen for osc on, if any
+    osc: Option<String>,
+    /// join ableton link session, clocking from its tempo and phase until toggled to midi
+    link: bool,
+    /// 0..=127, as from controller
+    speed: Option<u8>,
+    width: Option<u8>,
+    /// internal clock bpm
+    tempo: Option<f32>,
+    /// incoming midi clocks per quarter, if not 24
+    ppq: Option<u8>,
+    /// step on phase-locked schedule of midi clock, filtering jitter
+    pll: bool,
+    /// crossfade between grains and across loop wraps in frames, if not LOOP_FADE
+    loop_fade: Option<usize>,
+}
+
+impl Config {
+    /// missing file is all defaults; malformed file warned of, then all defaults
+    fn load(path: &str) -> Self {
+        let text = match std::fs::read_to_string(path) {
+            Ok(v) => v,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                println!("ignoring {}: {}", path, e);
+                return Self::default();
+            }
+        };
+        toml::from_str(&text).unwrap_or_else(|e| {
+            println!("ignoring {}: {}", path, e);
+            Self::default()
+        })
+    }
+}
+
+/// value following flag in args, if any
+fn flag<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|v| v == name).and_then(|i| args.get(i + 1)).map(|v| v.as_str())
+}
+
+/// index of option matching query by index or name, else error listing options
+fn preselect(kind: &str, names: &[String], query: &str) -> Result<usize> {
+    let index = query.parse::<usize>().ok().filter(|v| *v < names.len())
+        .or_else(|| names.iter().position(|v| v == query));
+    if let Some(index) = index {
+        println!("selected {}: {}", kind, names[index]);
+        return Ok(index);
+    }
+    let options = names.iter().enumerate().map(|(i, v)| format!("\n{}: {}", i, v)).collect::<String>();
+    Err(color_eyre::Report::msg(format!("no {} `{}`; available:{}", kind, query, options)))
+}
+
+type Handler = std::sync::Arc<std::sync::Mutex<audio::pads::AudioHandler<{audio::PAD_COUNT}>>>;
+
+/// run stream on device until exit, else error once device lost
+fn play(
+    device: &cpal::Device,
+    handler: Handler,
+    tui_tx: std::sync::mpsc::Sender<tui::Cmd>,
+    exit: &std::sync::atomic::AtomicBool,
+) -> Result<()> {
+    let config = device.default_output_config()?;
+    handler.lock().unwrap().assign_sample_rate(config.sample_rate().0);
+
+    match config.sample_format() {
+        cpal::SampleFormat::I16 => run::<i16>(device, &config.into(), handler, tui_tx, exit),
+        cpal::SampleFormat::F32 => run::<f32>(device, &config.into(), handler, tui_tx, exit),
+        sample_format => Err(color_eyre::Report::msg(format!("unsupported sample format: {}", sample_format))),
+    }
+}
+
+fn run<T>(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
-    mut handler: audio::pads::AudioHandler<{audio::PAD_COUNT}>,
+    handler: Handler,
+    tui_tx: std::sync::mpsc::Sender<tui::Cmd>,
+    exit: &std::sync::atomic::AtomicBool,
 ) -> Result<()>
 where
     T: SizedSample + FromSample<f32>,
 {
     let channels = config.channels as usize;
 
-    let out_fn = move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
-        handler.tick(data, channels).unwrap();
+    let out_fn = move |data: &mut [T], _: &cpal::OutputCallbackInfo| match handler.try_lock() {
+        Ok(mut v) => v.tick(data, channels),
+        // only contended while stream rebuilt
+        Err(_) => data.fill(T::EQUILIBRIUM),
     };
-    let err_fn = |_| {};
+    let lost = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let err_lost = lost.clone();
+    let thread = std::thread::current();
+    let err_fn = move |e| stream_error(e, &err_lost, &thread, &tui_tx);
     let stream = device.build_output_stream(config, out_fn, err_fn, None)?;
 
     stream.play()?;
-    std::thread::park();
+    park_until(exit, &lost)
+}
 
+/// flag device loss and wake stream thread, else log error to tui
+fn stream_error(
+    e: cpal::StreamError,
+    lost: &std::sync::atomic::AtomicBool,
+    thread: &std::thread::Thread,
+    tui_tx: &std::sync::mpsc::Sender<tui::Cmd>,
+) {
+    if let cpal::StreamError::DeviceNotAvailable = e {
+        lost.store(true, std::sync::atomic::Ordering::Relaxed);
+        thread.unpark();
+    } else {
+        tui_tx.send(tui::Cmd::Error(format!("stream error: {}", e))).ok();
+    }
+}
+
+/// park until exit, else error once device lost
+fn park_until(exit: &std::sync::atomic::AtomicBool, lost: &std::sync::atomic::AtomicBool) -> Result<()> {
+    // park may wake spuriously
+    while !exit.load(std::sync::atomic::Ordering::Relaxed) {
+        if lost.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err(color_eyre::Report::msg("audio device lost"));
+        }
+        std::thread::park();
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_config_parses() {
+        let config: Config = toml::from_str(r#"
+            host = "ALSA"
+            device = "pulse"
+            midi_in = "launchpad"
+            onsets_dir = "/srv/onsets"
+            speed = 80
+            width = 100
+            tempo = 96.5
+        "#).unwrap();
+        assert_eq!(config.host.as_deref(), Some("ALSA"));
+        assert_eq!(config.device.as_deref(), Some("pulse"));
+        assert_eq!(config.midi_in.as_deref(), Some("launchpad"));
+        assert_eq!(config.midi_out, None);
+        assert_eq!(config.onsets_dir.as_deref(), Some(std::path::Path::new("/srv/onsets")));
+        assert_eq!(config.scenes_dir, None);
+        assert_eq!((config.speed, config.width, config.tempo), (Some(80), Some(100), Some(96.5)));
+    }
+
+    #[test]
+    fn lost_device_ends_stream_for_rebuild() {
+        use std::sync::{atomic::AtomicBool, Arc};
+        let (tui_tx, tui_rx) = std::sync::mpsc::channel();
+        let (exit, lost) = (Arc::new(AtomicBool::new(false)), Arc::new(AtomicBool::new(false)));
+        let stream = {
+            let (exit, lost) = (exit.clone(), lost.clone());
+            std::thread::spawn(move || park_until(&exit, &lost))
+        };
+        // other errors logged, stream kept
+        let err = cpal::BackendSpecificError { description: "xrun".to_string() };
+        stream_error(cpal::StreamError::BackendSpecific { err }, &lost, stream.thread(), &tui_tx);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!stream.is_finished());
+        assert!(tui_rx.try_iter().any(|v| matches!(v, tui::Cmd::Error(e) if e.starts_with("stream error") && e.contains("xrun"))));
+        // lost device wakes stream to error, handing back to rebuild loop
+        stream_error(cpal::StreamError::DeviceNotAvailable, &lost, stream.thread(), &tui_tx);
+        let e = stream.join().unwrap().unwrap_err();
+        assert_eq!(e.to_string(), "audio device lost");
+        // rebuilt stream parks until exit
+        lost.store(false, std::sync::atomic::Ordering::Relaxed);
+        let stream = {
+            let (exit, lost) = (exit.clone(), lost.clone());
+            std::thread::spawn(move || park_until(&exit, &lost))
+        };
+        exit.store(true, std::sync::atomic::Ordering::Relaxed);
+        stream.thread().unpark();
+        assert!(stream.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn missing_or_malformed_config_defaults() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        assert!(Config::load(path.to_str().unwrap()).host.is_none());
+        // unknown keys and bad values rejected whole
+        for text in ["hots = \"ALSA\"", "host = \"ALSA\"\nspeed = 300"] {
+            std::fs::write(&path, text).unwrap();
+            assert!(Config::load(path.to_str().unwrap()).host.is_none());
+        }
+        std::fs::remove_file(&path).ok();
+    }
+}
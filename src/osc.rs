@@ -0,0 +1,211 @@
+use crate::{input::{Bank, InputHandler}, tui, PAD_COUNT};
+use std::{
+    net::UdpSocket,
+    sync::{mpsc::Sender, Arc, Mutex},
+};
+use color_eyre::Result;
+
+pub enum Arg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+}
+
+impl Arg {
+    fn as_f32(&self) -> Option<f32> {
+        match self {
+            Self::Int(v) => Some(*v as f32),
+            Self::Float(v) => Some(*v),
+            Self::Str(_) => None,
+        }
+    }
+}
+
+pub struct Message {
+    pub addr: String,
+    pub args: Vec<Arg>,
+}
+
+fn invalid(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// null-terminated string padded to 4 bytes, advancing past it
+fn read_str(bytes: &mut &[u8]) -> Result<String, std::io::Error> {
+    let len = bytes.iter().position(|v| *v == 0).ok_or_else(|| invalid("unterminated osc string"))?;
+    let s = std::str::from_utf8(&bytes[..len]).map_err(|_| invalid("non-utf8 osc string"))?.to_string();
+    *bytes = bytes.get((len + 4) & !3..).ok_or_else(|| invalid("unpadded osc string"))?;
+    Ok(s)
+}
+
+/// big-endian word, advancing past it
+fn read_word(bytes: &mut &[u8]) -> Result<[u8; 4], std::io::Error> {
+    let word = bytes.get(..4).ok_or_else(|| invalid("truncated osc argument"))?;
+    let word = [word[0], word[1], word[2], word[3]];
+    *bytes = &bytes[4..];
+    Ok(word)
+}
+
+/// messages of packet, flattening bundles; unsupported argument types end a message's args
+pub fn decode(mut bytes: &[u8]) -> Result<Vec<Message>, std::io::Error> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(invalid("osc packet not word aligned"));
+    }
+    if bytes.starts_with(b"#bundle\0") {
+        // skip time tag; timed delivery unsupported, so elements apply at once
+        bytes = bytes.get(16..).ok_or_else(|| invalid("truncated osc bundle"))?;
+        let mut messages = Vec::new();
+        while !bytes.is_empty() {
+            let len = u32::from_be_bytes(read_word(&mut bytes)?) as usize;
+            let element = bytes.get(..len).ok_or_else(|| invalid("truncated osc bundle element"))?;
+            messages.extend(decode(element)?);
+            bytes = &bytes[len..];
+        }
+        return Ok(messages);
+    }
+    let addr = read_str(&mut bytes)?;
+    let tags = if bytes.is_empty() { String::new() } else { read_str(&mut bytes)? };
+    let mut args = Vec::new();
+    for tag in tags.chars().skip(1) {
+        args.push(match tag {
+            'i' => Arg::Int(i32::from_be_bytes(read_word(&mut bytes)?)),
+            'f' => Arg::Float(f32::from_be_bytes(read_word(&mut bytes)?)),
+            's' => Arg::Str(read_str(&mut bytes)?),
+            'T' => Arg::Int(1),
+            'F' => Arg::Int(0),
+            _ => break,
+        });
+    }
+    Ok(vec![Message { addr, args }])
+}
+
+/// apply message as the midi path would; false if address unknown
+///
+/// /pad/{a,b}/{1..} n: press pad while n is nonzero
+/// /bank/{a,b}/{ctrl} f: bank's ctrl, e.g. speed, phrase_drift, in [0, 1]
+/// /ctrl/{ctrl} f: global ctrl, e.g. swing, grain_len, in [0, 1]
+/// /key/{key} n: press key while n is nonzero, e.g. global, tap
+/// /tempo f: internal clock bpm
+pub fn apply(input: &mut InputHandler, message: &Message) -> Result<bool> {
+    let value = message.args.first().and_then(Arg::as_f32).unwrap_or(1.);
+    let parts = message.addr.split('/').skip(1).collect::<Vec<_>>();
+    let bank = |v: &str| match v {
+        "a" => Some(Bank::A),
+        "b" => Some(Bank::B),
+        _ => None,
+    };
+    match parts[..] {
+        ["pad", b, n] => {
+            let (Some(bank), Ok(n)) = (bank(b), n.parse::<u8>()) else {
+                return Ok(false);
+            };
+            if !(1..=PAD_COUNT as u8).contains(&n) {
+                return Ok(false);
+            }
+            input.push_pad(bank, n - 1, value != 0.)?;
+            Ok(true)
+        }
+        ["bank", b, ctrl] if bank(b).is_some() => input.push_ctrl(&format!("{}{}", ctrl, b), value),
+        ["ctrl", ctrl] => input.push_ctrl(ctrl, value),
+        ["key", key] => input.push_key(key, value != 0.),
+        ["tempo"] => {
+            input.assign_defaults(None, None, Some(value))?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// listen on udp address, applying messages to input until exit
+pub fn listen(addr: &str, input: Arc<Mutex<InputHandler>>, tui_tx: Sender<tui::Cmd>) -> Result<()> {
+    let socket = UdpSocket::bind(addr)?;
+    std::thread::spawn(move || {
+        let mut buf = vec![0u8; u16::MAX as usize];
+        loop {
+            let Ok((len, _)) = socket.recv_from(&mut buf) else {
+                continue;
+            };
+            let messages = match decode(&buf[..len]) {
+                Ok(v) => v,
+                Err(e) => {
+                    tui_tx.send(tui::Cmd::Error(format!("osc error: {}", e))).ok();
+                    continue;
+                }
+            };
+            for message in messages {
+                match apply(&mut input.lock().unwrap(), &message) {
+                    Ok(true) => (),
+                    Ok(false) => {
+                        tui_tx.send(tui::Cmd::Error(format!("unknown osc address `{}`", message.addr))).ok();
+                    }
+                    Err(e) => {
+                        tui_tx.send(tui::Cmd::Error(format!("osc error: {}", e))).ok();
+                    }
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// osc string, null-terminated and padded to 4 bytes
+    fn string(s: &str) -> Vec<u8> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize((s.len() + 4) & !3, 0);
+        bytes
+    }
+
+    /// message of address and float args
+    fn packet(addr: &str, args: &[f32]) -> Vec<u8> {
+        let mut bytes = string(addr);
+        bytes.extend(string(&format!(",{}", "f".repeat(args.len()))));
+        bytes.extend(args.iter().flat_map(|v| v.to_be_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn decodes_messages_and_bundles() {
+        let messages = decode(&packet("/bank/a/speed", &[0.5])).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].addr, "/bank/a/speed");
+        assert!(matches!(messages[0].args[..], [Arg::Float(v)] if v == 0.5));
+        // bundle of a pad press and a tempo
+        let mut pad = string("/pad/b/3");
+        pad.extend(string(",i"));
+        pad.extend(1i32.to_be_bytes());
+        let tempo = packet("/tempo", &[120.]);
+        let mut bundle = string("#bundle");
+        bundle.extend([0; 8]);
+        for element in [&pad, &tempo] {
+            bundle.extend((element.len() as u32).to_be_bytes());
+            bundle.extend(element);
+        }
+        let messages = decode(&bundle).unwrap();
+        assert_eq!(messages.iter().map(|v| v.addr.as_str()).collect::<Vec<_>>(), ["/pad/b/3", "/tempo"]);
+        assert!(matches!(messages[0].args[..], [Arg::Int(1)]));
+        // unaligned and unterminated packets refused
+        assert!(decode(b"/tempo").is_err());
+        assert!(decode(b"/tem").is_err());
+    }
+
+    #[test]
+    fn applies_as_midi_path_and_refuses_unknown() {
+        let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+        let (pads_tx, mut pads_rx) = crate::audio::cmd_queue();
+        let mut input = InputHandler::new(tui_tx, pads_tx).unwrap();
+        let message = decode(&packet("/bank/a/speed", &[0.5])).unwrap().remove(0);
+        assert!(apply(&mut input, &message).unwrap());
+        let cmds = std::iter::from_fn(|| pads_rx.try_recv()).collect::<Vec<_>>();
+        assert!(cmds.iter().any(|v| matches!(v, crate::audio::Cmd::Bank(Bank::A, crate::audio::BankCmd::AssignSpeed(_)))));
+        // no blend ctrl to map to
+        for addr in ["/blend", "/bank/c/speed", "/bank/a/blend", "/pad/a/0"] {
+            let message = decode(&packet(addr, &[0.5])).unwrap().remove(0);
+            assert!(!apply(&mut input, &message).unwrap(), "{addr}");
+        }
+        assert!(pads_rx.try_recv().is_none());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{input::InputHandler, tui};
+use std::sync::{mpsc::Sender, Arc, Mutex};
+
+/// phase correction per step, as fraction of error
+pub const ALPHA: f64 = 0.1;
+/// period correction per step, as fraction of error
+pub const BETA: f64 = 0.005;
+/// error beyond this fraction of a step resyncs to raw clock
+pub const TOLERANCE: f64 = 0.25;
+/// wait between checks for due steps
+pub const POLL: std::time::Duration = std::time::Duration::from_millis(1);
+
+/// steps on smoothed period and phase of received clock steps, leading them by at most one
+pub struct Pll {
+    epoch: std::time::Instant,
+    /// seconds per step, once two steps received
+    period: Option<f64>,
+    /// due time of next step emitted
+    next: f64,
+    /// time of last step received
+    last: Option<f64>,
+    received: u64,
+    emitted: u64,
+}
+
+impl Pll {
+    pub fn new() -> Self {
+        Self {
+            epoch: std::time::Instant::now(),
+            period: None,
+            next: f64::INFINITY,
+            last: None,
+            received: 0,
+            emitted: 0,
+        }
+    }
+
+    /// seconds since created, as taken by `reference` and `tick`
+    pub fn now(&self) -> f64 {
+        self.epoch.elapsed().as_secs_f64()
+    }
+
+    /// unlock, as on transport change; next step received is emitted at once
+    pub fn reset(&mut self) {
+        *self = Self { epoch: self.epoch, ..Self::new() };
+    }
+
+    /// step received at time `t`
+    pub fn reference(&mut self, t: f64) {
+        let index = self.received;
+        self.received += 1;
+        let ioi = self.last.map(|v| t - v);
+        self.last = Some(t);
+        match (self.period, ioi) {
+            (Some(period), Some(ioi)) => {
+                // due time of this step, emitted or not
+                let due = self.next - (self.emitted as f64 - index as f64) * period;
+                let error = t - due;
+                if error.abs() > TOLERANCE * period {
+                    self.resync(t, ioi, index);
+                } else {
+                    self.next += ALPHA * error;
+                    self.period = Some(period + BETA * error);
+                }
+            }
+            (_, Some(ioi)) => self.resync(t, ioi, index),
+            // first step; emit at once
+            (_, None) => self.next = t,
+        }
+    }
+
+    /// due step `index` at `t`, steps behind it overdue
+    fn resync(&mut self, t: f64, period: f64, index: u64) {
+        self.period = Some(period);
+        self.next = t + (self.emitted as f64 - index as f64) * period;
+    }
+
+    /// steps due by `now`
+    pub fn tick(&mut self, now: f64) -> u32 {
+        let mut steps = 0;
+        while self.emitted <= self.received && now >= self.next {
+            self.emitted += 1;
+            steps += 1;
+            self.next = self.period.map_or(f64::INFINITY, |v| self.next + v);
+        }
+        steps
+    }
+}
+
+impl Default for Pll {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// emit due steps of input's pll until exit
+pub fn run(input: Arc<Mutex<InputHandler>>, tui_tx: Sender<tui::Cmd>) {
+    std::thread::spawn(move || loop {
+        if let Err(e) = input.lock().unwrap().pll_tick() {
+            tui_tx.send(tui::Cmd::Error(format!("pll error: {}", e))).ok();
+        }
+        std::thread::sleep(POLL);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// times steps emitted, driven every 0.5ms by steps received at `times`
+    fn emitted(times: &[f64]) -> Vec<f64> {
+        let mut pll = Pll::new();
+        let mut received = times.iter().peekable();
+        let mut out = Vec::new();
+        let end = times.last().unwrap() + 0.25;
+        let mut now = 0.;
+        while now < end {
+            while let Some(t) = received.next_if(|v| **v <= now) {
+                pll.reference(*t);
+            }
+            for _ in 0..pll.tick(now) {
+                out.push(now);
+            }
+            now += 0.0005;
+        }
+        out
+    }
+
+    fn intervals(times: &[f64]) -> Vec<f64> {
+        times.windows(2).map(|v| v[1] - v[0]).collect()
+    }
+
+    /// 120 bpm steps from 0.1s, each pushed up to 10ms either way
+    fn jittered(count: usize) -> Vec<f64> {
+        let mut seed = 1u32;
+        (0..count)
+            .map(|i| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                0.1 + i as f64 * 0.125 + ((seed >> 8) as f64 / (1 << 24) as f64 * 2. - 1.) * 0.01
+            })
+            .collect()
+    }
+
+    #[test]
+    fn jittered_steps_emitted_near_constant() {
+        let times = jittered(128);
+        let out = emitted(&times);
+        // first step at once, then leading received by at most one
+        assert!(out[0] - times[0] < 0.001);
+        assert!((times.len()..=times.len() + 1).contains(&out.len()));
+        let raw = intervals(&times).iter().fold(0., |max: f64, v| max.max((v - 0.125).abs()));
+        let locked = intervals(&out)[64..].iter().fold(0., |max: f64, v| max.max((v - 0.125).abs()));
+        assert!(raw > 0.01);
+        assert!(locked < 0.002, "{locked}");
+    }
+
+    #[test]
+    fn tracks_tempo_change() {
+        let steady = (0..64).map(|i| 0.1 + i as f64 * 0.125);
+        let faster = (0..64).map(|i| 0.1 + 64. * 0.125 + i as f64 * 0.12);
+        let out = emitted(&steady.chain(faster).collect::<Vec<_>>());
+        let intervals = intervals(&out);
+        assert!(intervals[..64].iter().all(|v| (v - 0.125).abs() < 0.001));
+        assert!(intervals[intervals.len() - 32..].iter().all(|v| (v - 0.12).abs() < 0.001));
+    }
+
+    #[test]
+    fn reset_emits_next_step_at_once() {
+        let mut pll = Pll::new();
+        for i in 0..8 {
+            pll.reference(i as f64 * 0.125);
+            pll.tick(i as f64 * 0.125);
+        }
+        pll.reset();
+        assert_eq!(pll.tick(10.), 0);
+        pll.reference(10.);
+        assert_eq!(pll.tick(10.), 1);
+        // period unknown until second step
+        assert_eq!(pll.tick(20.), 0);
+    }
+}
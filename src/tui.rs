@@ -1,24 +1,57 @@
-use crate::audio::PAD_COUNT;
-use crate::input::Bank;
+use crate::audio::{self, PAD_COUNT};
+use crate::input::{Bank, InputHandler};
 
 use color_eyre::eyre::Result;
-use crossterm::event::{self, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{self, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
+use std::{cell::RefCell, sync::{Arc, Mutex}};
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Flex, Layout, Rect},
-    style::Stylize,
-    text::{Line, Text},
-    widgets::{Block, Padding, Paragraph, Widget, Wrap},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Clear, Padding, Paragraph, Widget, Wrap},
     DefaultTerminal, Frame,
 };
 
 pub const FILE_COUNT: usize = 5;
+/// level meter floor in db
+pub const LEVEL_FLOOR: f32 = -48.;
+/// held peak fall per level update
+pub const LEVEL_DECAY: f32 = 0.02;
+/// waveform preview resolution over whole file
+pub const PEAK_BINS: usize = 512;
 pub const LOG_DURATION: std::time::Duration = std::time::Duration::from_millis(1000);
+/// smallest terminal drawn in full
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = FILE_COUNT as u16 + 5;
+/// error pane height in rows, including border
+pub const ERROR_PANE_LEN: u16 = 8;
+pub const LEARN_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(10000);
+/// step grid cells per row
+pub const GRID_ROW: u16 = 16;
+/// beats per bar in displayed lengths
+pub const BAR_BEATS: u16 = 4;
+
+/// steps as whole bars, else whole beats, else steps
+pub fn musical_len(steps: u16) -> String {
+    let beat = crate::audio::STEP_DIV as u16;
+    let (n, unit) = if steps > 0 && steps.is_multiple_of(beat * BAR_BEATS) {
+        (steps / (beat * BAR_BEATS), "bar")
+    } else if steps > 0 && steps.is_multiple_of(beat) {
+        (steps / beat, "beat")
+    } else {
+        (steps, "step")
+    };
+    format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" })
+}
 
 macro_rules! down {
-    ($char:expr) => {
+    ($char:literal) => {
+        down!(KeyCode::Char($char))
+    };
+    ($code:pat) => {
         event::Event::Key(KeyEvent {
-            code: KeyCode::Char($char),
+            code: $code,
             kind: KeyEventKind::Press,
             ..
         })
@@ -28,12 +61,37 @@ macro_rules! down {
 pub enum Cmd {
     Clock,
     Stop,
+    /// bank a and b peak magnitudes
+    Levels(f32, f32),
+    /// pad index and alt of bank's sounding onset, if any
+    Playing(Bank, Option<(u8, bool)>),
+    /// bank's song section and count, and section pass and repeats, if playing
+    Song(Bank, Option<(usize, usize, u16, u16)>),
+    /// steps until bank's armed record captures, if counting in
+    CountIn(Bank, Option<u16>),
+    Tempo(f32),
+    /// scene slot last recalled, if any
+    AssignSlot(Option<u8>),
     Yield,
     AssignScene(Box<Scene>),
+    /// mirror of audio kit slot copy into other bank
+    CopyKit(Bank, u8),
+    /// mirror of kit file stored into bank's slot
+    StoreKit(Bank, u8, [Pad; PAD_COUNT]),
+    SwapBanks,
+    /// prompt for scene name, starting from given
+    NameScene(String),
     SaveScene(String),
-    LoadScene([String; FILE_COUNT]),
-    LoadWav([String; FILE_COUNT]),
-    AssignOnset { name: String, index: usize, count: usize, alt: bool },
+    /// transient message in log line
+    Log(String),
+    /// failure kept in error pane until cleared
+    Error(String),
+    LoadScene(Listing),
+    LoadWav(Listing),
+    AssignOnset { name: String, index: usize, count: usize, alt: bool, pos: f32 },
+    /// waveform preview of onset selection, if file readable
+    AssignPeaks(Option<std::sync::Arc<[f32]>>),
+    Learn(String),
     Bank(Bank, BankCmd),
 }
 
@@ -41,21 +99,47 @@ pub enum BankCmd {
     Pad(u8, bool),
     LoadOnset,
     AssignDrift(u8),
+    AssignPhraseDrift(u8),
     AssignBias(u8),
+    AssignSpeed(u8),
+    AssignWidth(u8),
+    Reverse(bool),
+    Hold(bool),
     AssignKit(Option<u8>),
     LoadKit(Option<u8>),
     BakeRecord(Option<u8>, u16),
     BuildPool,
     ClearPool,
+    ClearPhrase(u8),
+    AssignPhrase(u8),
+}
+
+/// window of browsed names centered on cursor
+pub struct Listing {
+    pub names: [String; FILE_COUNT],
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Listing {
+    /// one-based cursor position over total, e.g. " 12/340 "
+    fn position(&self) -> Line<'static> {
+        if self.count == 0 {
+            Line::raw("")
+        } else {
+            Line::raw(format!(" {}/{} ", self.index + 1, self.count))
+        }
+    }
 }
 
 #[derive(Default)]
 enum GlobalState {
     #[default]
     Yield,
-    LoadScene { paths: [String; FILE_COUNT] },
-    LoadWav { paths: [String; FILE_COUNT] },
-    AssignOnset { name: String, index: usize, count: usize, alt: bool },
+    LoadScene { listing: Listing },
+    LoadWav { listing: Listing },
+    AssignOnset { name: String, index: usize, count: usize, alt: bool, pos: f32 },
+    Learn { name: String, since: std::time::Instant },
 }
 
 #[derive(Default)]
@@ -68,6 +152,87 @@ enum BankState {
     BuildPool,
 }
 
+#[derive(Copy, Clone, PartialEq)]
+enum Step {
+    Pad(u8),
+    Rest,
+}
+
+/// phrase programmed a step at a time from the computer keyboard
+struct Grid {
+    bank: Bank,
+    /// empty steps sustain the previous
+    steps: [Option<Step>; audio::MAX_PHRASE_LEN as usize],
+    len: u16,
+    cursor: u16,
+    /// choosing pad to assign phrase to
+    assign: bool,
+    /// euclidean fill being typed as "pad pulses steps", if any
+    prompt: Option<String>,
+}
+
+impl Grid {
+    fn new(bank: Bank) -> Self {
+        Self {
+            bank,
+            steps: [None; audio::MAX_PHRASE_LEN as usize],
+            len: GRID_ROW,
+            cursor: 0,
+            assign: false,
+            prompt: None,
+        }
+    }
+
+    /// set step at cursor, clearing it if already set, then advance
+    fn toggle(&mut self, step: Step) {
+        let cell = &mut self.steps[self.cursor as usize];
+        *cell = if *cell == Some(step) { None } else { Some(step) };
+        self.cursor = (self.cursor + 1) % self.len;
+    }
+
+    /// replace steps with pad's pulses spread evenly over `len`, None if unparsed
+    fn euclid(&mut self, prompt: &str) -> Option<()> {
+        let mut args = prompt.split_whitespace().map(|v| v.parse::<u16>().ok());
+        let (Some(Some(pad)), Some(Some(pulses)), Some(Some(len)), None) = (args.next(), args.next(), args.next(), args.next()) else {
+            return None;
+        };
+        let index = pad.checked_sub(1).filter(|v| *v < PAD_COUNT as u16)? as u8;
+        self.resize(len);
+        self.steps = [None; audio::MAX_PHRASE_LEN as usize];
+        for (step, pulse) in self.steps.iter_mut().zip(audio::euclid(pulses, self.len)) {
+            if pulse {
+                *step = Some(Step::Pad(index));
+            }
+        }
+        self.cursor = 0;
+        Some(())
+    }
+
+    fn resize(&mut self, len: u16) {
+        self.len = len.clamp(1, audio::MAX_PHRASE_LEN);
+        self.cursor = self.cursor.min(self.len - 1);
+    }
+
+    /// full-velocity holds on pad steps, releases on rests
+    fn phrase(&self) -> audio::Phrase {
+        let events = self.steps[..self.len as usize].iter().enumerate().filter_map(|(i, v)| {
+            let event = match (*v)? {
+                Step::Pad(index) => audio::Event::Hold { index, vel: 1. },
+                Step::Rest => audio::Event::Sync,
+            };
+            Some((i as u16, event))
+        });
+        audio::Phrase::new(events, self.len)
+    }
+}
+
+/// clickable cell
+#[derive(Copy, Clone, PartialEq)]
+enum Hit {
+    Pad(Bank, u8),
+    File(usize),
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct Pad {
     pub onsets: [bool; 2],
@@ -82,53 +247,102 @@ pub struct Scene {
 
 impl Scene {
     pub fn from_audio(scene: &crate::audio::pads::Scene<PAD_COUNT>) -> Self {
-        let kit_a = core::array::from_fn(|i| {
-            core::array::from_fn(|j| {
-                let pad = &scene.kit_a[i].inner[j];
-                Pad {
-                    onsets: [pad.onsets[0].is_some(), pad.onsets[1].is_some()],
-                    phrase: pad.phrase.is_some(),
-                }
-            })
-        });
-        let kit_b = core::array::from_fn(|i| {
-            core::array::from_fn(|j| {
-                let pad = &scene.kit_b[i].inner[j];
-                Pad {
-                    onsets: [pad.onsets[0].is_some(), pad.onsets[1].is_some()],
-                    phrase: pad.phrase.is_some(),
-                }
-            })
-        });
+        let kit_a = core::array::from_fn(|i| Self::kit_from_audio(&scene.kit_a[i]));
+        let kit_b = core::array::from_fn(|i| Self::kit_from_audio(&scene.kit_b[i]));
         Self { kit_a, kit_b }
     }
+
+    pub fn kit_from_audio(kit: &crate::audio::pads::Kit<PAD_COUNT>) -> [Pad; PAD_COUNT] {
+        core::array::from_fn(|i| {
+            let pad = &kit.inner[i];
+            Pad {
+                onsets: [pad.onsets[0].is_some(), pad.onsets[1].is_some()],
+                phrase: pad.phrase.is_some(),
+            }
+        })
+    }
 }
 
-#[derive(Default)]
 struct BankHandler {
     drift: u8,
+    phrase_drift: u8,
     bias: u8,
+    speed: u8,
+    width: u8,
+    reverse: bool,
+    hold: bool,
+    song: Option<(usize, usize, u16, u16)>,
+    /// steps until record captures, if counting in
+    count_in: Option<u16>,
+    playing: Option<(u8, bool)>,
+    /// meter level in [0, 1]
+    level: f32,
+    /// held meter level in [0, 1]
+    held: f32,
     pads: [Pad; PAD_COUNT],
     downs: Vec<u8>,
     pool: Vec<u8>,
     state: BankState,
 }
 
+impl Default for BankHandler {
+    fn default() -> Self {
+        // cc positions of audio defaults
+        Self {
+            drift: 0,
+            phrase_drift: 0,
+            bias: 0,
+            speed: 64,
+            width: 127,
+            reverse: false,
+            hold: false,
+            song: None,
+            count_in: None,
+            playing: None,
+            level: 0.,
+            held: 0.,
+            pads: Default::default(),
+            downs: Vec::new(),
+            pool: Vec::new(),
+            state: BankState::default(),
+        }
+    }
+}
+
 impl BankHandler {
     fn cmd(&mut self, kits: &mut [[Pad; PAD_COUNT]; PAD_COUNT], cmd: BankCmd) {
         match cmd {
             BankCmd::Pad(index, down) => self.pad(index, down),
             BankCmd::LoadOnset => self.load_onset(),
             BankCmd::AssignDrift(v) => self.drift = v,
+            BankCmd::AssignPhraseDrift(v) => self.phrase_drift = v,
             BankCmd::AssignBias(v) => self.bias = v,
+            BankCmd::AssignSpeed(v) => self.speed = v,
+            BankCmd::AssignWidth(v) => self.width = v,
+            BankCmd::Reverse(v) => self.reverse = v,
+            BankCmd::Hold(v) => self.hold = v,
             BankCmd::AssignKit(index) => self.assign_kit(kits, index),
             BankCmd::LoadKit(index) => self.load_kit(kits, index),
             BankCmd::BakeRecord(index, len) => self.state = BankState::BakeRecord { index, len },
             BankCmd::BuildPool => self.state = BankState::BuildPool,
             BankCmd::ClearPool => self.pool.clear(),
+            BankCmd::ClearPhrase(index) => {
+                self.pads[index as usize].phrase = false;
+                self.pool.retain(|v| *v != index);
+            }
+            BankCmd::AssignPhrase(index) => {
+                self.pads[index as usize].phrase = true;
+                self.pool = vec![index];
+            }
         }
     }
 
+    fn levels(&mut self, peak: f32) {
+        let db = 20. * peak.max(f32::MIN_POSITIVE).log10();
+        self.level = (1. - db / LEVEL_FLOOR).clamp(0., 1.);
+        self.held = self.level.max(self.held - LEVEL_DECAY);
+    }
+
     fn pad(&mut self, index: u8, down: bool) {
         if down {
             self.downs.push(index);
@@ -161,9 +375,9 @@ impl BankHandler {
         self.state = BankState::LoadKit { index };
     }
 
-    fn render(&self, kits: &[[Pad; PAD_COUNT]; PAD_COUNT], flex: Flex, area: Rect, buf: &mut Buffer) {
+    fn render(&self, bank: Bank, kits: &[[Pad; PAD_COUNT]; PAD_COUNT], flex: Flex, area: Rect, buf: &mut Buffer, hits: &mut Vec<(Rect, Hit)>) {
         match self.state {
-            BankState::LoadOnset => self.render_load_onset(flex, area, buf),
+            BankState::LoadOnset => self.render_load_onset(bank, flex, area, buf, hits),
             BankState::LoadKit { index } => self.render_load_kit(index, kits, flex, area, buf),
             BankState::AssignKit { index } => self.render_assign_kit(index, kits, flex, area, buf),
             BankState::BakeRecord { index, len } => self.render_bake_record(index, len, flex, area, buf),
@@ -171,34 +385,77 @@ impl BankHandler {
         }
     }
 
-    fn render_load_onset(&self, flex: Flex, area: Rect, buf: &mut Buffer) {
-        let [area] = Layout::horizontal(vec![Constraint::Max(14)])
+    fn render_load_onset(&self, bank: Bank, flex: Flex, area: Rect, buf: &mut Buffer, hits: &mut Vec<(Rect, Hit)>) {
+        let [area] = Layout::horizontal(vec![Constraint::Max(26)])
             .flex(flex)
             .areas(area);
-        let [pad_area, param_area] = Layout::vertical(Constraint::from_maxes([3, 3]))
+        let [pad_area, meter_area, param_area] = Layout::vertical(Constraint::from_maxes([3, 1, 4]))
             .flex(Flex::SpaceBetween)
             .areas(area);
-        // render border
-        Block::bordered().bold().render(area, buf);
-        // render pads
-        Paragraph::new(Text::raw(String::from_iter(core::array::from_fn::<_, PAD_COUNT, _>(|i| {
-            if self.downs.contains(&(i as u8)) {
-                'o'
+        // render border, flagging reverse and hold
+        let mut block = Block::bordered().bold();
+        if self.reverse {
+            block = block.title(Line::raw(" ◄ ").reversed());
+        }
+        if self.hold {
+            block = block.title(Line::raw(" hold ").right_aligned());
+        }
+        if let Some(rem) = self.count_in {
+            block = block.title(Line::raw(format!(" count-in {} ", rem)).centered().reversed());
+        }
+        if let Some((index, count, pass, repeats)) = self.song {
+            block = block.title_bottom(Line::raw(format!(" song {}/{} x{}/{} ", index + 1, count, pass + 1, repeats)).centered());
+        }
+        block.render(area, buf);
+        // render meter
+        let width = meter_area.width.saturating_sub(4) as usize;
+        let level = (self.level * width as f32) as usize;
+        let held = ((self.held * width as f32) as usize).min(width.saturating_sub(1));
+        Paragraph::new(Text::raw(String::from_iter((0..width).map(|i| {
+            if i < level {
+                '█'
+            } else if i == held && self.held > 0. {
+                '|'
             } else {
-                '.'
+                ' '
+            }
+        }))))
+        .block(Block::new().padding(Padding::horizontal(2)))
+        .render(meter_area, buf);
+        // render pads, marking sounding onset a/b
+        Paragraph::new(Text::raw(String::from_iter(core::array::from_fn::<_, PAD_COUNT, _>(|i| {
+            match self.playing {
+                Some((index, alt)) if index as usize == i => if alt { 'b' } else { 'a' },
+                _ if self.downs.contains(&(i as u8)) => 'o',
+                _ => '.',
             }
         }))))
         .block(Block::new().bold().padding(Padding::new(5, 5, 1, 0)))
         .wrap(Wrap { trim: false })
         .render(pad_area, buf);
+        // pads wrap within padding
+        let cols = pad_area.width.saturating_sub(10).max(1);
+        hits.extend((0..PAD_COUNT as u16).map(|i| {
+            (Rect::new(pad_area.x + 5 + i % cols, pad_area.y + 1 + i / cols, 1, 1), Hit::Pad(bank, i as u8))
+        }));
         // render params
+        let [left_area, right_area] = Layout::horizontal(Constraint::from_percentages([50, 50])).areas(param_area);
         Paragraph::new(Text::from(vec![
             Line::raw(format!("drift: {:>3}", self.drift)).italic(),
+            // phrase drift of pool sequence
+            Line::raw(format!("  seq: {:>3}", self.phrase_drift)).italic(),
             Line::raw(format!(" bias: {:>3}", self.bias)).italic(),
         ]))
-        .block(Block::new().bold().padding(Padding::new(2, 2, 0, 1)))
+        .block(Block::new().bold().padding(Padding::new(2, 0, 0, 1)))
         .left_aligned()
-        .render(param_area, buf);
+        .render(left_area, buf);
+        Paragraph::new(Text::from(vec![
+            Line::raw(format!("speed: {:>3}", self.speed)).italic(),
+            Line::raw(format!("width: {:>3}", self.width)).italic(),
+        ]))
+        .block(Block::new().bold().padding(Padding::new(0, 2, 0, 1)))
+        .right_aligned()
+        .render(right_area, buf);
     }
 
     fn render_load_kit(&self, index: Option<u8>, kits: &[[Pad; PAD_COUNT]; PAD_COUNT], flex: Flex, area: Rect, buf: &mut Buffer) {
@@ -264,7 +521,7 @@ impl BankHandler {
                 .render(pad_area, buf);
         }
         // render length
-        Paragraph::new(Text::raw(format!("length: {:>3}", len)).left_aligned())
+        Paragraph::new(Text::raw(musical_len(len)).centered())
             .block(Block::new().padding(Padding::new(2, 2, 0, 1)))
             .wrap(Wrap { trim: false})
             .render(len_area, buf);
@@ -300,11 +557,33 @@ impl BankHandler {
 
 #[derive(Default)]
 pub struct TuiHandler {
+    input: Option<Arc<Mutex<InputHandler>>>,
+    /// clickable cells of last render
+    hits: RefCell<Vec<(Rect, Hit)>>,
+    /// pads held by mouse
+    mouse_downs: Vec<(Bank, u8)>,
+
     exit: bool,
+    /// help overlay scroll, if open
+    help: Option<u16>,
     clock: bool,
+    tempo: Option<f32>,
+    /// scene slot last recalled, if any
+    slot: Option<u8>,
     scene: Scene,
 
     log: Option<(std::time::Instant, String)>,
+    /// errors since last clear, oldest first
+    errors: Vec<String>,
+    /// error pane scroll, if open
+    error_pane: Option<u16>,
+    /// browser search query, if typing
+    search: Option<String>,
+    /// scene name being typed, and whether confirming overwrite
+    naming: Option<(String, bool)>,
+    /// step grid, if open
+    grid: Option<Grid>,
+    peaks: Option<std::sync::Arc<[f32]>>,
 
     state: GlobalState,
     bank_a: BankHandler,
@@ -322,8 +601,14 @@ impl TuiHandler {
                     flush = true;
                 }
             }
+            if let GlobalState::Learn { since, .. } = &self.state {
+                if since.elapsed() >= LEARN_TIMEOUT {
+                    self.state = GlobalState::Yield;
+                    flush = true;
+                }
+            }
             if event::poll(std::time::Duration::ZERO)? {
-                self.kbd()?;
+                self.kbd(event::read()?)?;
                 flush = true;
             }
             match input_rx.try_recv() {
@@ -341,9 +626,189 @@ impl TuiHandler {
         Ok(())
     }
 
-    fn kbd(&mut self) -> Result<()> {
-        if let down!('q') = event::read()? {
-            self.exit = true;
+    pub fn new(input: Arc<Mutex<InputHandler>>) -> Self {
+        // saves wait on name prompt
+        input.lock().unwrap().assign_naming(true);
+        Self {
+            input: Some(input),
+            ..Default::default()
+        }
+    }
+
+    fn kbd(&mut self, event: event::Event) -> Result<()> {
+        if let Some((name, confirm)) = self.naming.as_mut() {
+            let save = match (event, *confirm) {
+                (down!(KeyCode::Char('y') | KeyCode::Enter), true) => Some(true),
+                (down!(KeyCode::Char('n') | KeyCode::Esc), true) => {
+                    *confirm = false;
+                    None
+                }
+                (down!(KeyCode::Char(c)), false) => {
+                    name.push(c);
+                    None
+                }
+                (down!(KeyCode::Backspace), false) => {
+                    name.pop();
+                    None
+                }
+                (down!(KeyCode::Enter), false) if !name.is_empty() => Some(false),
+                (down!(KeyCode::Esc), false) => {
+                    self.naming = None;
+                    None
+                }
+                _ => None,
+            };
+            if let (Some(overwrite), Some(input), Some((name, confirm))) = (save, &self.input, self.naming.as_mut()) {
+                if input.lock().unwrap().save_scene(name, overwrite)? {
+                    self.naming = None;
+                } else {
+                    // name taken
+                    *confirm = true;
+                }
+            }
+            return Ok(());
+        }
+        if let Some(query) = self.search.as_mut() {
+            match event {
+                down!(KeyCode::Char(c)) => query.push(c),
+                down!(KeyCode::Backspace) => {
+                    query.pop();
+                }
+                down!(KeyCode::Enter | KeyCode::Esc) => {
+                    self.search = None;
+                    return Ok(());
+                }
+                _ => return Ok(()),
+            }
+            if let Some(input) = &self.input {
+                input.lock().unwrap().search(query)?;
+            }
+            return Ok(());
+        }
+        if self.grid.is_some() && self.help.is_none() {
+            return self.grid_kbd(event);
+        }
+        let browsing = matches!(self.state, GlobalState::LoadScene { .. } | GlobalState::LoadWav { .. });
+        match (event, self.help) {
+            (down!('/'), None) if browsing => self.search = Some(String::new()),
+            (down!('g'), None) if matches!(self.state, GlobalState::Yield) => self.grid = Some(Grid::new(Bank::A)),
+            (down!('r'), None) if matches!(self.state, GlobalState::Yield) => if let Some(input) = &self.input {
+                input.lock().unwrap().rescan()?;
+            }
+            (down!('?'), None) => self.help = Some(0),
+            (down!('?') | down!(KeyCode::Esc), Some(_)) => self.help = None,
+            (down!(KeyCode::Up | KeyCode::Char('k')), Some(v)) => self.help = Some(v.saturating_sub(1)),
+            (down!(KeyCode::Down | KeyCode::Char('j')), Some(v)) => self.help = Some(v + 1),
+            (down!('q'), _) => self.exit = true,
+            (down!('e'), None) => self.error_pane = match self.error_pane {
+                Some(_) => None,
+                None => Some(0),
+            },
+            (down!('c'), None) if self.error_pane.is_some() => {
+                self.errors.clear();
+                self.error_pane = Some(0);
+            }
+            (down!(KeyCode::Up | KeyCode::Char('k')), None) if self.error_pane.is_some() => {
+                self.error_pane = self.error_pane.map(|v| v.saturating_sub(1));
+            }
+            (down!(KeyCode::Down | KeyCode::Char('j')), None) if self.error_pane.is_some() => {
+                self.error_pane = self.error_pane.map(|v| (v + 1).min(self.errors.len().saturating_sub(1) as u16));
+            }
+            (event::Event::Mouse(mouse), None) => self.mouse(mouse)?,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn grid_kbd(&mut self, event: event::Event) -> Result<()> {
+        let Some(grid) = self.grid.as_mut() else {
+            return Ok(());
+        };
+        let pad = |c: char| c.to_digit(10).filter(|v| (1..=PAD_COUNT as u32).contains(v)).map(|v| v as u8 - 1);
+        if let Some(prompt) = grid.prompt.as_mut() {
+            match event {
+                down!(KeyCode::Char(c)) => prompt.push(c),
+                down!(KeyCode::Backspace) => {
+                    prompt.pop();
+                }
+                down!(KeyCode::Enter) => {
+                    let prompt = grid.prompt.take().unwrap_or_default();
+                    if grid.euclid(&prompt).is_none() {
+                        self.log = Some((std::time::Instant::now(), "usage: pad pulses steps, e.g. 1 3 8".to_string()));
+                    }
+                }
+                down!(KeyCode::Esc) => grid.prompt = None,
+                _ => (),
+            }
+            return Ok(());
+        }
+        if grid.assign {
+            match event {
+                down!(KeyCode::Char(c)) if pad(c).is_some() => {
+                    let index = pad(c).unwrap();
+                    if let Some(input) = &self.input {
+                        input.lock().unwrap().assign_phrase(grid.bank, index, grid.phrase())?;
+                    }
+                    self.grid = None;
+                }
+                down!(KeyCode::Esc) => grid.assign = false,
+                _ => (),
+            }
+            return Ok(());
+        }
+        match event {
+            down!(KeyCode::Char(c)) if pad(c).is_some() => grid.toggle(Step::Pad(pad(c).unwrap())),
+            down!('.') => grid.toggle(Step::Rest),
+            down!(KeyCode::Backspace) => grid.steps[grid.cursor as usize] = None,
+            down!(KeyCode::Left | KeyCode::Char('h')) => grid.cursor = (grid.cursor + grid.len - 1) % grid.len,
+            down!(KeyCode::Right | KeyCode::Char('l')) => grid.cursor = (grid.cursor + 1) % grid.len,
+            down!(KeyCode::Up | KeyCode::Char('k')) => grid.cursor = grid.cursor.saturating_sub(GRID_ROW),
+            down!(KeyCode::Down | KeyCode::Char('j')) => grid.cursor = (grid.cursor + GRID_ROW).min(grid.len - 1),
+            down!('[') => grid.resize(grid.len - 1),
+            down!(']') => grid.resize(grid.len + 1),
+            down!('{') => grid.resize(grid.len.saturating_sub(audio::STEP_DIV as u16)),
+            down!('}') => grid.resize(grid.len + audio::STEP_DIV as u16),
+            down!(KeyCode::Tab) => grid.bank = match grid.bank {
+                Bank::A => Bank::B,
+                Bank::B => Bank::A,
+            },
+            down!(KeyCode::Enter) => grid.assign = true,
+            down!('e') => grid.prompt = Some(String::new()),
+            down!(KeyCode::Esc) => self.grid = None,
+            down!('?') => self.help = Some(0),
+            down!('q') => self.exit = true,
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        let Some(input) = self.input.clone() else {
+            return Ok(());
+        };
+        let mut input = input.lock().unwrap();
+        let hit = self.hits.borrow().iter().find(|(rect, _)| {
+            rect.contains(ratatui::layout::Position::new(mouse.column, mouse.row))
+        }).map(|(_, hit)| *hit);
+        match (mouse.kind, hit) {
+            (MouseEventKind::Down(MouseButton::Left), Some(Hit::Pad(bank, index))) => {
+                self.mouse_downs.push((bank, index));
+                input.push_pad(bank, index, true)?;
+            }
+            // drag across pads of same bank for loop length
+            (MouseEventKind::Drag(MouseButton::Left), Some(Hit::Pad(bank, index)))
+                if self.mouse_downs.first().is_some_and(|v| v.0 == bank) && !self.mouse_downs.contains(&(bank, index)) =>
+            {
+                self.mouse_downs.push((bank, index));
+                input.push_pad(bank, index, true)?;
+            }
+            (MouseEventKind::Down(MouseButton::Left), Some(Hit::File(row))) => input.push_file(row)?,
+            (MouseEventKind::Up(MouseButton::Left), _) => {
+                for (bank, index) in self.mouse_downs.drain(..).rev() {
+                    input.push_pad(bank, index, false)?;
+                }
+            }
+            _ => (),
         }
         Ok(())
     }
@@ -351,17 +816,47 @@ impl TuiHandler {
     fn cmd(&mut self, cmd: Cmd) {
         match cmd {
             Cmd::Clock => self.clock = !self.clock,
-            Cmd::Stop => self.clock = false,
+            Cmd::Stop => {
+                self.clock = false;
+                self.tempo = None;
+            }
+            Cmd::Tempo(v) => self.tempo = Some(v),
+            Cmd::AssignSlot(v) => self.slot = v,
+            Cmd::Levels(a, b) => {
+                self.bank_a.levels(a);
+                self.bank_b.levels(b);
+            }
+            Cmd::Playing(Bank::A, v) => self.bank_a.playing = v,
+            Cmd::Playing(Bank::B, v) => self.bank_b.playing = v,
+            Cmd::Song(Bank::A, v) => self.bank_a.song = v,
+            Cmd::Song(Bank::B, v) => self.bank_b.song = v,
+            Cmd::CountIn(Bank::A, v) => self.bank_a.count_in = v,
+            Cmd::CountIn(Bank::B, v) => self.bank_b.count_in = v,
             Cmd::Yield => {
                 self.state = GlobalState::Yield;
+                self.search = None;
                 self.bank_a.state = BankState::LoadOnset;
                 self.bank_b.state = BankState::LoadOnset;
             }
             Cmd::AssignScene(scene) => self.scene = *scene,
+            Cmd::CopyKit(Bank::A, index) => self.scene.kit_b[index as usize] = self.scene.kit_a[index as usize],
+            Cmd::CopyKit(Bank::B, index) => self.scene.kit_a[index as usize] = self.scene.kit_b[index as usize],
+            Cmd::StoreKit(Bank::A, index, kit) => self.scene.kit_a[index as usize] = kit,
+            Cmd::StoreKit(Bank::B, index, kit) => self.scene.kit_b[index as usize] = kit,
+            Cmd::SwapBanks => {
+                // held pads stay with their bank
+                std::mem::swap(&mut self.bank_a, &mut self.bank_b);
+                std::mem::swap(&mut self.bank_a.downs, &mut self.bank_b.downs);
+            }
+            Cmd::NameScene(name) => self.naming = Some((name, false)),
             Cmd::SaveScene(path) => self.log = Some((std::time::Instant::now(), format!("saved scene to `{}`!", path))),
-            Cmd::LoadScene(paths) => self.state = GlobalState::LoadScene { paths },
-            Cmd::LoadWav(paths) => self.state = GlobalState::LoadWav { paths },
-            Cmd::AssignOnset { name, index, count, alt } => self.state = GlobalState::AssignOnset { name, index, count, alt },
+            Cmd::Log(msg) => self.log = Some((std::time::Instant::now(), msg)),
+            Cmd::Error(msg) => self.errors.push(msg),
+            Cmd::LoadScene(listing) => self.state = GlobalState::LoadScene { listing },
+            Cmd::LoadWav(listing) => self.state = GlobalState::LoadWav { listing },
+            Cmd::AssignOnset { name, index, count, alt, pos } => self.state = GlobalState::AssignOnset { name, index, count, alt, pos },
+            Cmd::AssignPeaks(peaks) => self.peaks = peaks,
+            Cmd::Learn(name) => self.state = GlobalState::Learn { name, since: std::time::Instant::now() },
             Cmd::Bank(bank, cmd) => {
                 if let BankCmd::Pad(index, true) = cmd {
                     if let GlobalState::AssignOnset { alt, .. } = self.state {
@@ -385,21 +880,51 @@ impl TuiHandler {
     }
 
     fn render_clock(&self, area: Rect, buf: &mut Buffer) {
-        let [left, right] = Layout::horizontal(Constraint::from_maxes([11, 11])).flex(Flex::Center).areas(area);
+        let [left, tempo, right] = Layout::horizontal(Constraint::from_maxes([11, 18, 11])).flex(Flex::Center).areas(area);
         if self.clock {
             Block::new().reversed().render(right, buf);
         } else {
             Block::new().reversed().render(left, buf);
         }
+        let mut text = match self.tempo {
+            Some(v) => format!("bpm: {:.1}", v),
+            None => "bpm: --".to_string(),
+        };
+        if let Some(slot) = self.slot {
+            text = format!("#{} {}", slot + 1, text);
+        }
+        Paragraph::new(Text::raw(text)).centered().render(tempo, buf);
     }
 
     fn render_log(&self, area: Rect, buf: &mut Buffer) {
-        if let Some((_, msg)) = &self.log {
+        if let Some(query) = &self.search {
+            Paragraph::new(Text::raw(format!("/{}", query))).centered().render(area, buf);
+        } else if let Some((name, confirm)) = &self.naming {
+            let msg = if *confirm {
+                format!("overwrite `{}`? y/n", name)
+            } else {
+                format!("save scene as: {}", name)
+            };
+            Paragraph::new(Text::raw(msg).bold()).centered().render(area, buf);
+        } else if let Some((_, msg)) = &self.log {
             Paragraph::new(Text::raw(msg)).centered().render(area, buf);
+        } else if !self.errors.is_empty() && self.error_pane.is_none() {
+            let msg = format!("{} error{}, e: view", self.errors.len(), if self.errors.len() == 1 { "" } else { "s" });
+            Paragraph::new(Text::raw(msg).red()).centered().render(area, buf);
         }
     }
 
-    fn render_load_scene(&self, paths: &[String; FILE_COUNT], area: Rect, buf: &mut Buffer) {
+    fn render_errors(&self, scroll: u16, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let lines = self.errors.iter().map(|v| Line::raw(v.as_str())).collect::<Vec<_>>();
+        Paragraph::new(Text::from(lines))
+            .scroll((scroll, 0))
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().bold().title(format!(" errors ({}): ", self.errors.len())).title_bottom(" e: close, c: clear, j/k: scroll ").padding(Padding::horizontal(1)))
+            .render(area, buf);
+    }
+
+    fn render_load_scene(&self, listing: &Listing, area: Rect, buf: &mut Buffer, hits: &mut Vec<(Rect, Hit)>) {
         let [pad_area, fs_area] = Layout::horizontal(vec![Constraint::Min(8), Constraint::Percentage(100)]).areas(area);
         let [_, arrow_area] = Layout::horizontal(Constraint::from_maxes([7, 2])).flex(Flex::Start).areas(area);
         let [a_area, b_area] = Layout::vertical(Constraint::from_maxes([3, 3])).flex(Flex::SpaceBetween).areas(pad_area);
@@ -433,8 +958,8 @@ impl TuiHandler {
         .render(b_area, buf);
         {
             // render fs
-            let text = if paths.iter().any(|v| !v.is_empty()) {
-                let mut lines = paths.clone().map(Line::raw).to_vec();
+            let text = if listing.names.iter().any(|v| !v.is_empty()) {
+                let mut lines = listing.names.clone().map(Line::raw).to_vec();
                 let mid = lines.len() / 2;
                 lines[mid] = lines[mid].clone().reversed();
                 Text::from(lines.to_vec())
@@ -443,8 +968,11 @@ impl TuiHandler {
             };
             Paragraph::new(text)
                 .left_aligned()
-                .block(Block::bordered().title(" load scene: ").padding(Padding::horizontal(1)))
+                .block(Block::bordered().title(" load scene: ").title(listing.position().right_aligned()).padding(Padding::horizontal(1)))
                 .render(fs_area, buf);
+            hits.extend((0..FILE_COUNT as u16).map(|i| {
+                (Rect::new(fs_area.x + 1, fs_area.y + 1 + i, fs_area.width.saturating_sub(2), 1), Hit::File(i as usize))
+            }));
         }
         // render arrow
         Paragraph::new(Text::raw("<<"))
@@ -452,7 +980,7 @@ impl TuiHandler {
             .render(arrow_area, buf);
     }
 
-    fn render_load_wav(&self, paths: &[String; FILE_COUNT], area: Rect, buf: &mut Buffer) {
+    fn render_load_wav(&self, listing: &Listing, area: Rect, buf: &mut Buffer, hits: &mut Vec<(Rect, Hit)>) {
         let [pad_area, fs_area] = Layout::horizontal(vec![Constraint::Min(8), Constraint::Percentage(100)]).areas(area);
         let [a_area, b_area] = Layout::vertical(Constraint::from_maxes([3, 3])).flex(Flex::SpaceBetween).areas(pad_area);
         // render border
@@ -499,8 +1027,8 @@ impl TuiHandler {
         .render(b_area, buf);
         {
             // render fs
-            let text = if paths.iter().any(|v| !v.is_empty()) {
-                let mut lines = paths.clone().map(Line::raw).to_vec();
+            let text = if listing.names.iter().any(|v| !v.is_empty()) {
+                let mut lines = listing.names.clone().map(Line::raw).to_vec();
                 let mid = lines.len() / 2;
                 lines[mid] = lines[mid].clone().reversed();
                 Text::from(lines.to_vec())
@@ -509,12 +1037,16 @@ impl TuiHandler {
             };
             Paragraph::new(text)
                 .left_aligned()
-                .block(Block::bordered().title(" load wav: ").padding(Padding::horizontal(1)))
+                .block(Block::bordered().title(" load wav: ").title(listing.position().right_aligned()).padding(Padding::horizontal(1)))
                 .render(fs_area, buf);
+            hits.extend((0..FILE_COUNT as u16).map(|i| {
+                (Rect::new(fs_area.x + 1, fs_area.y + 1 + i, fs_area.width.saturating_sub(2), 1), Hit::File(i as usize))
+            }));
         }
     }
 
-    fn render_assign_onset(&self, name: &String, index: usize, count: usize, alt: bool, area: Rect, buf: &mut Buffer) {
+    #[allow(clippy::too_many_arguments)]
+    fn render_assign_onset(&self, name: &String, index: usize, count: usize, alt: bool, pos: f32, area: Rect, buf: &mut Buffer) {
         let [pad_area, onset_area] = Layout::horizontal(vec![Constraint::Min(8), Constraint::Percentage(100)]).areas(area);
         let [_, arrow_area] = Layout::horizontal(Constraint::from_maxes([7, 2])).flex(Flex::Start).areas(area);
         let [a_area, b_area] = Layout::vertical(Constraint::from_maxes([3, 3])).flex(Flex::SpaceBetween).areas(pad_area);
@@ -559,6 +1091,7 @@ impl TuiHandler {
         {
             // render onset
             let mut lines: [Line; FILE_COUNT] = core::array::from_fn(|_| Line::raw(""));
+            lines[FILE_COUNT / 2 - 1] = self.waveform(pos, onset_area.width.saturating_sub(4) as usize);
             lines[FILE_COUNT / 2] = Line::raw(name).reversed();
             let to = if alt { 'b' } else { 'a' };
             lines[FILE_COUNT - 1] = Line::raw(format!("{:>3}/{:>3} to {}", index, count, to));
@@ -572,10 +1105,104 @@ impl TuiHandler {
             .block(Block::new().padding(Padding::new(0, 0, FILE_COUNT as u16, 0)))
             .render(arrow_area, buf);
     }
+
+    /// peaks around onset at `pos` into file, onset highlighted
+    fn waveform(&self, pos: f32, width: usize) -> Line<'_> {
+        const LEVELS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let Some(peaks) = self.peaks.as_ref().filter(|v| !v.is_empty()) else {
+            return Line::raw("no waveform </3").italic();
+        };
+        let mark = ((pos * peaks.len() as f32) as usize).min(peaks.len() - 1);
+        let start = mark.saturating_sub(width / 2);
+        let level = |i: usize| LEVELS[(peaks[i].clamp(0., 1.) * (LEVELS.len() - 1) as f32).round() as usize];
+        Line::from_iter((start..(start + width).min(peaks.len())).map(|i| {
+            if i == mark {
+                Span::raw(level(i).to_string()).reversed()
+            } else {
+                Span::raw(level(i).to_string())
+            }
+        }))
+    }
+
+    fn render_help(&self, scroll: u16, area: Rect, buf: &mut Buffer) {
+        // dim main view behind overlay
+        buf.set_style(area, Style::new().dim());
+        let [area] = Layout::horizontal(vec![Constraint::Max(80)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::vertical(vec![Constraint::Percentage(80)]).flex(Flex::Center).areas(area);
+        Clear.render(area, buf);
+        Paragraph::new(Text::raw(crate::input::CHORDS))
+            .scroll((scroll, 0))
+            .block(Block::bordered().bold().title(" help: ").title_bottom(" ?/esc: close, j/k: scroll ").padding(Padding::horizontal(1)))
+            .render(area, buf);
+    }
+
+    fn render_grid(&self, grid: &Grid, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, Style::new().dim());
+        let rows = grid.len.div_ceil(GRID_ROW);
+        let [area] = Layout::horizontal(vec![Constraint::Max(64)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::vertical(vec![Constraint::Max(rows + 2)]).flex(Flex::Center).areas(area);
+        Clear.render(area, buf);
+        let lines = (0..rows).map(|row| Line::from_iter((row * GRID_ROW..((row + 1) * GRID_ROW).min(grid.len)).flat_map(|i| {
+            let cell = match grid.steps[i as usize] {
+                Some(Step::Pad(index)) => (b'1' + index) as char,
+                Some(Step::Rest) => '-',
+                // mark beats
+                None if i % audio::STEP_DIV as u16 == 0 => ':',
+                None => '.',
+            };
+            let span = Span::raw(cell.to_string());
+            let span = if i == grid.cursor { span.reversed() } else { span };
+            [span, Span::raw(" ")]
+        }))).collect::<Vec<_>>();
+        let bank = match grid.bank {
+            Bank::A => "a",
+            Bank::B => "b",
+        };
+        let hint = if let Some(prompt) = &grid.prompt {
+            format!(" pad pulses steps: {} ", prompt)
+        } else if grid.assign {
+            format!(" 1-{}: assign to pad, esc: back ", PAD_COUNT)
+        } else {
+            format!(" 1-{}: pad, .: rest, [/]: len, tab: bank, e: euclid, enter: assign ", PAD_COUNT)
+        };
+        Paragraph::new(Text::from(lines))
+            .block(Block::bordered().bold().title(format!(" step grid {}: {} steps ", bank, grid.len)).title_bottom(hint).padding(Padding::horizontal(1)))
+            .render(area, buf);
+    }
+
+    fn render_learn(&self, name: &String, area: Rect, buf: &mut Buffer) {
+        let [area] = Layout::horizontal(vec![Constraint::Max(40)]).flex(Flex::Center).areas(area);
+        let [area] = Layout::vertical(vec![Constraint::Max(4)]).flex(Flex::Center).areas(area);
+        Paragraph::new(Text::from(vec![
+            Line::raw(format!("move a control for {}", name)),
+            Line::raw("Global: cancel").italic(),
+        ]))
+        .centered()
+        .block(Block::bordered().bold().title(" learn: "))
+        .render(area, buf);
+    }
 }
 
 impl Widget for &TuiHandler {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let full = area;
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            // layouts below collapse to empty rects
+            self.hits.borrow_mut().clear();
+            let [area] = Layout::vertical(vec![Constraint::Length(1)]).flex(Flex::Center).areas(area);
+            Paragraph::new(Text::raw(format!("terminal too small; need {}x{}", MIN_WIDTH, MIN_HEIGHT)))
+                .centered()
+                .render(area, buf);
+            return;
+        }
+        // reserve bottom rows for error pane, else overlay it
+        let [area, error_area] = match self.error_pane {
+            Some(_) if area.height >= MIN_HEIGHT + ERROR_PANE_LEN => {
+                Layout::vertical(vec![Constraint::Fill(1), Constraint::Length(ERROR_PANE_LEN)]).areas(area)
+            }
+            Some(_) => [area, area],
+            None => [area, Rect::default()],
+        };
         let [area] = Layout::vertical(vec![Constraint::Max(FILE_COUNT as u16 + 5)])
             .flex(Flex::Center)
             .areas(area);
@@ -584,15 +1211,194 @@ impl Widget for &TuiHandler {
             .areas(area);
         self.render_clock(clock_area, buf);
         self.render_log(log_area, buf);
+        let mut hits = self.hits.borrow_mut();
+        hits.clear();
         match &self.state {
             GlobalState::Yield => {
                 let [a_area, b_area] = Layout::horizontal(Constraint::from_percentages([50, 50])).flex(Flex::Center).areas(area);
-                self.bank_a.render(&self.scene.kit_a, Flex::End, a_area, buf);
-                self.bank_b.render(&self.scene.kit_b, Flex::Start, b_area, buf);
+                self.bank_a.render(Bank::A, &self.scene.kit_a, Flex::End, a_area, buf, &mut hits);
+                self.bank_b.render(Bank::B, &self.scene.kit_b, Flex::Start, b_area, buf, &mut hits);
             }
-            GlobalState::LoadScene { paths } => self.render_load_scene(paths, area, buf),
-            GlobalState::LoadWav { paths } => self.render_load_wav(paths, area, buf),
-            GlobalState::AssignOnset { name, index, count, alt } => self.render_assign_onset(name, *index, *count, *alt, area, buf)
+            GlobalState::LoadScene { listing } => self.render_load_scene(listing, area, buf, &mut hits),
+            GlobalState::LoadWav { listing } => self.render_load_wav(listing, area, buf, &mut hits),
+            GlobalState::AssignOnset { name, index, count, alt, pos } => self.render_assign_onset(name, *index, *count, *alt, *pos, area, buf),
+            GlobalState::Learn { name, .. } => self.render_learn(name, area, buf),
+        }
+        if let Some(scroll) = self.error_pane {
+            self.render_errors(scroll, error_area, buf);
+        }
+        if let Some(grid) = &self.grid {
+            hits.clear();
+            self.render_grid(grid, full, buf);
         }
+        if let Some(scroll) = self.help {
+            hits.clear();
+            self.render_help(scroll, full, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn musical_len_prefers_bars_then_beats() {
+        assert_eq!(musical_len(16), "1 bar");
+        assert_eq!(musical_len(64), "4 bars");
+        assert_eq!(musical_len(4), "1 beat");
+        assert_eq!(musical_len(24), "6 beats");
+        assert_eq!(musical_len(1), "1 step");
+        assert_eq!(musical_len(7), "7 steps");
+        assert_eq!(musical_len(0), "0 steps");
+    }
+
+    #[test]
+    fn grid_steps_stamped_in_order_within_len() {
+        let mut grid = Grid::new(Bank::A);
+        grid.toggle(Step::Pad(2));
+        grid.toggle(Step::Rest);
+        grid.toggle(Step::Pad(0));
+        // toggled off again, leaving step 2 sustaining
+        grid.cursor = 2;
+        grid.toggle(Step::Pad(0));
+        grid.cursor = 5;
+        grid.toggle(Step::Pad(1));
+        grid.resize(4);
+        // past len, so dropped
+        grid.steps[4] = Some(Step::Pad(3));
+        let phrase = serde_json::to_value(grid.phrase()).unwrap();
+        assert_eq!(phrase, serde_json::json!({
+            "events": [
+                { "event": { "Hold": { "index": 2, "vel": 1.0 } }, "step": 0, "prob": 1.0, "condition": null },
+                { "event": "Sync", "step": 1, "prob": 1.0, "condition": null },
+            ],
+            "len": 4,
+        }));
+    }
+
+    #[test]
+    fn cleared_phrase_leaves_pool_marker() {
+        let mut bank = BankHandler::default();
+        let mut kits = Default::default();
+        // pads wrapped within border and padding
+        let markers = |bank: &BankHandler| {
+            let area = Rect::new(0, 0, 40, 4);
+            let mut buf = Buffer::empty(area);
+            bank.render_pool(area, &mut buf);
+            (1..3).flat_map(|y| (2..6).map(move |x| (x, y))).map(|v| buf[v].symbol().to_string()).collect::<String>()
+        };
+        bank.cmd(&mut kits, BankCmd::AssignPhrase(2));
+        bank.cmd(&mut kits, BankCmd::AssignPhrase(5));
+        bank.pool = vec![2, 5, 2];
+        assert_eq!(markers(&bank), "..p..p..");
+        bank.cmd(&mut kits, BankCmd::ClearPhrase(2));
+        assert_eq!(markers(&bank), ".....p..");
+        assert_eq!(bank.pool, [5]);
+    }
+
+    #[test]
+    fn errors_accumulate_until_cleared() {
+        let mut tui = TuiHandler::default();
+        let key = |c| event::Event::Key(event::KeyEvent::new(KeyCode::Char(c), event::KeyModifiers::NONE));
+        tui.cmd(Cmd::Error("bank a silenced".to_string()));
+        tui.cmd(Cmd::Log("saved".to_string()));
+        tui.cmd(Cmd::Error("no onsets".to_string()));
+        assert_eq!(tui.errors, ["bank a silenced", "no onsets"]);
+        // clear only once pane open
+        tui.kbd(key('c')).unwrap();
+        assert_eq!(tui.errors.len(), 2);
+        tui.kbd(key('e')).unwrap();
+        tui.kbd(key('j')).unwrap();
+        assert_eq!(tui.error_pane, Some(1));
+        tui.kbd(key('c')).unwrap();
+        assert!(tui.errors.is_empty());
+        assert_eq!(tui.error_pane, Some(0));
+        tui.kbd(key('e')).unwrap();
+        assert_eq!(tui.error_pane, None);
+    }
+
+    #[test]
+    fn small_terminal_renders_notice() {
+        let mut tui = TuiHandler::default();
+        let text = |buf: &Buffer| buf.content.iter().map(|v| v.symbol()).collect::<String>();
+        // notice in place of collapsed layout, under every overlay
+        for (error_pane, help) in [(None, None), (Some(0), None), (None, Some(0))] {
+            tui.error_pane = error_pane;
+            tui.help = help;
+            let mut buf = Buffer::empty(Rect::new(0, 0, 10, 5));
+            tui.render(buf.area, &mut buf);
+            assert!(text(&buf).contains("terminal"));
+            assert!(tui.hits.borrow().is_empty());
+        }
+        // laid out from minimum size up
+        let mut buf = Buffer::empty(Rect::new(0, 0, MIN_WIDTH, MIN_HEIGHT));
+        tui.render(buf.area, &mut buf);
+        assert!(!text(&buf).contains("terminal"));
+    }
+
+    #[test]
+    fn speed_cc_updates_displayed_speed() {
+        let (tui_tx, tui_rx) = std::sync::mpsc::channel();
+        let (pads_tx, _pads_rx) = audio::cmd_queue::<PAD_COUNT>();
+        let mut input = InputHandler::new(tui_tx, pads_tx).unwrap();
+        let mut tui = TuiHandler::default();
+        let text = |tui: &mut TuiHandler| {
+            tui_rx.try_iter().for_each(|v| tui.cmd(v));
+            let mut buf = Buffer::empty(Rect::new(0, 0, 80, 20));
+            (&*tui).render(buf.area, &mut buf);
+            buf.content.iter().map(|v| v.symbol()).collect::<String>()
+        };
+        assert!(text(&mut tui).contains("speed:  64"));
+        // speed a cc
+        input.push(&[0xb0, 105, 32]).unwrap();
+        let shown = text(&mut tui);
+        assert!(shown.contains("speed:  32") && shown.contains("speed:  64"));
+    }
+
+    #[test]
+    fn reverse_and_hold_flags_follow_keys() {
+        let (tui_tx, tui_rx) = std::sync::mpsc::channel();
+        let (pads_tx, _pads_rx) = audio::cmd_queue::<PAD_COUNT>();
+        let mut input = InputHandler::new(tui_tx, pads_tx).unwrap();
+        let mut tui = TuiHandler::default();
+        let flags = |tui: &mut TuiHandler| {
+            tui_rx.try_iter().for_each(|v| tui.cmd(v));
+            let mut buf = Buffer::empty(Rect::new(0, 0, 80, 20));
+            (&*tui).render(buf.area, &mut buf);
+            let text = buf.content.iter().map(|v| v.symbol()).collect::<String>();
+            (text.contains('◄'), text.contains(" hold "))
+        };
+        assert_eq!(flags(&mut tui), (false, false));
+        // reverse a held
+        input.push(&[0x90, 57, 100]).unwrap();
+        assert_eq!(flags(&mut tui), (true, false));
+        input.push(&[0x80, 57, 0]).unwrap();
+        assert_eq!(flags(&mut tui), (false, false));
+        // hold a toggled per press
+        input.push(&[0x90, 59, 100]).unwrap();
+        input.push(&[0x80, 59, 0]).unwrap();
+        assert_eq!(flags(&mut tui), (false, true));
+        input.push(&[0x90, 59, 100]).unwrap();
+        input.push(&[0x80, 59, 0]).unwrap();
+        assert_eq!(flags(&mut tui), (false, false));
+    }
+
+    #[test]
+    fn euclid_fill_bounded_by_phrase_len() {
+        let mut grid = Grid::new(Bank::A);
+        grid.euclid("2 3 8").unwrap();
+        let pads = grid.steps.iter().map(|v| if *v == Some(Step::Pad(1)) { 'x' } else { '.' }).collect::<String>();
+        assert_eq!(&pads[..8], "x..x..x.");
+        assert!(pads[8..].chars().all(|v| v == '.'));
+        // longer fills clamped to longest phrase
+        grid.euclid("1 5 1000").unwrap();
+        assert_eq!(grid.len, audio::MAX_PHRASE_LEN);
+        let phrase = serde_json::to_value(grid.phrase()).unwrap();
+        assert_eq!(phrase["len"], audio::MAX_PHRASE_LEN);
+        assert_eq!(phrase["events"].as_array().unwrap().len(), 5);
+        // unparsed or out of range pad leaves grid
+        assert!(grid.euclid("0 3 8").is_none() && grid.euclid("1 3").is_none());
+        assert_eq!(grid.len, audio::MAX_PHRASE_LEN);
     }
 }
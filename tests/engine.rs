@@ -0,0 +1,62 @@
+//! engine driven through the library alone, with no midi port or audio device
+
+use pc_computer_doctor::{audio, AudioHandler, Bank, BankCmd, Cmd, InputHandler, PAD_COUNT};
+
+const RATE: u32 = 44100;
+
+/// 16 bit mono wav of a constant word
+fn image(word: i16, words: usize) -> Vec<u8> {
+    let pcm: Vec<u8> = std::iter::repeat_n(word, words).flat_map(i16::to_le_bytes).collect();
+    let mut wav = b"RIFF".to_vec();
+    wav.extend((36 + pcm.len() as u32).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    wav.extend(16u32.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(RATE.to_le_bytes());
+    wav.extend((RATE * 2).to_le_bytes());
+    wav.extend(2u16.to_le_bytes());
+    wav.extend(16u16.to_le_bytes());
+    wav.extend(b"data");
+    wav.extend((pcm.len() as u32).to_le_bytes());
+    wav.extend(pcm);
+    wav
+}
+
+#[test]
+fn cmds_render_buffers() {
+    let (mut tx, rx) = audio::cmd_queue::<PAD_COUNT>();
+    let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+    let mut handler = AudioHandler::<PAD_COUNT>::new(rx, tui_tx, None, RATE);
+    let data: std::sync::Arc<[u8]> = image(8192, 4096).into();
+    let wav = audio::Wav {
+        tempo: None,
+        steps: None,
+        path: std::path::Path::new("engine.wav").into(),
+        len: data.len() as u64 - 44,
+        data: Some(data),
+        stream: None,
+    };
+    tx.send(Cmd::AssignTempo(120.)).unwrap();
+    tx.send(Cmd::Bank(Bank::A, BankCmd::AssignOnset(0, false, Box::new(audio::Onset { wav, start: 0 })))).unwrap();
+    tx.send(Cmd::Bank(Bank::A, BankCmd::ForceEvent(audio::Event::Hold { index: 0, vel: 1. }))).unwrap();
+    let mut buffer = vec![0f32; 512];
+    handler.tick(&mut buffer, 2);
+    assert!(buffer.iter().any(|v| *v != 0.));
+    // panic silences
+    tx.send(Cmd::Panic).unwrap();
+    handler.tick(&mut buffer, 2);
+    handler.tick(&mut buffer, 2);
+    assert!(buffer.iter().all(|v| *v == 0.));
+}
+
+#[test]
+fn midi_bytes_become_cmds() {
+    let (tui_tx, _tui_rx) = std::sync::mpsc::channel();
+    let (pads_tx, mut pads_rx) = audio::cmd_queue::<PAD_COUNT>();
+    let mut input = InputHandler::new(tui_tx, pads_tx).unwrap();
+    while pads_rx.try_recv().is_some() {}
+    // first pad of bank a
+    input.push(&[0x90, 48, 100]).unwrap();
+    assert!(std::iter::from_fn(|| pads_rx.try_recv()).any(|v| matches!(v, Cmd::Bank(Bank::A, _))));
+}